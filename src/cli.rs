@@ -1,14 +1,22 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use uuid::Uuid;
 
-use crate::models::Action;
+use crate::models::{
+    Action, ConflictPolicy, DedupMode, EntryType, GroupBy, ISO_TIME_FORMAT, ListOptions, Operation,
+    Ownership, PasteOptions, PasteSort, ReorderPosition, TransferOptions, validate_time_format,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, propagate_version = true)]
 pub struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Answer yes to every prompt, overwriting existing files unless --conflict says otherwise
+    #[arg(long, short, global = true)]
+    yes: bool,
 }
 
 #[derive(Subcommand)]
@@ -19,6 +27,46 @@ enum Commands {
     #[command(alias = "y")]
     Copy {
         paths: Vec<PathBuf>,
+
+        #[command(flatten)]
+        transfer: TransferArgs,
+
+        /// Stage the content read from stdin as a new file
+        #[arg(long, requires = "name", conflicts_with = "paths")]
+        stdin_content: bool,
+
+        /// File name to paste the content staged with --stdin-content as
+        #[arg(long, requires = "stdin_content")]
+        name: Option<String>,
+
+        /// Stage the targets of symlinks instead of the symlinks themselves
+        #[arg(long)]
+        follow: bool,
+
+        /// Keep watching the given directories or globs and stage new files until interrupted
+        #[arg(long, conflicts_with = "stdin_content")]
+        watch: bool,
+
+        /// Stage a copy of each file's current content so later edits do not affect the paste
+        #[arg(long, conflicts_with = "stdin_content")]
+        snapshot: bool,
+
+        /// Skip hidden files and directories when pasting staged directories
+        #[arg(long, overrides_with = "include_hidden")]
+        exclude_hidden: bool,
+
+        /// Keep hidden files and directories when pasting staged directories (default)
+        #[arg(long, overrides_with = "exclude_hidden")]
+        include_hidden: bool,
+
+        /// Clear the clipboard before staging, so it holds only the given paths
+        #[arg(long, conflicts_with = "watch")]
+        replace: bool,
+
+        /// Stage the newline-separated paths currently on the OS clipboard
+        #[cfg(feature = "os-clipboard")]
+        #[arg(long, conflicts_with_all = ["stdin_content", "watch"])]
+        from_clipboard_paths: bool,
     },
 
     /// Cut files to the clipboard
@@ -27,6 +75,13 @@ enum Commands {
     #[command(alias = "x")]
     Cut {
         paths: Vec<PathBuf>,
+
+        #[command(flatten)]
+        transfer: TransferArgs,
+
+        /// Stage the targets of symlinks instead of the symlinks themselves
+        #[arg(long)]
+        follow: bool,
     },
 
     /// Create symbolic links to files and add them to the clipboard
@@ -34,40 +89,694 @@ enum Commands {
     #[command(alias = "s")]
     Link {
         paths: Vec<PathBuf>,
+
+        #[command(flatten)]
+        transfer: TransferArgs,
+    },
+
+    /// Create symbolic links to files in a directory without touching the clipboard
+    Stage {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Directory to create the symbolic links in
+        #[arg(long, value_name = "DIR")]
+        link_to: PathBuf,
+
+        /// Create links with paths relative to the link's directory
+        #[arg(long)]
+        relative: bool,
     },
 
     /// Paste files from the clipboard to the specified directory
     #[command(alias = "p")]
     #[command(alias = "v")]
     Paste {
-        #[arg(default_value = ".")]
-        path: PathBuf,
+        /// Destination directory (default: the current directory)
+        path: Option<PathBuf>,
+
+        /// Paste into a new directory under the system temp directory and print its path
+        #[arg(long, conflicts_with = "path")]
+        into_tmp: bool,
+
+        /// Resolve collisions without prompting
+        #[arg(long, value_enum)]
+        conflict: Option<ConflictPolicy>,
+
+        /// Paste entries ordered by path, size (smallest first) or staging time (oldest first)
+        #[arg(long, value_enum, value_name = "KEY")]
+        sort: Option<PasteSort>,
+
+        /// Append each collision and how it was resolved to this file
+        #[arg(long, value_name = "FILE")]
+        on_conflict_log: Option<PathBuf>,
+
+        /// Merge directories into existing ones instead of replacing them
+        #[arg(long)]
+        merge: bool,
+
+        /// Abort instead of warning when the destination is short on free space
+        #[arg(long)]
+        strict: bool,
+
+        /// Skip entries whose type changed since staging instead of only warning about them
+        #[arg(long)]
+        verify_type: bool,
+
+        /// Retry a copy or move this many times when it fails with a transient error
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retry: u32,
+
+        /// Write the pasted directory to this file so a shell wrapper can cd into it
+        #[arg(long, value_name = "FILE")]
+        cd: Option<PathBuf>,
+
+        /// Print a JSON summary of pasted, skipped and failed entries instead of messages
+        #[arg(long, conflicts_with = "into_tmp")]
+        summary_json: bool,
+
+        /// Print a JSON progress event to stderr after each pasted entry
+        #[arg(long)]
+        progress_json: bool,
+
+        /// Ask for confirmation before pasting each entry
+        #[arg(long)]
+        prompt_each: bool,
+
+        /// Rename pasted entries using placeholders: {name}, {stem}, {ext}, {date}, {op} and {index}.
+        /// The result may contain `/` to paste into subdirectories
+        #[arg(long, value_name = "TEMPLATE")]
+        template: Option<String>,
+
+        /// Paste each entry into a subdirectory named after its source's parent directory
+        #[arg(long)]
+        group_by_source_dir: bool,
+
+        /// Recreate the staged entries' directory layout relative to their common ancestor
+        #[arg(long, conflicts_with = "group_by_source_dir")]
+        preserve_structure: bool,
+
+        /// Create links with paths relative to the link's directory
+        #[arg(long)]
+        relative: bool,
+
+        /// Paste every entry with this operation, whatever it was staged with
+        #[arg(long = "as", value_enum, value_name = "OPERATION")]
+        paste_as: Option<Operation>,
+
+        /// Create the missing parent directories of each pasted path, including template subdirectories
+        #[arg(long)]
+        parents: bool,
+
+        /// Count copied files whose destination already has identical content as pasted
+        #[arg(long)]
+        destination_exists_ok: bool,
+
+        /// Copy linked entries instead when the destination filesystem does not support the link
+        #[arg(long)]
+        link_fallback_copy: bool,
+
+        /// Leave entries whose absolute path matches this glob in the clipboard (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Exit with a distinct non-zero code when the clipboard is empty
+        #[arg(long)]
+        fail_if_empty: bool,
+
+        /// Warn when the destination filesystem is short on free inodes for the paste
+        #[arg(long)]
+        verify_free_inodes: bool,
+
+        /// Set the permissions of pasted files to this octal mode, e.g. 644
+        #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+        chmod: Option<u32>,
+
+        /// Set the permissions of pasted directories to this octal mode (default: the --chmod mode)
+        #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+        chmod_dirs: Option<u32>,
+
+        #[command(flatten)]
+        ownership: OwnershipArgs,
     },
 
     /// List files currently in the clipboard
     #[command(alias = "l")]
     #[command(alias = "ls")]
-    List,
+    List {
+        /// Keep the list open and reload it whenever the clipboard changes
+        #[arg(long, short, conflicts_with_all = ["plain", "json"])]
+        follow: bool,
+
+        /// Only show entries staged with this operation
+        #[arg(long, value_enum)]
+        operation: Option<Operation>,
+
+        /// Print one path per line instead of opening the TUI
+        #[arg(long, conflicts_with = "json")]
+        plain: bool,
+
+        /// Print the entries as JSON instead of opening the TUI
+        #[arg(long)]
+        json: bool,
+
+        /// Print one JSON object per entry and line instead of opening the TUI
+        #[arg(long, conflicts_with_all = ["follow", "plain", "json", "select_all", "group_by"])]
+        json_lines: bool,
+
+        /// Terminate each path printed by --plain with a NUL byte instead of a newline
+        #[arg(long, short = '0', requires = "plain")]
+        null: bool,
+
+        /// With --plain, --json or --json-lines, format staging times with this strftime string (default: ISO-8601)
+        #[arg(
+            long,
+            value_name = "FORMAT",
+            num_args = 0..=1,
+            default_missing_value = ISO_TIME_FORMAT,
+            value_parser = parse_time_format
+        )]
+        time_format: Option<String>,
+
+        /// Open the TUI with every valid entry already marked
+        #[arg(long, conflicts_with_all = ["plain", "json"])]
+        select_all: bool,
+
+        /// Show entries grouped under a header per operation
+        #[arg(long, value_enum, value_name = "KEY", conflicts_with_all = ["json", "null"])]
+        group_by: Option<GroupBy>,
+
+        /// Remove entries from the clipboard as soon as their files disappear
+        #[arg(long, conflicts_with_all = ["plain", "json", "json_lines"])]
+        watch_validity: bool,
+    },
 
     /// Show the history of clipboard operations
     #[command(alias = "h")]
-    History,
+    History {
+        #[command(subcommand)]
+        command: Option<HistoryCommands>,
+
+        /// Write the history to a CSV file instead of opening the TUI
+        #[arg(long, value_name = "FILE")]
+        export_csv: Option<PathBuf>,
+
+        /// Print one JSON object per entry and line instead of opening the TUI
+        #[arg(long, conflicts_with = "export_csv")]
+        json_lines: bool,
+
+        /// Undo every paste made within this duration, newest first, e.g. 10m
+        #[arg(
+            long,
+            value_name = "DURATION",
+            value_parser = parse_duration,
+            conflicts_with_all = ["export_csv", "json_lines"]
+        )]
+        undo_all_since: Option<Duration>,
+    },
 
     /// Clear the clipboard and history
     Clear,
+
+    /// Print version information
+    Version {
+        /// Also print the state directory and clipboard statistics
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Remove entries whose files no longer exist
+    Prune {
+        /// Also prune the history
+        #[arg(long)]
+        history: bool,
+    },
+
+    /// Merge clipboard entries that point at the same path, keeping the most recent
+    Dedup,
+
+    /// Move a clipboard entry to the front or back, changing the order it is pasted in
+    Reorder {
+        /// The entry's id, as shown by `clp list --json`
+        id: Uuid,
+
+        /// Move the entry to the front of the clipboard (the default)
+        #[arg(long, conflicts_with = "to_back")]
+        to_front: bool,
+
+        /// Move the entry to the back of the clipboard
+        #[arg(long)]
+        to_back: bool,
+    },
+
+    /// Print every detail of one clipboard or history entry
+    Info {
+        /// The entry's id, as shown by `clp list --json`
+        id: Uuid,
+
+        /// Print the details as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print where the state directory, clipboard and history are stored
+    Paths {
+        /// Print the paths as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Time writing and reading a record file in each storage format with synthetic entries
+    #[command(hide = true)]
+    Bench {
+        /// The number of entries in the record file
+        #[arg(long, default_value_t = 1000)]
+        entries: usize,
+
+        /// The number of write and read round trips
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        rounds: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommands {
+    /// Remove history entries older than a given age
+    Clear {
+        /// Age cutoff, e.g. 30d, 12h, 45m, 90s or 2w
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        older_than: Duration,
+    },
+
+    /// Paste the source of a past paste again into another directory
+    Replay {
+        /// The history entry's id, as shown by `clp history --json-lines`
+        id: Uuid,
+
+        /// The directory to paste into
+        destination: PathBuf,
+    },
+}
+
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let split_index = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split_index);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number", input))?;
+    let seconds = match unit {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => {
+            return Err(format!(
+                "'{}' has an unknown unit; use s, m, h, d or w",
+                input
+            ));
+        }
+    };
+    amount
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("'{}' is too large", input))
+}
+
+fn parse_time_format(input: &str) -> Result<String, String> {
+    if validate_time_format(input) {
+        Ok(input.to_string())
+    } else {
+        Err(format!(
+            "'{}' contains an invalid strftime specifier, e.g. use '%Y-%m-%d %H:%M:%S'",
+            input
+        ))
+    }
+}
+
+fn parse_size(input: &str) -> Result<u64, String> {
+    let split_index = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split_index);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number", input))?;
+    let multiplier: u64 = match unit.to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(format!("'{}' has an unknown unit; use K, M, G or T", input)),
+    };
+    amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("'{}' is too large", input))
+}
+
+fn parse_mode(input: &str) -> Result<u32, String> {
+    u32::from_str_radix(input.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("'{}' is not an octal mode, e.g. use 644 or 0755", input))
+}
+
+#[derive(Args)]
+struct TransferArgs {
+    /// Warn about paths that do not exist instead of aborting
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Check every path before staging any, leaving the clipboard untouched if one fails
+    #[arg(long, conflicts_with = "keep_going")]
+    atomic_batch: bool,
+
+    /// Limit how deep staged directories are walked to compute their size
+    #[arg(long, value_name = "DEPTH")]
+    max_depth: Option<usize>,
+
+    /// Follow symlinked directories when computing sizes, skipping symlink cycles
+    #[arg(long)]
+    recursive_symlink_safe: bool,
+
+    /// Only re-stage already staged paths that were modified since they were staged
+    #[arg(long)]
+    if_newer: bool,
+
+    /// Skip paths larger than this size, e.g. 512K, 100M or 2G
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    exclude_larger_than: Option<u64>,
+
+    /// Skip paths that duplicate another path staged in the same command
+    #[arg(long, value_enum)]
+    dedup_by: Option<DedupMode>,
+
+    /// Read piped paths separated by NUL bytes, e.g. from `find -print0`
+    #[arg(long)]
+    stdin_null: bool,
+
+    /// Keep at most this many clipboard entries after staging, dropping the oldest
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_entries: Option<u64>,
+
+    /// Warn about paths that were pasted within this long ago (default: 10m)
+    #[arg(
+        long,
+        value_name = "DURATION",
+        num_args = 0..=1,
+        default_missing_value = "10m",
+        value_parser = parse_duration
+    )]
+    follow_into_history: Option<Duration>,
+
+    /// Print only the ids of the new clipboard entries, one per line
+    #[arg(long)]
+    print_id: bool,
+
+    /// Resolve relative paths and globs against this directory instead of the current one
+    #[arg(long, value_name = "DIR")]
+    cwd: Option<PathBuf>,
+
+    /// Stage the given paths ordered by path, size (smallest first) or modification time (oldest first)
+    #[arg(long, value_enum, value_name = "KEY")]
+    sort_on_stage: Option<PasteSort>,
+
+    /// Attach a note to the staged entries, shown by `clp list`
+    #[arg(long, value_name = "TEXT")]
+    note: Option<String>,
+
+    /// Do not warn about globs that match nothing
+    #[arg(long)]
+    quiet_unmatched: bool,
+
+    /// Only stage regular files, skipping directories and other entries matched by a glob
+    #[arg(long, conflicts_with = "dirs_only")]
+    files_only: bool,
+
+    /// Only stage directories, skipping files and other entries matched by a glob
+    #[arg(long)]
+    dirs_only: bool,
+}
+
+#[cfg(feature = "ownership")]
+#[derive(Args)]
+struct OwnershipArgs {
+    /// Change the owner of pasted files to this user name or uid
+    #[arg(long, value_name = "USER")]
+    owner: Option<String>,
+
+    /// Change the group of pasted files to this group name or gid
+    #[arg(long, value_name = "GROUP")]
+    group: Option<String>,
+}
+
+#[cfg(not(feature = "ownership"))]
+#[derive(Args)]
+struct OwnershipArgs {}
+
+impl OwnershipArgs {
+    #[cfg(feature = "ownership")]
+    fn into_ownership(self) -> Ownership {
+        Ownership {
+            owner: self.owner,
+            group: self.group,
+        }
+    }
+
+    #[cfg(not(feature = "ownership"))]
+    fn into_ownership(self) -> Ownership {
+        Ownership::default()
+    }
+}
+
+impl TransferArgs {
+    fn into_options(self) -> TransferOptions {
+        TransferOptions {
+            keep_going: self.keep_going,
+            atomic_batch: self.atomic_batch,
+            max_depth: self.max_depth,
+            recursive_symlink_safe: self.recursive_symlink_safe,
+            if_newer: self.if_newer,
+            exclude_larger_than: self.exclude_larger_than,
+            dedup_by: self.dedup_by,
+            stdin_null: self.stdin_null,
+            max_entries: self.max_entries.map(|max_entries| max_entries as usize),
+            follow_into_history: self.follow_into_history,
+            print_id: self.print_id,
+            cwd: self.cwd,
+            sort_on_stage: self.sort_on_stage,
+            quiet_unmatched: self.quiet_unmatched,
+            note: self.note,
+            only_entry_type: if self.files_only {
+                Some(EntryType::File)
+            } else if self.dirs_only {
+                Some(EntryType::Directory)
+            } else {
+                None
+            },
+            ..Default::default()
+        }
+    }
 }
 
 pub fn handle_cli() -> Action {
     let cli = Cli::parse();
+    let non_interactive = cli.yes;
 
     match cli.command {
-        Commands::Copy { paths } => Action::Copy(paths),
-        Commands::Cut { paths } => Action::Cut(paths),
-        Commands::Link { paths } => Action::Link(paths),
-        Commands::Paste { path } => Action::Paste(path),
-        Commands::List => Action::Clipboard,
-        Commands::History => Action::History,
+        Commands::Copy {
+            paths,
+            transfer,
+            stdin_content,
+            name,
+            follow,
+            watch,
+            snapshot,
+            exclude_hidden,
+            include_hidden: _,
+            replace,
+            #[cfg(feature = "os-clipboard")]
+            from_clipboard_paths,
+        } => {
+            // Paths copied in a file manager may have been moved since, so missing ones are
+            // reported instead of aborting the batch.
+            #[cfg(feature = "os-clipboard")]
+            let keep_going = transfer.keep_going || from_clipboard_paths;
+            #[cfg(not(feature = "os-clipboard"))]
+            let keep_going = transfer.keep_going;
+            Action::Copy {
+                paths,
+                options: TransferOptions {
+                    stdin_content_name: if stdin_content { name } else { None },
+                    follow_symlinks: follow,
+                    watch,
+                    snapshot,
+                    exclude_hidden,
+                    replace,
+                    keep_going,
+                    #[cfg(feature = "os-clipboard")]
+                    from_clipboard_paths,
+                    ..transfer.into_options()
+                },
+            }
+        }
+        Commands::Cut {
+            paths,
+            transfer,
+            follow,
+        } => Action::Cut {
+            paths,
+            options: TransferOptions {
+                follow_symlinks: follow,
+                ..transfer.into_options()
+            },
+        },
+        Commands::Link { paths, transfer } => Action::Link {
+            paths,
+            options: transfer.into_options(),
+        },
+        Commands::Stage {
+            paths,
+            link_to,
+            relative,
+        } => Action::Stage {
+            paths,
+            link_to,
+            relative,
+        },
+        Commands::Paste {
+            path,
+            into_tmp,
+            conflict,
+            sort,
+            on_conflict_log,
+            merge,
+            strict,
+            verify_type,
+            retry,
+            cd,
+            summary_json,
+            progress_json,
+            prompt_each,
+            template,
+            group_by_source_dir,
+            preserve_structure,
+            relative,
+            link_fallback_copy,
+            destination_exists_ok,
+            parents,
+            paste_as,
+            exclude,
+            fail_if_empty,
+            verify_free_inodes,
+            chmod,
+            chmod_dirs,
+            ownership,
+        } => Action::Paste {
+            path: path.clone().unwrap_or(PathBuf::from(".")),
+            options: PasteOptions {
+                default_destination: path.is_none() && !into_tmp,
+                conflict,
+                merge,
+                strict,
+                verify_type,
+                retry,
+                cd_file: cd,
+                prompt_each,
+                template,
+                group_by_source_dir,
+                preserve_structure,
+                relative_links: relative,
+                link_fallback_copy,
+                destination_exists_ok,
+                parents,
+                paste_as,
+                exclude,
+                fail_if_empty,
+                verify_free_inodes,
+                ownership: ownership.into_ownership(),
+                file_mode: chmod,
+                dir_mode: chmod_dirs.or(chmod),
+                conflict_log: on_conflict_log,
+                sort,
+                into_tmp,
+                summary_json,
+                progress_json,
+                non_interactive,
+                ..Default::default()
+            },
+        },
+        Commands::List {
+            follow,
+            operation,
+            plain,
+            json,
+            json_lines,
+            select_all,
+            null,
+            time_format,
+            group_by,
+            watch_validity,
+        } => Action::List {
+            options: ListOptions {
+                follow,
+                operation,
+                plain,
+                json,
+                json_lines,
+                select_all,
+                null,
+                time_format,
+                group_by,
+                non_interactive,
+                watch_validity,
+            },
+        },
+        Commands::History {
+            command,
+            export_csv,
+            json_lines,
+            undo_all_since,
+        } => match command {
+            None => match (export_csv, undo_all_since) {
+                (Some(path), _) => Action::ExportHistoryCsv { path },
+                (None, Some(since)) => Action::UndoSince { since },
+                (None, None) => Action::History {
+                    options: ListOptions {
+                        json_lines,
+                        non_interactive,
+                        ..Default::default()
+                    },
+                },
+            },
+            Some(HistoryCommands::Clear { older_than }) => Action::ClearHistory { older_than },
+            Some(HistoryCommands::Replay { id, destination }) => Action::Replay {
+                id,
+                destination,
+                options: PasteOptions {
+                    non_interactive,
+                    ..Default::default()
+                },
+            },
+        },
         Commands::Clear => Action::Clear,
+        Commands::Prune { history } => Action::Prune { history },
+        Commands::Dedup => Action::Dedup,
+        Commands::Info { id, json } => Action::Info { id, json },
+        Commands::Reorder { id, to_back, .. } => Action::Reorder {
+            id,
+            position: if to_back {
+                ReorderPosition::Back
+            } else {
+                ReorderPosition::Front
+            },
+        },
+        Commands::Version { verbose } => Action::Version { verbose },
+        Commands::Paths { json } => Action::Paths { json },
+        Commands::Bench { entries, rounds } => Action::Bench { entries, rounds },
     }
 }
 
@@ -78,8 +787,12 @@ mod tests {
     #[test]
     fn test_action_copy() {
         let paths = vec![PathBuf::from("test.txt")];
-        match Action::Copy(paths.clone()) {
-            Action::Copy(p) => assert_eq!(p, paths),
+        let action = Action::Copy {
+            paths: paths.clone(),
+            options: TransferOptions::default(),
+        };
+        match action {
+            Action::Copy { paths: p, .. } => assert_eq!(p, paths),
             _ => panic!("Expected Action::Copy"),
         }
     }
@@ -87,8 +800,12 @@ mod tests {
     #[test]
     fn test_action_cut() {
         let paths = vec![PathBuf::from("test.txt")];
-        match Action::Cut(paths.clone()) {
-            Action::Cut(p) => assert_eq!(p, paths),
+        let action = Action::Cut {
+            paths: paths.clone(),
+            options: TransferOptions::default(),
+        };
+        match action {
+            Action::Cut { paths: p, .. } => assert_eq!(p, paths),
             _ => panic!("Expected Action::Cut"),
         }
     }
@@ -96,8 +813,12 @@ mod tests {
     #[test]
     fn test_action_link() {
         let paths = vec![PathBuf::from("test.txt")];
-        match Action::Link(paths.clone()) {
-            Action::Link(p) => assert_eq!(p, paths),
+        let action = Action::Link {
+            paths: paths.clone(),
+            options: TransferOptions::default(),
+        };
+        match action {
+            Action::Link { paths: p, .. } => assert_eq!(p, paths),
             _ => panic!("Expected Action::Link"),
         }
     }
@@ -105,24 +826,48 @@ mod tests {
     #[test]
     fn test_action_paste() {
         let path = PathBuf::from("/tmp");
-        match Action::Paste(path.clone()) {
-            Action::Paste(p) => assert_eq!(p, path),
+        let action = Action::Paste {
+            path: path.clone(),
+            options: PasteOptions {
+                conflict: Some(ConflictPolicy::Skip),
+                ..Default::default()
+            },
+        };
+        match action {
+            Action::Paste { path: p, options } => {
+                assert_eq!(p, path);
+                assert_eq!(options.conflict, Some(ConflictPolicy::Skip));
+            }
             _ => panic!("Expected Action::Paste"),
         }
     }
 
     #[test]
-    fn test_action_clipboard() {
-        match Action::Clipboard {
-            Action::Clipboard => {}
-            _ => panic!("Expected Action::Clipboard"),
+    fn test_action_list() {
+        match (Action::List {
+            options: ListOptions {
+                follow: true,
+                operation: Some(Operation::Cut),
+                ..Default::default()
+            },
+        }) {
+            Action::List { options } => {
+                assert!(options.follow);
+                assert_eq!(options.operation, Some(Operation::Cut));
+            }
+            _ => panic!("Expected Action::List"),
         }
     }
 
     #[test]
     fn test_action_history() {
-        match Action::History {
-            Action::History => {}
+        match (Action::History {
+            options: ListOptions {
+                json_lines: true,
+                ..Default::default()
+            },
+        }) {
+            Action::History { options } => assert!(options.json_lines),
             _ => panic!("Expected Action::History"),
         }
     }
@@ -134,4 +879,88 @@ mod tests {
             _ => panic!("Expected Action::Clear"),
         }
     }
+
+    #[test]
+    fn test_action_version() {
+        match (Action::Version { verbose: true }) {
+            Action::Version { verbose } => assert!(verbose),
+            _ => panic!("Expected Action::Version"),
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45m"), Ok(Duration::from_secs(45 * 60)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(
+            parse_duration("30d"),
+            Ok(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2w"),
+            Ok(Duration::from_secs(2 * 7 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("10y").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_format() {
+        assert_eq!(parse_time_format("%Y").unwrap(), "%Y");
+        assert!(parse_time_format("%Q").unwrap_err().contains("'%Q'"));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("100M"), Ok(100 * 1024 * 1024));
+        assert_eq!(parse_size("2g"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1GiB"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("644"), Ok(0o644));
+        assert_eq!(parse_mode("0755"), Ok(0o755));
+        assert_eq!(parse_mode("0o600"), Ok(0o600));
+        assert_eq!(parse_mode("4755"), Ok(0o4755));
+        assert!(parse_mode("").is_err());
+        assert!(parse_mode("648").is_err());
+        assert!(parse_mode("17777").is_err());
+        assert!(parse_mode("rw-r--r--").is_err());
+    }
+
+    #[test]
+    fn test_action_prune() {
+        match (Action::Prune { history: true }) {
+            Action::Prune { history } => assert!(history),
+            _ => panic!("Expected Action::Prune"),
+        }
+    }
+
+    #[test]
+    fn test_paste_into_tmp_conflicts_with_path() {
+        assert!(Cli::try_parse_from(["clp", "paste", "--into-tmp"]).is_ok());
+        assert!(Cli::try_parse_from(["clp", "paste", "--into-tmp", "dest"]).is_err());
+    }
+
+    #[test]
+    fn test_from_clipboard_paths_requires_os_clipboard_feature() {
+        let parsed = Cli::try_parse_from(["clp", "copy", "--from-clipboard-paths"]);
+        assert_eq!(parsed.is_ok(), cfg!(feature = "os-clipboard"));
+    }
+
+    #[test]
+    fn test_yes_is_global() {
+        for args in [["clp", "-y", "paste"], ["clp", "paste", "--yes"]] {
+            assert!(Cli::try_parse_from(args).unwrap().yes);
+        }
+        assert!(!Cli::try_parse_from(["clp", "paste"]).unwrap().yes);
+    }
 }
@@ -1,6 +1,12 @@
 use glob::{GlobError, PatternError};
-use std::{io::Error as IoError, path::PathBuf};
+#[cfg(feature = "ownership")]
+use nix::errno::Errno;
+use std::{io::Error as IoError, path::PathBuf, process::ExitCode, time::Duration};
+use strum_macros::IntoStaticStr;
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::{EntryType, RecordType, ReorderPosition, format_duration, format_size};
 
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -14,6 +20,23 @@ pub enum AppError {
     Tui(#[from] TuiError),
 }
 
+pub const EXIT_USAGE_ERROR: u8 = 2;
+pub const EXIT_RECORD_ERROR: u8 = 3;
+pub const EXIT_FILE_ERROR: u8 = 4;
+pub const EXIT_TUI_ERROR: u8 = 5;
+pub const EXIT_WARNINGS: u8 = 6;
+pub const EXIT_NOTHING_TO_PASTE: u8 = 7;
+
+impl AppError {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            AppError::Record(_) => EXIT_RECORD_ERROR,
+            AppError::File(_) => EXIT_FILE_ERROR,
+            AppError::Tui(_) => EXIT_TUI_ERROR,
+        })
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum RecordError {
     #[error("Could not get the user's state directory. Please check your permissions.")]
@@ -46,6 +69,15 @@ pub enum RecordError {
         source: IoError,
     },
 
+    #[error(
+        "Could not lock record file at '{path}'. Please check your permissions or remove it if no other clp is running."
+    )]
+    LockRecordFile {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
     #[error(
         "Could not read from record file at '{path}'. The file may be corrupted. Try running `clp clear` to reset it."
     )]
@@ -64,12 +96,33 @@ pub enum RecordError {
         source: toml::de::Error,
     },
 
+    #[error(
+        "Could not parse line {line} of record file at '{path}'. The file may be corrupted. Try running `clp clear` to reset it."
+    )]
+    DeserializeRecordLog {
+        path: PathBuf,
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error(
+        "Record file at '{path}' uses schema version {version}, which is newer than this version of clp supports. Please upgrade clp."
+    )]
+    UnsupportedSchemaVersion { path: PathBuf, version: u32 },
+
     #[error("Could not prepare data for saving to the record file.")]
     SerializeRecordFile {
         #[source]
         source: toml::ser::Error,
     },
 
+    #[error("Could not format the records as JSON.")]
+    SerializeJson {
+        #[source]
+        source: serde_json::Error,
+    },
+
     #[error(
         "Could not write to record file at '{path}'. Please check permissions and available disk space."
     )]
@@ -79,12 +132,39 @@ pub enum RecordError {
         source: IoError,
     },
 
+    #[error(
+        "Could not export the history to '{path}'. Please check permissions and available disk space."
+    )]
+    ExportCsv {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
     #[error("Could not delete record at '{path}'. Please check permissions.")]
     ClearRecords {
         path: PathBuf,
         #[source]
         source: IoError,
     },
+
+    #[error(
+        "Could not move the record file at '{path}' from the legacy state directory. Please move it manually."
+    )]
+    MigrateRecordFile {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
+    #[error(
+        "Imported the record file at '{path}' into the selected storage format, but could not remove it. Please remove it manually."
+    )]
+    RemoveImportedRecordFile {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -130,6 +210,17 @@ pub enum FileError {
     )]
     FileNameCollision { num_files: usize, to_path: PathBuf },
 
+    #[error(
+        "The destination path '{path}' already exists. Choose a different `--conflict` policy to overwrite, skip, or rename it."
+    )]
+    DestinationExists { path: PathBuf },
+
+    #[error(
+        "Not enough free space to paste {} into '{path}'. Please free up some space and try again.",
+        format_size(*required)
+    )]
+    InsufficientSpace { path: PathBuf, required: u64 },
+
     #[error(
         "Cannot create directory at '{path}'. Please check permissions and available disk space."
     )]
@@ -144,11 +235,69 @@ pub enum FileError {
         source: IoError,
     },
 
+    #[error("'{name}' is not a valid file name. Please provide a name without path separators.")]
+    InvalidName { name: String },
+
+    #[error(
+        "'{path}' is not a valid template result. Templates must produce a relative path inside the destination."
+    )]
+    InvalidTemplatePath { path: String },
+
+    #[error("The directory '{path}' does not exist. Pass --parents to create it.")]
+    ParentMissing { path: PathBuf },
+
+    #[cfg(feature = "ownership")]
+    #[error("No user named '{name}' exists on this system.")]
+    UnknownOwner { name: String },
+
+    #[cfg(feature = "ownership")]
+    #[error("No group named '{name}' exists on this system.")]
+    UnknownGroup { name: String },
+
+    #[error(
+        "'{template}' is not a valid template. Supported placeholders are {{name}}, {{stem}}, {{ext}}, {{date}}, {{op}} and {{index}}."
+    )]
+    InvalidTemplate { template: String },
+
+    #[cfg(feature = "os-clipboard")]
+    #[error(
+        "Could not read paths from the OS clipboard. Install wl-clipboard, xclip, xsel or pbpaste."
+    )]
+    OsClipboardUnavailable,
+
+    #[error(
+        "Could not write the staged content to '{path}'. Please check permissions and available disk space."
+    )]
+    StageContent {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
+    #[error(
+        "Could not write the pasted directory to '{path}'. Please check that the path is writable."
+    )]
+    WriteCdFile {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
+    #[error(
+        "Could not write shell completions to '{path}'. Please check that the path is writable."
+    )]
+    WriteCompletions {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
     #[error("The file type for '{path}' is not supported.")]
     UnsupportedType { path: PathBuf },
 
     #[error(
-        "Could not copy '{from_path}' to '{to_path}'. Please check that the destination exists and you have sufficient permissions."
+        "Could not copy '{from_path}' to '{to_path}' ({kind}). Please check that the destination exists and you have sufficient permissions.",
+        kind = source.kind()
     )]
     Copy {
         from_path: PathBuf,
@@ -158,7 +307,8 @@ pub enum FileError {
     },
 
     #[error(
-        "Could not move '{from_path}' to '{to_path}'. Please check that the destination exists and you have sufficient permissions."
+        "Could not move '{from_path}' to '{to_path}' ({kind}). Please check that the destination exists and you have sufficient permissions.",
+        kind = source.kind()
     )]
     Move {
         from_path: PathBuf,
@@ -192,6 +342,30 @@ pub enum FileError {
         #[source]
         source: PatternError,
     },
+
+    #[error(
+        "Could not write to the conflict log at '{path}'. Please check permissions and available disk space."
+    )]
+    ConflictLog {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
+    #[error(
+        "Nothing was staged because {} path(s) could not be staged, and the clipboard was left unchanged:\n{}",
+        errors.len(),
+        format_errors(errors)
+    )]
+    AtomicBatch { errors: Vec<FileError> },
+}
+
+fn format_errors(errors: &[FileError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("  - {}", error))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(Debug, Error)]
@@ -230,8 +404,69 @@ pub enum AppWarning {
     Record(#[from] RecordWarning),
 }
 
-#[derive(Debug, Error)]
+impl AppWarning {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppWarning::File(warning) => warning.into(),
+            AppWarning::Record(warning) => warning.into(),
+        }
+    }
+}
+
+#[derive(Debug, Error, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum FileWarning {
+    #[cfg(feature = "ownership")]
+    #[error(
+        "Could not change the ownership of '{path}'. Changing the owner usually requires root privileges."
+    )]
+    Chown {
+        path: PathBuf,
+        #[source]
+        source: Errno,
+    },
+
+    #[error("Could not change the permissions of '{path}'. Please check that you own it.")]
+    Chmod {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
+    #[error(
+        "Moving {count} cut entries into the current directory was not confirmed, so nothing was pasted."
+    )]
+    MoveIntoCwdDeclined { count: usize },
+
+    #[error(
+        "Some entries in '{path}' were skipped while merging it into '{destination}', so it was left in the clipboard."
+    )]
+    CutMergeIncomplete { path: PathBuf, destination: PathBuf },
+
+    #[error("'{path}' was modified after it was pasted, so the paste was not undone.")]
+    UndoModified { path: PathBuf },
+
+    #[error(
+        "'{path}' existed before it was pasted, e.g. from a merge or an overwrite, so the paste was not undone."
+    )]
+    UndoPreexisting { path: PathBuf },
+
+    #[error("'{original}' exists again, so '{path}' was not moved back.")]
+    UndoOriginalExists { path: PathBuf, original: PathBuf },
+
+    #[error("'{path}' was pasted before sources were recorded, so the paste cannot be replayed.")]
+    ReplaySourceUnknown { path: PathBuf },
+
+    #[error("'{path}' no longer exists, so the paste cannot be replayed.")]
+    ReplaySourceMissing { path: PathBuf },
+
+    #[error("Could not undo the paste of '{path}'. Please check your permissions.")]
+    UndoFailed {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
     #[error("File '{path}' was modified since last access. Consider reviewing recent changes.")]
     ModifiedMismatch { path: PathBuf },
 
@@ -245,7 +480,18 @@ pub enum FileWarning {
     },
 
     #[error(
-        "File '{path}' changed size from {old_size} bytes to {new_size} bytes. Check if this change was intentional."
+        "'{path}' changed type from {old_type} to {new_type} since it was staged. Skipping it and leaving it in the clipboard."
+    )]
+    TypeChangedSkipped {
+        path: PathBuf,
+        old_type: String,
+        new_type: String,
+    },
+
+    #[error(
+        "File '{path}' changed size from {} to {}. Check if this change was intentional.",
+        format_size(*old_size),
+        format_size(*new_size)
     )]
     SizeMismatch {
         path: PathBuf,
@@ -253,11 +499,56 @@ pub enum FileWarning {
         new_size: u64,
     },
 
-    #[error("Glob pattern '{path}' did not match any file.")]
-    GlobUnmatched { path: PathBuf },
+    #[error("'{path}' did not match any file or directory. Skipping it.")]
+    PathMissing { path: PathBuf },
+
+    #[error(
+        "'{path}' was pasted recently, from an entry staged {} ago. Staging it again may start a copy loop.",
+        format_duration(*ago)
+    )]
+    RecentlyPasted { path: PathBuf, ago: Duration },
+
+    #[error(
+        "'{path}' could not be fully resolved, so it was staged as given. Pasting it may behave differently than expected."
+    )]
+    NonCanonical { path: PathBuf },
+
+    #[error(
+        "Could not paste '{path}' after {attempts} attempts, so it was left in the clipboard: {error}"
+    )]
+    RetriesExhausted {
+        path: PathBuf,
+        attempts: u32,
+        #[source]
+        error: Box<FileError>,
+    },
+
+    #[error("'{path}' disappeared before it could be pasted. Skipping it.")]
+    SourceVanished { path: PathBuf },
+
+    #[error(
+        "Pasting into '{path}' needs {} but only {} is available. The paste may not complete.",
+        format_size(*required),
+        format_size(*available)
+    )]
+    InsufficientSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+
+    #[error(
+        "The filesystem holding '{path}' has {available} free inodes, but pasting needs about {required}. Some files may fail to copy."
+    )]
+    InsufficientInodes {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum RecordWarning {
     #[error(
         "Could not read data from the clipboard file. It may be corrupted. Try running `clp clear` to reset it."
@@ -266,22 +557,92 @@ pub enum RecordWarning {
 
     #[error("Specified entry was not found in the clipboard.")]
     EntryNotFound,
+
+    #[error(
+        "The clipboard holds at most {max} entries, so the {dropped} oldest entries were dropped."
+    )]
+    ClipboardCapped { dropped: usize, max: usize },
+
+    #[error(
+        "There is nothing to paste, since the clipboard is empty or every entry was excluded or is missing."
+    )]
+    NothingToPaste,
 }
 
 #[derive(Debug, Error)]
 pub enum AppInfo {
     #[error("Copied {path}")]
-    Copy { path: PathBuf },
+    Copy { path: PathBuf, id: Uuid },
 
     #[error("Cut {path}")]
-    Cut { path: PathBuf },
+    Cut { path: PathBuf, id: Uuid },
 
     #[error("Linked {path}")]
-    Link { path: PathBuf },
+    Link { path: PathBuf, id: Uuid },
 
     #[error("Pasted {path}")]
     Paste { path: PathBuf },
 
+    #[error("{path} was already up to date")]
+    AlreadyUpToDate { path: PathBuf },
+
+    #[error("Copied {path} because a link could not be created there")]
+    LinkFallbackCopy { path: PathBuf },
+
     #[error("Deleted {path}")]
     Clear { path: PathBuf },
+
+    #[error("Moved {from} to {to}")]
+    MigrateRecordFile { from: PathBuf, to: PathBuf },
+
+    #[error("Imported {from} into {to} for the storage format selected by FILE_CLIPPER_STORAGE")]
+    ImportRecordFile { from: PathBuf, to: PathBuf },
+
+    #[error("Skipped {path} because it has the same content as {original}")]
+    Deduplicated { path: PathBuf, original: PathBuf },
+
+    #[error("Skipped {path} because its size of {} exceeds the limit", format_size(*size))]
+    ExcludedLarger { path: PathBuf, size: u64 },
+
+    #[error("Skipped {path} because it has not changed since it was staged")]
+    NotNewer { path: PathBuf },
+
+    #[error(
+        "Skipped {count} paths that are not {}",
+        if *kept == EntryType::Directory { "directories" } else { "files" }
+    )]
+    EntryTypeFiltered { count: usize, kept: EntryType },
+
+    #[error("Skipped symlink cycle at {path} while computing its size")]
+    SymlinkCycle { path: PathBuf },
+
+    #[error("History holds at most {max} entries, so the {dropped} oldest were discarded")]
+    HistoryCapped { dropped: usize, max: usize },
+
+    #[error("Removed {count} old entries from history")]
+    ClearHistory { count: usize },
+
+    #[error("Undid the paste of {path}")]
+    Undo { path: PathBuf },
+
+    #[error("Undid {undone} pastes and skipped {skipped}")]
+    UndoSummary { undone: usize, skipped: usize },
+
+    #[error("Moved {path} to the {position} of the clipboard")]
+    Reorder {
+        path: PathBuf,
+        position: ReorderPosition,
+    },
+
+    #[error("Exported {count} history entries to {path}")]
+    ExportCsv { path: PathBuf, count: usize },
+
+    #[error("Merged {count} duplicate entries in the clipboard")]
+    Dedup { count: usize },
+
+    #[error("Pruned {count} invalid entries from {record_type}")]
+    Prune {
+        count: usize,
+        record_type: RecordType,
+    },
 }
@@ -1,79 +1,561 @@
+use chrono::Local;
 use dircpy::copy_dir;
-use glob::glob;
+use glob::{Pattern, glob};
+use nix::sys::statvfs::statvfs;
+#[cfg(feature = "ownership")]
+use nix::{
+    fcntl::AtFlags,
+    unistd::{Gid, Group, Uid, User, fchownat},
+};
+use sha2::{Digest, Sha256};
 use std::{
-    collections::VecDeque,
-    env::current_dir,
-    fs::{copy, create_dir_all, remove_file, rename, symlink_metadata},
-    io::{Error as IoError, ErrorKind as IoErrorKind},
-    os::unix::fs::symlink,
-    path::{Path, PathBuf},
-    time::SystemTime,
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    env::{current_dir, temp_dir},
+    ffi::OsStr,
+    fs::{
+        File, OpenOptions, Permissions, canonicalize, copy, create_dir, create_dir_all, metadata,
+        read_dir, read_link, remove_dir, remove_dir_all, remove_file, rename, set_permissions,
+        symlink_metadata,
+    },
+    io::{
+        BufRead, Error as IoError, ErrorKind as IoErrorKind, IsTerminal, Read, Write,
+        copy as io_copy, stderr, stdin,
+    },
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{PermissionsExt, symlink},
+    },
+    path::{Component, Path, PathBuf},
+    thread::sleep,
+    time::{Duration, SystemTime},
 };
+#[cfg(feature = "os-clipboard")]
+use std::{ffi::OsString, os::unix::ffi::OsStringExt, process::Command};
 use text_io::read;
 use uuid::Uuid;
 
 use crate::{
-    errors::{AppError, AppInfo, AppWarning, FileError, FileWarning},
+    errors::{AppError, AppInfo, AppWarning, FileError, FileWarning, RecordWarning},
     models::{
-        CollisionResolution, CollisionResolutionChoice, EntryType, Metadata, Operation,
-        PasteContent, RecordEntry, RecordType,
+        CollisionResolution, CollisionResolutionChoice, ConflictPolicy, DedupMode, EntryInfo,
+        EntryType, Metadata, Operation, Ownership, PasteConfirmationChoice, PasteContent,
+        PasteOptions, PasteOutcome, PasteSort, ProgressEvent, RecordEntry, RecordType,
+        TransferOptions, format_size,
+    },
+    records::{
+        get_content_dir, get_max_entries, lock_records, read_clipboard, read_history,
+        write_clipboard, write_clipboard_with_max, write_history,
     },
-    records::{read_clipboard, read_history, write_clipboard, write_history},
 };
 
 pub fn handle_transfer<P: AsRef<Path>>(
     paths: Vec<P>,
     operation: Operation,
+    options: &TransferOptions,
 ) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
-    let mut clipboard_entries = VecDeque::from(read_clipboard()?.unwrap_or(Vec::new()));
-    let (expanded_paths, warnings) = expand_paths(paths)?;
+    let _lock = lock_records()?;
+    let mut clipboard_entries = if options.replace {
+        VecDeque::new()
+    } else {
+        VecDeque::from(read_clipboard()?.unwrap_or(Vec::new()))
+    };
+    let paths = resolve_against(paths, options.cwd.as_deref());
+    let follow_symlinks = operation == Operation::Link || options.follow_symlinks;
+    let (mut expanded_paths, mut warnings) = if options.atomic_batch {
+        validate_batch(paths, follow_symlinks, options.quiet_unmatched)?
+    } else {
+        expand_paths(paths, options.keep_going, options.quiet_unmatched)?
+    };
+    if let Some(sort) = options.sort_on_stage {
+        sort_paths(&mut expanded_paths, sort, follow_symlinks);
+    }
     let mut infos = Vec::new();
+    if let Some(kept) = &options.only_entry_type {
+        let count = expanded_paths.len();
+        // Paths without metadata are kept so staging reports them as before.
+        expanded_paths.retain(|path| {
+            get_metadata(path, follow_symlinks)
+                .map_or(true, |metadata| metadata.entry_type == *kept)
+        });
+        if expanded_paths.len() < count {
+            infos.push(AppInfo::EntryTypeFiltered {
+                count: count - expanded_paths.len(),
+                kept: kept.clone(),
+            });
+        }
+    }
+    let mut cycle_infos = Vec::new();
+    let mut staged_hashes: HashMap<Vec<u8>, PathBuf> = HashMap::new();
+    let history_entries = match options.follow_into_history {
+        Some(_) => read_history()?.unwrap_or(Vec::new()),
+        None => Vec::new(),
+    };
 
-    for path in &expanded_paths {
+    // New entries go in front of the existing ones but keep the order they were given in, so
+    // `clp copy a b c` lists a, b, c.
+    let mut insert_at = 0;
+    for path in expanded_paths {
         let Metadata {
             size,
             entry_type,
             absolute_path,
-            modified: _,
-        } = get_metadata(path)?;
+            modified,
+            canonical,
+        } = get_metadata(&path, follow_symlinks)?;
+        let (size, size_is_lower_bound) = if entry_type == EntryType::Directory {
+            let (size, truncated) = if options.recursive_symlink_safe {
+                let (size, truncated, cycles) =
+                    get_recursive_size_following_symlinks(&absolute_path, options.max_depth);
+                cycle_infos.extend(
+                    cycles
+                        .into_iter()
+                        .map(|path| AppInfo::SymlinkCycle { path }),
+                );
+                (size, truncated)
+            } else {
+                get_recursive_size(&absolute_path, options.max_depth)
+            };
+            (Some(size), truncated)
+        } else {
+            (size, false)
+        };
+        if let (Some(limit), Some(size)) = (options.exclude_larger_than, size)
+            && size > limit
+        {
+            infos.push(AppInfo::ExcludedLarger { path, size });
+            continue;
+        }
+        if options.dedup_by == Some(DedupMode::Content)
+            && entry_type == EntryType::File
+            && let Some(hash) = get_content_hash(&absolute_path)
+        {
+            if let Some(original) = staged_hashes.get(&hash) {
+                infos.push(AppInfo::Deduplicated {
+                    path,
+                    original: original.clone(),
+                });
+                continue;
+            }
+            staged_hashes.insert(hash, path.clone());
+        }
+        if options.if_newer
+            && let Some(existing_index) = clipboard_entries
+                .iter()
+                .position(|entry| entry.path == absolute_path)
+        {
+            if !is_newer(modified, clipboard_entries[existing_index].timestamp) {
+                infos.push(AppInfo::NotNewer { path });
+                continue;
+            }
+            clipboard_entries.remove(existing_index);
+            if existing_index < insert_at {
+                insert_at -= 1;
+            }
+        }
+        if let Some(window) = options.follow_into_history
+            && let Some(ago) = get_recently_pasted(&history_entries, &absolute_path, window)
+        {
+            warnings.push(AppWarning::File(FileWarning::RecentlyPasted {
+                path: absolute_path.clone(),
+                ago,
+            }));
+        }
+
+        if !canonical {
+            warnings.push(AppWarning::File(FileWarning::NonCanonical {
+                path: absolute_path.clone(),
+            }));
+        }
+        let absolute_path = if options.snapshot && entry_type == EntryType::File {
+            snapshot_file(&absolute_path)?
+        } else {
+            absolute_path
+        };
+
+        let id = Uuid::new_v4();
+        clipboard_entries.insert(
+            insert_at,
+            RecordEntry {
+                operation: operation.clone(),
+                size,
+                size_is_lower_bound,
+                non_canonical: !canonical,
+                exclude_hidden: options.exclude_hidden && entry_type == EntryType::Directory,
+                destination: None,
+                source: None,
+                pasted_at: None,
+                note: options.note.clone(),
+                created_destination: false,
+                entry_type,
+                path: absolute_path,
+                timestamp: SystemTime::now(),
+                id,
+            },
+        );
+        insert_at += 1;
+        infos.push(match operation {
+            Operation::Copy => AppInfo::Copy { path, id },
+            Operation::Cut => AppInfo::Cut { path, id },
+            Operation::Link => AppInfo::Link { path, id },
+        });
+    }
+    let clipboard_entries: Vec<RecordEntry> = clipboard_entries.into();
+    let max_entries = get_max_entries(options.max_entries);
+    if clipboard_entries.len() > max_entries {
+        warnings.push(AppWarning::Record(RecordWarning::ClipboardCapped {
+            dropped: clipboard_entries.len() - max_entries,
+            max: max_entries,
+        }));
+    }
+    write_clipboard_with_max(&clipboard_entries, options.max_entries)?;
+    infos.extend(cycle_infos);
+    Ok((infos, warnings))
+}
+
+// Joining leaves absolute paths untouched, and globs are expanded from the joined string.
+fn resolve_against<P: AsRef<Path>>(paths: Vec<P>, cwd: Option<&Path>) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .map(|path| match cwd {
+            Some(cwd) => cwd.join(path),
+            None => path.as_ref().to_path_buf(),
+        })
+        .collect()
+}
+
+// History paths record where entries were pasted, so a match means the path came out of a paste.
+// Entries pasted before paste times were recorded fall back to the staging time, which is
+// earlier than the paste, so they can only look older than they are.
+fn get_recently_pasted(
+    history_entries: &[RecordEntry],
+    path: &Path,
+    window: Duration,
+) -> Option<Duration> {
+    let now = SystemTime::now();
+    history_entries
+        .iter()
+        .filter(|entry| entry.path == path)
+        .map(|entry| {
+            now.duration_since(entry.pasted_at.unwrap_or(entry.timestamp))
+                .unwrap_or_default()
+        })
+        .min()
+        .filter(|ago| *ago <= window)
+}
+
+pub fn handle_watch<P, F>(
+    paths: Vec<P>,
+    options: &TransferOptions,
+    poll_interval: Duration,
+    mut on_poll: F,
+) -> Result<(), AppError>
+where
+    P: AsRef<Path>,
+    F: FnMut(Vec<AppInfo>, Vec<AppWarning>) -> bool,
+{
+    let paths = resolve_against(paths, options.cwd.as_deref());
+    let mut seen = get_watch_matches(&paths)?;
+    loop {
+        sleep(poll_interval);
+        let matches = get_watch_matches(&paths)?;
+        let mut new_paths: Vec<PathBuf> = matches.difference(&seen).cloned().collect();
+        new_paths.sort();
+        let (infos, warnings) = if new_paths.is_empty() {
+            (Vec::new(), Vec::new())
+        } else {
+            handle_transfer(new_paths, Operation::Copy, options)?
+        };
+        seen = matches;
+        if !on_poll(infos, warnings) {
+            return Ok(());
+        }
+    }
+}
+
+// A watched directory yields its direct children, and anything else is treated as a glob.
+fn get_watch_matches<P: AsRef<Path>>(paths: &[P]) -> Result<HashSet<PathBuf>, FileError> {
+    let mut matches = HashSet::new();
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let children = read_dir(path).map_err(|source| FileError::Metadata {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            matches.extend(children.filter_map(Result::ok).map(|child| child.path()));
+        } else {
+            let entries =
+                glob(&path.to_string_lossy()).map_err(|source| FileError::GlobInvalidPattern {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+            matches.extend(entries.filter_map(Result::ok));
+        }
+    }
+    matches.retain(|path| path.is_file());
+    Ok(matches)
+}
 
-        clipboard_entries.push_front(RecordEntry {
-            operation: operation.clone(),
+pub fn handle_link_to<P: AsRef<Path>>(
+    paths: Vec<P>,
+    link_to: PathBuf,
+    relative: bool,
+) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
+    let (expanded_paths, mut warnings) = expand_paths(paths, false, false)?;
+    let mut entries = Vec::new();
+    for path in expanded_paths {
+        let Metadata {
+            size,
+            entry_type,
+            absolute_path,
+            modified: _,
+            canonical,
+        } = get_metadata(&path, true)?;
+        entries.push(RecordEntry {
+            operation: Operation::Link,
             size,
+            size_is_lower_bound: false,
+            non_canonical: !canonical,
+            exclude_hidden: false,
+            destination: None,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
             entry_type,
             path: absolute_path,
             timestamp: SystemTime::now(),
             id: Uuid::new_v4(),
         });
     }
-    let clipboard_entries: Vec<RecordEntry> = clipboard_entries.into();
-    write_clipboard(&clipboard_entries)?;
-    for path in expanded_paths {
-        infos.push(match operation {
-            Operation::Copy => AppInfo::Copy { path },
-            Operation::Cut => AppInfo::Cut { path },
-            Operation::Link => AppInfo::Link { path },
-        });
-    }
+    // Pasting from a history source leaves both the clipboard and the history untouched.
+    let paste_content = PasteContent {
+        entries,
+        source: RecordType::History,
+    };
+    let options = PasteOptions {
+        relative_links: relative,
+        ..Default::default()
+    };
+    let (infos, paste_warnings) = handle_paste(link_to, Some(paste_content), &options)?;
+    warnings.extend(paste_warnings);
     Ok((infos, warnings))
 }
 
+pub fn stage_content<R: Read>(name: &str, mut reader: R) -> Result<PathBuf, AppError> {
+    if name.is_empty() || Path::new(name).file_name() != Some(OsStr::new(name)) {
+        return Err(AppError::File(FileError::InvalidName {
+            name: name.to_string(),
+        }));
+    }
+    let staged_dir = get_content_dir()?.join(Uuid::new_v4().to_string());
+    create_dir_all(&staged_dir).map_err(|_| FileError::CreateDir {
+        path: staged_dir.clone(),
+    })?;
+    let staged_path = staged_dir.join(name);
+    let mut file = File::create(&staged_path).map_err(|source| FileError::StageContent {
+        path: staged_path.clone(),
+        source,
+    })?;
+    io_copy(&mut reader, &mut file).map_err(|source| FileError::StageContent {
+        path: staged_path.clone(),
+        source,
+    })?;
+    Ok(staged_path)
+}
+
+// Tried in order, so Wayland is preferred when an X11 helper is installed as well.
+#[cfg(feature = "os-clipboard")]
+const OS_CLIPBOARD_COMMANDS: [(&str, &[&str]); 4] = [
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+];
+
+#[cfg(feature = "os-clipboard")]
+pub fn read_os_clipboard() -> Result<String, FileError> {
+    for (program, args) in OS_CLIPBOARD_COMMANDS {
+        if let Ok(output) = Command::new(program).args(args).output()
+            && output.status.success()
+        {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    Err(FileError::OsClipboardUnavailable)
+}
+
+// Reports the helper `read_os_clipboard` would try first, found by searching `PATH`.
+#[cfg(feature = "os-clipboard")]
+pub fn get_os_clipboard_backend() -> Option<&'static str> {
+    let search_path = std::env::var_os("PATH")?;
+    OS_CLIPBOARD_COMMANDS
+        .into_iter()
+        .map(|(program, _)| program)
+        .find(|program| {
+            std::env::split_paths(&search_path).any(|dir| {
+                metadata(dir.join(program)).is_ok_and(|metadata| {
+                    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+                })
+            })
+        })
+}
+
+#[cfg(not(feature = "os-clipboard"))]
+pub fn get_os_clipboard_backend() -> Option<&'static str> {
+    None
+}
+
+// File managers copy selections as a URI list, so `file://` URIs are decoded and comment
+// lines are ignored. Plain newline-separated paths pass through unchanged.
+#[cfg(feature = "os-clipboard")]
+pub fn get_clipboard_paths(
+    read_clipboard_text: fn() -> Result<String, FileError>,
+) -> Result<Vec<PathBuf>, FileError> {
+    Ok(read_clipboard_text()?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| decode_file_uri(line).unwrap_or_else(|| PathBuf::from(line)))
+        .collect())
+}
+
+// Only local URIs name a path on this machine, so the host has to be empty or `localhost`.
+// Anything else is left to be reported as a missing path.
+#[cfg(feature = "os-clipboard")]
+fn decode_file_uri(line: &str) -> Option<PathBuf> {
+    let rest = line.strip_prefix("file://")?;
+    let path = rest
+        .strip_prefix("localhost")
+        .filter(|path| path.starts_with('/'))
+        .unwrap_or(rest);
+    if !path.starts_with('/') {
+        return None;
+    }
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(decoded)))
+}
+
+// Snapshots live in a directory named after the content hash, so staging an unchanged file
+// twice reuses the stored copy. The file keeps its name so it pastes under the original one.
+fn snapshot_file(path: &Path) -> Result<PathBuf, AppError> {
+    let file_name = path.file_name().ok_or_else(|| FileError::FileName {
+        path: path.to_path_buf(),
+    })?;
+    let mut hasher = Sha256::new();
+    File::open(path)
+        .and_then(|mut file| io_copy(&mut file, &mut hasher))
+        .map_err(|source| FileError::StageContent {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let snapshot_dir = get_content_dir()?.join(hash);
+    create_dir_all(&snapshot_dir).map_err(|_| FileError::CreateDir {
+        path: snapshot_dir.clone(),
+    })?;
+    let snapshot_path = snapshot_dir.join(file_name);
+    if !snapshot_path.is_file() {
+        copy(path, &snapshot_path).map_err(|source| FileError::StageContent {
+            path: snapshot_path.clone(),
+            source,
+        })?;
+    }
+    Ok(snapshot_path)
+}
+
+// The directory is left in place after the paste so its contents can be inspected.
+pub fn create_temp_destination() -> Result<PathBuf, FileError> {
+    let path = temp_dir().join(format!("clp-{}", Uuid::new_v4()));
+    create_dir(&path).map_err(|_| FileError::CreateDir { path: path.clone() })?;
+    Ok(path)
+}
+
+pub type PasteReport = (Vec<AppInfo>, Vec<AppWarning>, Vec<(PathBuf, PasteOutcome)>);
+
 pub fn handle_paste<P: AsRef<Path>>(
     destination_path: P,
     paste_content: Option<PasteContent>,
+    options: &PasteOptions,
 ) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
     handle_paste_with_prompt(
         destination_path,
         paste_content,
+        options,
+        get_collision_resolution_choice,
+    )
+}
+
+// Also reports what happened to each staged source path, for summaries that need to tell
+// skipped entries from ones that were never considered.
+pub fn handle_paste_with_outcomes<P: AsRef<Path>>(
+    destination_path: P,
+    paste_content: Option<PasteContent>,
+    options: &PasteOptions,
+) -> Result<PasteReport, AppError> {
+    handle_paste_with_prompts(
+        destination_path,
+        paste_content,
+        options,
         get_collision_resolution_choice,
+        get_paste_confirmation_choice,
+        get_move_confirmation,
+        &mut stderr(),
     )
 }
 
 fn handle_paste_with_prompt<P: AsRef<Path>>(
     destination_path: P,
     paste_content: Option<PasteContent>,
+    options: &PasteOptions,
     get_collision_resolution_choice: fn(path: &Path) -> CollisionResolutionChoice,
 ) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
+    let (infos, warnings, _) = handle_paste_with_prompts(
+        destination_path,
+        paste_content,
+        options,
+        get_collision_resolution_choice,
+        get_paste_confirmation_choice,
+        get_move_confirmation,
+        &mut stderr(),
+    )?;
+    Ok((infos, warnings))
+}
+
+fn handle_paste_with_prompts<P: AsRef<Path>>(
+    destination_path: P,
+    paste_content: Option<PasteContent>,
+    options: &PasteOptions,
+    get_collision_resolution_choice: fn(path: &Path) -> CollisionResolutionChoice,
+    get_paste_confirmation_choice: fn(from: &Path, to: &Path) -> PasteConfirmationChoice,
+    get_move_confirmation: fn(count: usize, destination: &Path) -> bool,
+    progress_output: &mut dyn Write,
+) -> Result<PasteReport, AppError> {
+    let _lock = lock_records()?;
     let destination_path = get_absolute_path(&destination_path)?;
     let mut infos = Vec::new();
     let mut warnings = Vec::new();
@@ -93,17 +575,76 @@ fn handle_paste_with_prompt<P: AsRef<Path>>(
             RecordType::History => (content.entries.clone(), None, None),
         },
     };
+    let exclude_patterns = options
+        .exclude
+        .iter()
+        .map(|pattern| {
+            Pattern::new(pattern).map_err(|source| FileError::GlobInvalidPattern {
+                path: pattern.into(),
+                source,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
     entries_to_paste.retain(|entry| {
-        let validity = check_validity(entry);
-        match validity {
-            Err(_) => false,
-            Ok(Some(warning)) => {
-                warnings.push(AppWarning::File(warning));
-                true
-            }
-            Ok(_) => true,
-        }
+        !exclude_patterns
+            .iter()
+            .any(|pattern| pattern.matches_path(&entry.path))
     });
+    // Entries start out skipped and are updated as they are pasted or fail.
+    let mut outcomes: Vec<(Uuid, PathBuf, PasteOutcome)> = entries_to_paste
+        .iter()
+        .map(|entry| (entry.id, entry.path.clone(), PasteOutcome::Skipped))
+        .collect();
+    // Invalid entries are never pasted, so they stay in the clipboard for `clp prune` to handle.
+    let (mut entries_to_paste, _invalid_entries, validity_warnings) =
+        filter_invalid_entries(entries_to_paste);
+    // With --verify-type, a type change is reported when the entry is skipped below instead.
+    warnings.extend(
+        validity_warnings
+            .into_iter()
+            .filter(|warning| {
+                !(options.verify_type && matches!(warning, FileWarning::TypeMismatch { .. }))
+            })
+            .map(AppWarning::File),
+    );
+    // Checked after filtering, so a paste whose entries were all excluded or invalid counts as
+    // empty for --fail-if-empty.
+    if entries_to_paste.is_empty() {
+        warnings.push(AppWarning::Record(RecordWarning::NothingToPaste));
+        return Ok((infos, warnings, collect_outcomes(outcomes)));
+    }
+    if let Some(sort) = options.sort {
+        sort_entries(&mut entries_to_paste, sort);
+    }
+    // Applied before anything looks at the operation, so the history records what was done.
+    if let Some(operation) = &options.paste_as {
+        for entry in &mut entries_to_paste {
+            entry.operation = operation.clone();
+        }
+    }
+    // Cut entries leave their source, so landing them in the current directory only because no
+    // destination was typed is confirmed first. A conflict policy means the paste is scripted, so
+    // it is never interrupted by a prompt.
+    let cut_count = entries_to_paste
+        .iter()
+        .filter(|entry| entry.operation == Operation::Cut)
+        .count();
+    if options.default_destination
+        && !options.non_interactive
+        && options.conflict.is_none()
+        && cut_count > 0
+        && !get_move_confirmation(
+            cut_count,
+            &destination_path
+                .canonicalize()
+                .unwrap_or_else(|_| destination_path.clone()),
+        )
+    {
+        warnings.push(AppWarning::File(FileWarning::MoveIntoCwdDeclined {
+            count: cut_count,
+        }));
+        return Ok((infos, warnings, collect_outcomes(outcomes)));
+    }
     if !destination_path.is_dir() && entries_to_paste.len() > 1 {
         return Err(AppError::File(FileError::FileNameCollision {
             num_files: entries_to_paste.len(),
@@ -111,72 +652,257 @@ fn handle_paste_with_prompt<P: AsRef<Path>>(
         }));
     }
 
-    let mut resolve_all: Option<CollisionResolution> = None;
-    for mut entry in entries_to_paste {
+    let common_root = if options.preserve_structure {
+        get_common_root(
+            entries_to_paste
+                .iter()
+                .filter_map(|entry| entry.path.parent()),
+        )
+    } else {
+        None
+    };
+
+    if options.conflict == Some(ConflictPolicy::Fail) {
+        for (index, entry) in entries_to_paste.iter().enumerate() {
+            let prospective_path = get_prospective_path(
+                &get_entry_destination(&destination_path, entry, options, common_root.as_deref()),
+                entry,
+                options,
+                index,
+            )?;
+            if prospective_path.exists() {
+                return Err(AppError::File(FileError::DestinationExists {
+                    path: prospective_path,
+                }));
+            }
+        }
+    }
+
+    let required_space: u64 = entries_to_paste
+        .iter()
+        .filter(|entry| entry.operation == Operation::Copy)
+        .map(get_paste_size)
+        .sum();
+    if let Some(warning) = check_available_space(&destination_path, required_space) {
+        if options.strict {
+            return Err(AppError::File(FileError::InsufficientSpace {
+                path: destination_path,
+                required: required_space,
+            }));
+        }
+        warnings.push(AppWarning::File(warning));
+    }
+
+    if options.verify_free_inodes
+        && let Some(warning) = check_available_inodes(&destination_path, &entries_to_paste)
+    {
+        warnings.push(AppWarning::File(warning));
+    }
+
+    let ownership = resolve_ownership(&options.ownership)?;
+    let mut conflict_log = options
+        .conflict_log
+        .as_deref()
+        .map(ConflictLog::open)
+        .transpose()?;
+    let content_dir = get_content_dir()?;
+    let mut resolve_all: Option<CollisionResolution> = match options.conflict {
+        Some(ConflictPolicy::Overwrite) => Some(CollisionResolution::Overwrite),
+        Some(ConflictPolicy::Skip) => Some(CollisionResolution::Skip),
+        Some(ConflictPolicy::Rename) => Some(CollisionResolution::Rename),
+        Some(ConflictPolicy::Fail) => None,
+        // --yes answers the collision prompt as "overwrite all remaining".
+        None => options
+            .non_interactive
+            .then_some(CollisionResolution::Overwrite),
+    };
+    let bytes_total: u64 = if options.progress_json {
+        entries_to_paste.iter().map(get_paste_size).sum()
+    } else {
+        0
+    };
+    let mut bytes_done = 0;
+    for (index, mut entry) in entries_to_paste.into_iter().enumerate() {
+        // Checked right before pasting, since earlier entries can take a while to copy.
+        if options.verify_type
+            && let Ok(metadata) = get_metadata(&entry.path, false)
+            && metadata.entry_type != entry.entry_type
+        {
+            warnings.push(AppWarning::File(FileWarning::TypeChangedSkipped {
+                path: entry.path.clone(),
+                old_type: entry.entry_type.to_string(),
+                new_type: metadata.entry_type.to_string(),
+            }));
+            continue;
+        }
         let mut collision_resolution = resolve_all;
+        let entry_destination =
+            get_entry_destination(&destination_path, &entry, options, common_root.as_deref());
+        if entry_destination != destination_path {
+            create_dir_all(&entry_destination).map_err(|_| FileError::CreateDir {
+                path: entry_destination.clone(),
+            })?;
+        }
+        let mut prospective_path =
+            get_prospective_path(&entry_destination, &entry, options, index)?;
+        // The subdirectories a template nests an entry in are only created with --parents.
+        if let Some(parent) = prospective_path.parent()
+            && parent != entry_destination
+            && prospective_path.starts_with(&entry_destination)
+            && !parent.is_dir()
+        {
+            if !options.parents {
+                return Err(AppError::File(FileError::ParentMissing {
+                    path: parent.to_path_buf(),
+                }));
+            }
+            create_dir_all(parent).map_err(|_| FileError::CreateDir {
+                path: parent.to_path_buf(),
+            })?;
+        }
 
-        let mut quit = false;
-        let file_name = entry.path.file_name().ok_or_else(|| FileError::FileName {
-            path: entry.path.clone(),
-        })?;
-        let prospective_path = if destination_path.is_dir() {
-            destination_path.join(file_name)
+        if options.prompt_each && !options.non_interactive {
+            match get_paste_confirmation_choice(&entry.path, &prospective_path) {
+                PasteConfirmationChoice::Yes => (),
+                PasteConfirmationChoice::No => continue,
+                PasteConfirmationChoice::Quit => break,
+            }
+        }
+
+        let merge = options.merge
+            && entry.operation != Operation::Link
+            && entry.entry_type == EntryType::Directory
+            && prospective_path.is_dir();
+
+        let up_to_date = options.destination_exists_ok
+            && entry.operation == Operation::Copy
+            && is_up_to_date(&entry.path, &prospective_path);
+
+        let entry_size = if options.progress_json {
+            get_paste_size(&entry)
         } else {
-            destination_path.clone()
+            0
         };
-
-        if collision_resolution.is_none() && prospective_path.exists() {
-            let overwrite_choice = get_collision_resolution_choice(&prospective_path);
-            match overwrite_choice {
-                CollisionResolutionChoice::Yes => {
-                    collision_resolution = Some(CollisionResolution::Overwrite)
-                }
-                CollisionResolutionChoice::No => {
-                    collision_resolution = Some(CollisionResolution::Skip)
-                }
-                CollisionResolutionChoice::OverwriteAll => {
-                    resolve_all = Some(CollisionResolution::Overwrite);
-                    collision_resolution = Some(CollisionResolution::Overwrite);
+        let mut files_reported = 0;
+        let mut copied_instead_of_linked = false;
+        let mut destination_existed = symlink_metadata(&prospective_path).is_ok();
+        let operation_result: Result<bool, FileError> = if merge {
+            match merge_directory(
+                &entry.path,
+                &prospective_path,
+                &entry.operation,
+                entry.exclude_hidden,
+                &mut resolve_all,
+                &mut conflict_log,
+                get_collision_resolution_choice,
+            ) {
+                Ok(MergeOutcome::Complete) => Ok(true),
+                Ok(MergeOutcome::Partial) => {
+                    warnings.push(AppWarning::File(FileWarning::CutMergeIncomplete {
+                        path: entry.path.clone(),
+                        destination: prospective_path.clone(),
+                    }));
+                    Ok(false)
                 }
-                CollisionResolutionChoice::SkipAll => {
-                    resolve_all = Some(CollisionResolution::Skip);
-                    collision_resolution = Some(CollisionResolution::Skip);
+                Ok(MergeOutcome::Quit) => break,
+                Err(error) => Err(error),
+            }
+        } else if up_to_date {
+            Ok(true)
+        } else {
+            let collided = prospective_path.exists();
+            if collision_resolution.is_none() && collided {
+                match resolve_collision(
+                    &prospective_path,
+                    &mut resolve_all,
+                    get_collision_resolution_choice,
+                ) {
+                    Some(resolution) => collision_resolution = Some(resolution),
+                    None => break,
                 }
-                CollisionResolutionChoice::Quit => quit = true,
             }
-        }
-        if quit {
-            break;
-        }
-        ensure_dir(&prospective_path).map_err(|_| FileError::CreateDir {
-            path: prospective_path.to_path_buf(),
-        })?;
+            if collided
+                && let (Some(log), Some(resolution)) = (conflict_log.as_mut(), collision_resolution)
+            {
+                log.record(&entry.path, &prospective_path, resolution)?;
+            }
+            if collision_resolution == Some(CollisionResolution::Rename) && collided {
+                prospective_path = get_renamed_path(&prospective_path);
+                destination_existed = symlink_metadata(&prospective_path).is_ok();
+            }
+            // ensure_dir guesses from the name whether the target is a directory; --parents only
+            // creates the directories above it.
+            let created = match prospective_path.parent() {
+                Some(parent) if options.parents => create_dir_all(parent),
+                _ => ensure_dir(&prospective_path),
+            };
+            created.map_err(|_| FileError::CreateDir {
+                path: prospective_path.to_path_buf(),
+            })?;
 
-        let operation_result: Result<bool, FileError> = match entry.operation {
-            Operation::Copy => copy_operation(&entry.path, &prospective_path, collision_resolution),
-            Operation::Cut => move_operation(&entry.path, &prospective_path, collision_resolution),
-            Operation::Link => {
-                if let Some(resolution) = collision_resolution
-                    && resolution == CollisionResolution::Overwrite
-                {
-                    let _ = remove_file(&prospective_path);
+            match entry.operation {
+                Operation::Copy => {
+                    // Copies report each file as it lands, so a large directory shows progress
+                    // while it is being copied.
+                    let mut report_copied = |path: &Path, size: u64| {
+                        bytes_done += size;
+                        files_reported += 1;
+                        write_progress_event(
+                            progress_output,
+                            &ProgressEvent {
+                                bytes_done,
+                                bytes_total,
+                                current_path: path.to_path_buf(),
+                            },
+                        );
+                    };
+                    with_retry(
+                        options.retry,
+                        RETRY_BACKOFF,
+                        collision_resolution,
+                        |resolution| {
+                            copy_operation(
+                                &entry.path,
+                                &prospective_path,
+                                resolution,
+                                entry.exclude_hidden,
+                                options
+                                    .progress_json
+                                    .then_some(&mut report_copied as OnCopied),
+                            )
+                        },
+                    )
                 }
-                match symlink(&entry.path, &prospective_path) {
-                    Ok(_) => Ok(true),
-                    Err(error) => {
-                        if let Some(resolution) = collision_resolution
-                            && resolution == CollisionResolution::Skip
-                            && error.kind() == IoErrorKind::AlreadyExists
-                        {
-                            Ok(false)
-                        } else {
-                            Err(FileError::Link {
-                                from_path: entry.path.clone(),
-                                to_path: prospective_path.clone(),
-                                source: error,
-                            })
-                        }
+                Operation::Cut => with_retry(
+                    options.retry,
+                    RETRY_BACKOFF,
+                    collision_resolution,
+                    |resolution| move_operation(&entry.path, &prospective_path, resolution),
+                ),
+                Operation::Link => {
+                    if let Some(resolution) = collision_resolution
+                        && resolution == CollisionResolution::Overwrite
+                    {
+                        let _ = remove_file(&prospective_path);
                     }
+                    let link_target = if options.relative_links {
+                        get_relative_link_target(&entry.path, &prospective_path)
+                    } else {
+                        entry.path.clone()
+                    };
+                    link_or_copy(
+                        |target, link| symlink(target, link),
+                        &entry.path,
+                        &link_target,
+                        &prospective_path,
+                        collision_resolution,
+                        entry.exclude_hidden,
+                        options.link_fallback_copy,
+                    )
+                    .map(|(pasted, copied)| {
+                        copied_instead_of_linked = copied;
+                        pasted
+                    })
                 }
             }
         };
@@ -184,63 +910,803 @@ fn handle_paste_with_prompt<P: AsRef<Path>>(
         match operation_result {
             Ok(pasted) => {
                 if pasted {
-                    entry.path = prospective_path;
                     if let Some(clipboard_entries) = clipboard_entries.as_mut() {
                         clipboard_entries.retain(|clipboard_entry| clipboard_entry.id != entry.id);
                     }
+                    // Snapshots are shared by entries with the same content, so the stored
+                    // copy stays until no clipboard entry refers to it. A history paste does
+                    // not know the clipboard, so it never removes one.
+                    if entry.path.starts_with(&content_dir)
+                        && let Some(staged_dir) = entry.path.parent()
+                        && let Some(clipboard_entries) = clipboard_entries.as_ref()
+                        && !clipboard_entries
+                            .iter()
+                            .any(|clipboard_entry| clipboard_entry.path.starts_with(staged_dir))
+                    {
+                        let _ = remove_dir_all(staged_dir);
+                    }
+                    entry.destination = prospective_path.parent().map(Path::to_path_buf);
+                    entry.source = Some(entry.path.clone());
+                    entry.path = prospective_path;
+                    entry.pasted_at = Some(SystemTime::now());
+                    entry.created_destination = !destination_existed;
+                    if copied_instead_of_linked {
+                        entry.operation = Operation::Copy;
+                        infos.push(AppInfo::LinkFallbackCopy {
+                            path: entry.path.clone(),
+                        });
+                    }
                     if let Some(history_entries) = history_entries.as_mut() {
                         history_entries.push_front(entry.clone());
                     }
+                    if let Some(warning) = apply_ownership(&entry.path, ownership) {
+                        warnings.push(AppWarning::File(warning));
+                    }
+                    if let Some(warning) =
+                        apply_permissions(&entry.path, options.file_mode, options.dir_mode)
+                    {
+                        warnings.push(AppWarning::File(warning));
+                    }
                     infos.push(AppInfo::Paste {
                         path: entry.path.clone(),
                     });
+                    set_outcome(&mut outcomes, entry.id, PasteOutcome::Pasted);
+                    if up_to_date {
+                        infos.push(AppInfo::AlreadyUpToDate {
+                            path: entry.path.clone(),
+                        });
+                    }
+                    write_paste_state(&clipboard_entries, &mut history_entries)?;
+                    // Moves, links and merges are reported once the whole entry is done.
+                    if options.progress_json && files_reported == 0 {
+                        bytes_done += entry_size;
+                        write_progress_event(
+                            progress_output,
+                            &ProgressEvent {
+                                bytes_done,
+                                bytes_total,
+                                current_path: entry.path.clone(),
+                            },
+                        );
+                    }
                 }
             }
+            Err(error) if source_vanished(&error, &entry.path) => {
+                warnings.push(AppWarning::File(FileWarning::SourceVanished {
+                    path: entry.path.clone(),
+                }));
+            }
+            Err(error) if options.retry > 0 && is_transient(&error) => {
+                set_outcome(&mut outcomes, entry.id, PasteOutcome::Failed);
+                warnings.push(AppWarning::File(FileWarning::RetriesExhausted {
+                    path: entry.path.clone(),
+                    attempts: options.retry + 1,
+                    error: Box::new(error),
+                }));
+            }
             Err(error) => return Err(AppError::File(error)),
         }
     }
 
-    if let Some(clipboard_entries) = clipboard_entries {
-        write_clipboard(&clipboard_entries)?
+    // The history is never trimmed in memory, so the final write reports every dropped entry.
+    infos.extend(write_paste_state(&clipboard_entries, &mut history_entries)?);
+    Ok((infos, warnings, collect_outcomes(outcomes)))
+}
+
+fn set_outcome(outcomes: &mut [(Uuid, PathBuf, PasteOutcome)], id: Uuid, outcome: PasteOutcome) {
+    if let Some(entry_outcome) = outcomes.iter_mut().find(|(entry_id, ..)| *entry_id == id) {
+        entry_outcome.2 = outcome;
     }
-    if let Some(history_entries) = history_entries {
-        let history_entries: Vec<RecordEntry> = history_entries.into();
-        write_history(&history_entries)?;
+}
+
+fn collect_outcomes(outcomes: Vec<(Uuid, PathBuf, PasteOutcome)>) -> Vec<(PathBuf, PasteOutcome)> {
+    outcomes
+        .into_iter()
+        .map(|(_, path, outcome)| (path, outcome))
+        .collect()
+}
+
+// Sizes recorded at staging are trusted, so only unknown or truncated ones are walked.
+fn get_paste_size(entry: &RecordEntry) -> u64 {
+    match entry.size {
+        Some(size) if !entry.size_is_lower_bound => size,
+        _ => get_recursive_size(&entry.path, None).0,
     }
-    Ok((infos, warnings))
 }
 
-pub fn get_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata, FileError> {
-    let path = path.as_ref();
+// Progress goes to stderr as one JSON object per line. A reader that went away must not abort
+// the paste, so write errors are ignored.
+fn write_progress_event(output: &mut dyn Write, event: &ProgressEvent) {
+    if let Ok(line) = serde_json::to_string(event) {
+        let _ = writeln!(output, "{}", line).and_then(|_| output.flush());
+    }
+}
 
-    let absolute_path = if path.is_relative() {
-        current_dir()
-            .map_err(|source| FileError::Cwd { source })?
-            .join(path)
-    } else {
-        path.to_path_buf()
+#[cfg(feature = "ownership")]
+type ResolvedOwnership = Option<(Option<Uid>, Option<Gid>)>;
+#[cfg(not(feature = "ownership"))]
+type ResolvedOwnership = Option<()>;
+
+// Names are looked up first, and anything that is not a known name is accepted as a numeric id.
+#[cfg(feature = "ownership")]
+fn resolve_ownership(ownership: &Ownership) -> Result<ResolvedOwnership, FileError> {
+    let owner = match &ownership.owner {
+        None => None,
+        Some(name) => Some(
+            User::from_name(name)
+                .ok()
+                .flatten()
+                .map(|user| user.uid)
+                .or_else(|| name.parse().ok().map(Uid::from_raw))
+                .ok_or_else(|| FileError::UnknownOwner {
+                    name: name.to_string(),
+                })?,
+        ),
+    };
+    let group = match &ownership.group {
+        None => None,
+        Some(name) => Some(
+            Group::from_name(name)
+                .ok()
+                .flatten()
+                .map(|group| group.gid)
+                .or_else(|| name.parse().ok().map(Gid::from_raw))
+                .ok_or_else(|| FileError::UnknownGroup {
+                    name: name.to_string(),
+                })?,
+        ),
     };
+    Ok((owner.is_some() || group.is_some()).then_some((owner, group)))
+}
 
-    let metadata = symlink_metadata(&absolute_path).map_err(|source| {
-        if source.kind() == IoErrorKind::NotFound {
-            FileError::PathNotFound {
-                path: absolute_path.clone(),
-            }
-        } else {
-            FileError::Metadata {
-                path: absolute_path.clone(),
-                source,
-            }
+#[cfg(not(feature = "ownership"))]
+fn resolve_ownership(_ownership: &Ownership) -> Result<ResolvedOwnership, FileError> {
+    Ok(None)
+}
+
+// Symlinks are changed themselves rather than their targets, and directories are walked
+// without following links so nothing outside the pasted tree is touched.
+#[cfg(feature = "ownership")]
+fn apply_ownership(path: &Path, ownership: ResolvedOwnership) -> Option<FileWarning> {
+    let (owner, group) = ownership?;
+    let chown_warning = |path: &Path, source| FileWarning::Chown {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if let Err(source) = fchownat(None, &current, owner, group, AtFlags::AT_SYMLINK_NOFOLLOW) {
+            return Some(chown_warning(&current, source));
         }
-    })?;
+        let is_dir = symlink_metadata(&current).is_ok_and(|metadata| metadata.is_dir());
+        if is_dir && let Ok(children) = read_dir(&current) {
+            pending.extend(children.flatten().map(|child| child.path()));
+        }
+    }
+    None
+}
 
-    let canonical_path =
-        absolute_path
-            .canonicalize()
-            .map_err(|source| FileError::AbsolutePath {
-                path: absolute_path,
-                source,
-            })?;
+#[cfg(not(feature = "ownership"))]
+fn apply_ownership(_path: &Path, _ownership: ResolvedOwnership) -> Option<FileWarning> {
+    None
+}
+
+// Symlinks are skipped, since changing their mode would change their target's instead. Modes
+// are applied children first, so a directory mode without search permission cannot block them.
+fn apply_permissions(
+    path: &Path,
+    file_mode: Option<u32>,
+    dir_mode: Option<u32>,
+) -> Option<FileWarning> {
+    if file_mode.is_none() && dir_mode.is_none() {
+        return None;
+    }
+    let mut walked = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(metadata) = symlink_metadata(&current) else {
+            continue;
+        };
+        if metadata.is_dir()
+            && let Ok(children) = read_dir(&current)
+        {
+            pending.extend(children.flatten().map(|child| child.path()));
+        }
+        walked.push((current, metadata));
+    }
+    for (current, metadata) in walked.into_iter().rev() {
+        let mode = if metadata.is_dir() {
+            dir_mode
+        } else if metadata.is_file() {
+            file_mode
+        } else {
+            None
+        };
+        if let Some(mode) = mode
+            && let Err(source) = set_permissions(&current, Permissions::from_mode(mode))
+        {
+            return Some(FileWarning::Chmod {
+                path: current,
+                source,
+            });
+        }
+    }
+    None
+}
+
+// Written after every pasted entry so an interrupt mid-batch (e.g. Ctrl-C at a prompt) leaves
+// the clipboard and history consistent with what was actually pasted. This stands in for a
+// Ctrl-C handler, at the cost of rewriting both record files once per entry.
+fn write_paste_state(
+    clipboard_entries: &Option<Vec<RecordEntry>>,
+    history_entries: &mut Option<VecDeque<RecordEntry>>,
+) -> Result<Option<AppInfo>, AppError> {
+    if let Some(clipboard_entries) = clipboard_entries {
+        write_clipboard(clipboard_entries)?
+    }
+    match history_entries {
+        Some(history_entries) => Ok(write_history(history_entries.make_contiguous())?),
+        None => Ok(None),
+    }
+}
+
+// Files above this size are always staged rather than hashed for deduplication.
+const DEDUP_SIZE_CAP: u64 = 256 * 1024 * 1024;
+
+fn get_content_hash(path: &Path) -> Option<Vec<u8>> {
+    if metadata(path).ok()?.len() > DEDUP_SIZE_CAP {
+        return None;
+    }
+    let mut file = File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    io_copy(&mut file, &mut hasher).ok()?;
+    Some(hasher.finalize().to_vec())
+}
+
+// Files too large to hash are never treated as up to date, so they go through the usual
+// collision handling.
+fn is_up_to_date(from: &Path, to: &Path) -> bool {
+    let (Ok(from_metadata), Ok(to_metadata)) = (metadata(from), metadata(to)) else {
+        return false;
+    };
+    from_metadata.is_file()
+        && to_metadata.is_file()
+        && from_metadata.len() == to_metadata.len()
+        && get_content_hash(from).is_some_and(|hash| get_content_hash(to) == Some(hash))
+}
+
+// Staged timestamps are stored with second precision, so compare at that granularity.
+fn is_newer(modified: SystemTime, staged: SystemTime) -> bool {
+    let seconds = |time: SystemTime| {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    };
+    seconds(modified) > seconds(staged)
+}
+
+fn get_recursive_size(path: &Path, max_depth: Option<usize>) -> (u64, bool) {
+    get_recursive_size_at_depth(path, 0, max_depth, &mut None)
+}
+
+// `ancestors` holds the directories on the current path, so only a link back into one of them
+// is a cycle. `visited` holds every directory seen, so one reached through several links is
+// still only counted once.
+#[derive(Default)]
+struct SymlinkWalk {
+    ancestors: HashSet<PathBuf>,
+    visited: HashSet<PathBuf>,
+    cycles: Vec<PathBuf>,
+}
+
+fn get_recursive_size_following_symlinks(
+    path: &Path,
+    max_depth: Option<usize>,
+) -> (u64, bool, Vec<PathBuf>) {
+    let mut walk = Some(SymlinkWalk::default());
+    let (size, truncated) = get_recursive_size_at_depth(path, 0, max_depth, &mut walk);
+    (
+        size,
+        truncated,
+        walk.map(|walk| walk.cycles).unwrap_or_default(),
+    )
+}
+
+fn get_recursive_size_at_depth(
+    path: &Path,
+    depth: usize,
+    max_depth: Option<usize>,
+    symlink_walk: &mut Option<SymlinkWalk>,
+) -> (u64, bool) {
+    let metadata = match symlink_walk {
+        Some(_) => metadata(path),
+        None => symlink_metadata(path),
+    };
+    let Ok(metadata) = metadata else {
+        return (0, false);
+    };
+    if !metadata.is_dir() {
+        return (metadata.len(), false);
+    }
+    let Some(walk) = symlink_walk else {
+        return get_children_size(path, depth, max_depth, symlink_walk);
+    };
+    let Ok(canonical_path) = canonicalize(path) else {
+        return (0, false);
+    };
+    if walk.ancestors.contains(&canonical_path) {
+        walk.cycles.push(path.to_path_buf());
+        return (0, false);
+    }
+    if !walk.visited.insert(canonical_path.clone()) {
+        return (0, false);
+    }
+    walk.ancestors.insert(canonical_path.clone());
+    let size = get_children_size(path, depth, max_depth, symlink_walk);
+    if let Some(walk) = symlink_walk {
+        walk.ancestors.remove(&canonical_path);
+    }
+    size
+}
+
+fn get_children_size(
+    path: &Path,
+    depth: usize,
+    max_depth: Option<usize>,
+    symlink_walk: &mut Option<SymlinkWalk>,
+) -> (u64, bool) {
+    let Ok(mut children) = read_dir(path) else {
+        return (0, false);
+    };
+    if let Some(max_depth) = max_depth
+        && depth >= max_depth
+    {
+        return (0, children.next().is_some());
+    }
+    children
+        .filter_map(Result::ok)
+        .map(|child| get_recursive_size_at_depth(&child.path(), depth + 1, max_depth, symlink_walk))
+        .fold((0, false), |(total, truncated), (size, child_truncated)| {
+            (total + size, truncated || child_truncated)
+        })
+}
+
+fn check_available_space(destination_path: &Path, required: u64) -> Option<FileWarning> {
+    let existing_path = destination_path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())?;
+    let stat = statvfs(existing_path).ok()?;
+    let available = stat.blocks_available() as u64 * stat.fragment_size() as u64;
+    if required > available {
+        Some(FileWarning::InsufficientSpace {
+            path: destination_path.to_path_buf(),
+            required,
+            available,
+        })
+    } else {
+        None
+    }
+}
+
+fn check_available_inodes(destination_path: &Path, entries: &[RecordEntry]) -> Option<FileWarning> {
+    let existing_path = destination_path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())?;
+    let available = statvfs(existing_path).ok()?.files_available() as u64;
+    // Counting stops once the total is known to exceed what is available.
+    let limit = available.saturating_add(1);
+    let mut required: u64 = 0;
+    for entry in entries {
+        required += match entry.operation {
+            Operation::Copy => count_files(&entry.path, limit - required),
+            Operation::Link => 1,
+            Operation::Cut => 0,
+        };
+        if required >= limit {
+            break;
+        }
+    }
+    if required > available {
+        Some(FileWarning::InsufficientInodes {
+            path: destination_path.to_path_buf(),
+            required,
+            available,
+        })
+    } else {
+        None
+    }
+}
+
+// Counts the path and everything below it without following symlinks, stopping at `limit`.
+fn count_files(path: &Path, limit: u64) -> u64 {
+    let mut count = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if count >= limit {
+            break;
+        }
+        count += 1;
+        let is_dir = symlink_metadata(&current).is_ok_and(|metadata| metadata.is_dir());
+        if is_dir && let Ok(children) = read_dir(&current) {
+            pending.extend(children.flatten().map(|child| child.path()));
+        }
+    }
+    count
+}
+
+fn resolve_collision(
+    path: &Path,
+    resolve_all: &mut Option<CollisionResolution>,
+    get_collision_resolution_choice: fn(path: &Path) -> CollisionResolutionChoice,
+) -> Option<CollisionResolution> {
+    if let Some(resolution) = resolve_all {
+        return Some(*resolution);
+    }
+    match get_collision_resolution_choice(path) {
+        CollisionResolutionChoice::Yes => Some(CollisionResolution::Overwrite),
+        CollisionResolutionChoice::No => Some(CollisionResolution::Skip),
+        CollisionResolutionChoice::OverwriteAll => {
+            *resolve_all = Some(CollisionResolution::Overwrite);
+            Some(CollisionResolution::Overwrite)
+        }
+        CollisionResolutionChoice::SkipAll => {
+            *resolve_all = Some(CollisionResolution::Skip);
+            Some(CollisionResolution::Skip)
+        }
+        CollisionResolutionChoice::Quit => None,
+    }
+}
+
+// Collisions are appended as tab-separated lines of source, destination and resolution.
+struct ConflictLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl ConflictLog {
+    fn open(path: &Path) -> Result<Self, FileError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|source| FileError::ConflictLog {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(ConflictLog {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    fn record(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        resolution: CollisionResolution,
+    ) -> Result<(), FileError> {
+        writeln!(
+            self.file,
+            "{}\t{}\t{}",
+            from.display(),
+            to.display(),
+            resolution
+        )
+        .map_err(|source| FileError::ConflictLog {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+// A cut merge only completes once the source directory is empty and removed, so any child left
+// behind, e.g. by a skipped collision, makes it partial.
+#[derive(Debug, PartialEq, Eq)]
+enum MergeOutcome {
+    Complete,
+    Partial,
+    Quit,
+}
+
+fn merge_directory(
+    from: &Path,
+    to: &Path,
+    operation: &Operation,
+    exclude_hidden: bool,
+    resolve_all: &mut Option<CollisionResolution>,
+    conflict_log: &mut Option<ConflictLog>,
+    get_collision_resolution_choice: fn(path: &Path) -> CollisionResolutionChoice,
+) -> Result<MergeOutcome, FileError> {
+    let children = read_dir(from).map_err(|source| FileError::Metadata {
+        path: from.to_path_buf(),
+        source,
+    })?;
+    for child in children {
+        let child = child.map_err(|source| FileError::Metadata {
+            path: from.to_path_buf(),
+            source,
+        })?;
+        if exclude_hidden && *operation != Operation::Cut && is_hidden(&child.file_name()) {
+            continue;
+        }
+        let from_child = child.path();
+        let mut to_child = to.join(child.file_name());
+        let from_is_dir = symlink_metadata(&from_child)
+            .map(|metadata| metadata.is_dir())
+            .unwrap_or(false);
+
+        if from_is_dir && to_child.is_dir() {
+            if merge_directory(
+                &from_child,
+                &to_child,
+                operation,
+                exclude_hidden,
+                resolve_all,
+                conflict_log,
+                get_collision_resolution_choice,
+            )? == MergeOutcome::Quit
+            {
+                return Ok(MergeOutcome::Quit);
+            }
+            continue;
+        }
+
+        let mut collision_resolution = None;
+        if symlink_metadata(&to_child).is_ok() {
+            let resolution =
+                resolve_collision(&to_child, resolve_all, get_collision_resolution_choice);
+            if let (Some(log), Some(resolution)) = (conflict_log.as_mut(), resolution) {
+                log.record(&from_child, &to_child, resolution)?;
+            }
+            match resolution {
+                Some(CollisionResolution::Skip) => continue,
+                Some(CollisionResolution::Rename) => to_child = get_renamed_path(&to_child),
+                Some(CollisionResolution::Overwrite) => {
+                    if to_child.is_dir() {
+                        remove_dir_all(&to_child)
+                    } else {
+                        remove_file(&to_child)
+                    }
+                    .map_err(|source| FileError::Copy {
+                        from_path: from_child.clone(),
+                        to_path: to_child.clone(),
+                        source,
+                    })?;
+                    collision_resolution = Some(CollisionResolution::Overwrite);
+                }
+                None => return Ok(MergeOutcome::Quit),
+            }
+        }
+
+        match operation {
+            Operation::Cut => move_operation(&from_child, &to_child, collision_resolution)?,
+            _ => copy_operation(
+                &from_child,
+                &to_child,
+                collision_resolution,
+                exclude_hidden,
+                None,
+            )?,
+        };
+    }
+
+    if *operation == Operation::Cut && remove_dir(from).is_err() {
+        return Ok(MergeOutcome::Partial);
+    }
+    Ok(MergeOutcome::Complete)
+}
+
+fn get_relative_link_target(target: &Path, link_path: &Path) -> PathBuf {
+    let Some(link_dir) = link_path.parent() else {
+        return target.to_path_buf();
+    };
+    let link_dir = canonicalize(link_dir).unwrap_or_else(|_| link_dir.to_path_buf());
+    let target_components: Vec<_> = target.components().collect();
+    let link_dir_components: Vec<_> = link_dir.components().collect();
+    let common = target_components
+        .iter()
+        .zip(&link_dir_components)
+        .take_while(|(target_component, link_component)| target_component == link_component)
+        .count();
+    let mut relative_target = PathBuf::new();
+    for _ in common..link_dir_components.len() {
+        relative_target.push("..");
+    }
+    for component in &target_components[common..] {
+        relative_target.push(component);
+    }
+    relative_target
+}
+
+fn get_common_root<'a>(mut paths: impl Iterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut common_root = paths.next()?.to_path_buf();
+    for path in paths {
+        while !path.starts_with(&common_root) {
+            if !common_root.pop() {
+                return None;
+            }
+        }
+    }
+    Some(common_root)
+}
+
+fn get_entry_destination(
+    destination_path: &Path,
+    entry: &RecordEntry,
+    options: &PasteOptions,
+    common_root: Option<&Path>,
+) -> PathBuf {
+    if let Some(common_root) = common_root
+        && destination_path.is_dir()
+        && let Some(relative_dir) = entry
+            .path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(common_root).ok())
+    {
+        return destination_path.join(relative_dir);
+    }
+    let source_dir_name = entry.path.parent().and_then(Path::file_name);
+    match source_dir_name {
+        Some(source_dir_name) if options.group_by_source_dir && destination_path.is_dir() => {
+            destination_path.join(source_dir_name)
+        }
+        _ => destination_path.to_path_buf(),
+    }
+}
+
+pub fn get_paste_target(
+    destination_path: &Path,
+    entry: &RecordEntry,
+    options: &PasteOptions,
+) -> Result<PathBuf, FileError> {
+    get_prospective_path(destination_path, entry, options, 0)
+}
+
+// A per-entry rename takes precedence over the template.
+fn get_prospective_path(
+    destination_path: &Path,
+    entry: &RecordEntry,
+    options: &PasteOptions,
+    index: usize,
+) -> Result<PathBuf, FileError> {
+    let file_name = entry.path.file_name().ok_or_else(|| FileError::FileName {
+        path: entry.path.clone(),
+    })?;
+    if destination_path.is_dir() {
+        match (options.renames.get(&entry.id), options.template.as_deref()) {
+            (Some(name), _) => Ok(destination_path.join(name)),
+            (None, Some(template)) => {
+                Ok(destination_path.join(render_template(template, entry, index)?))
+            }
+            (None, None) => Ok(destination_path.join(file_name)),
+        }
+    } else {
+        Ok(destination_path.to_path_buf())
+    }
+}
+
+fn render_template(template: &str, entry: &RecordEntry, index: usize) -> Result<String, FileError> {
+    let path = &entry.path;
+    let lossy = |part: Option<&OsStr>| {
+        part.map(|part| part.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| FileError::InvalidTemplate {
+                template: template.to_string(),
+            })?;
+        rendered.push_str(&match &rest[start + 1..end] {
+            "name" => lossy(path.file_name()),
+            "stem" => lossy(path.file_stem()),
+            "ext" => lossy(path.extension()),
+            "date" => Local::now().format("%Y-%m-%d").to_string(),
+            "op" => entry.operation.to_string(),
+            "index" => (index + 1).to_string(),
+            _ => {
+                return Err(FileError::InvalidTemplate {
+                    template: template.to_string(),
+                });
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    // Templates may nest entries in subdirectories, but never outside the destination.
+    let stays_inside = Path::new(&rendered)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if rendered.is_empty() || rendered.ends_with('/') || !stays_inside {
+        return Err(FileError::InvalidTemplatePath { path: rendered });
+    }
+    Ok(rendered)
+}
+
+fn get_renamed_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut index = 1;
+    loop {
+        let renamed_path = path.with_file_name(format!("{} ({}){}", stem, index, extension));
+        if symlink_metadata(&renamed_path).is_err() {
+            return renamed_path;
+        }
+        index += 1;
+    }
+}
+
+pub fn get_metadata<P: AsRef<Path>>(path: P, follow_symlinks: bool) -> Result<Metadata, FileError> {
+    let path = path.as_ref();
+
+    let absolute_path = if path.is_relative() {
+        current_dir()
+            .map_err(|source| FileError::Cwd { source })?
+            .join(path)
+    } else {
+        path.to_path_buf()
+    };
+
+    // A dangling symlink has nothing to follow, so it is staged as the link itself.
+    let metadata = if follow_symlinks {
+        metadata(&absolute_path).or_else(|error| match symlink_metadata(&absolute_path) {
+            Ok(link_metadata) if error.kind() == IoErrorKind::NotFound => Ok(link_metadata),
+            _ => Err(error),
+        })
+    } else {
+        symlink_metadata(&absolute_path)
+    }
+    .map_err(|source| {
+        if source.kind() == IoErrorKind::NotFound {
+            FileError::PathNotFound {
+                path: absolute_path.clone(),
+            }
+        } else {
+            FileError::Metadata {
+                path: absolute_path.clone(),
+                source,
+            }
+        }
+    })?;
+
+    // Without following, a symlink is staged as itself, so only its parent is canonicalized.
+    // Paths that exist but cannot be resolved, such as `/proc` entries pointing at pipes, fall
+    // back to the absolute path and are flagged as non-canonical.
+    // Following never yields a symlink, so one here is a dangling link that was not resolved.
+    let mut canonical = !(follow_symlinks && metadata.file_type().is_symlink());
+    let mut canonicalize_path = |path: &Path, fallback: PathBuf| match path.canonicalize() {
+        Ok(path) => Ok(path),
+        Err(source)
+            if matches!(
+                source.kind(),
+                IoErrorKind::NotFound | IoErrorKind::InvalidInput
+            ) =>
+        {
+            canonical = false;
+            Ok(fallback)
+        }
+        Err(source) => Err(FileError::AbsolutePath {
+            path: absolute_path.clone(),
+            source,
+        }),
+    };
+    let canonical_path = match (absolute_path.parent(), absolute_path.file_name()) {
+        (Some(parent), Some(file_name)) if metadata.file_type().is_symlink() => {
+            canonicalize_path(parent, parent.to_path_buf())?.join(file_name)
+        }
+        _ => canonicalize_path(&absolute_path, absolute_path.clone())?,
+    };
 
     let modified = metadata
         .modified()
@@ -273,6 +1739,7 @@ pub fn get_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata, FileError> {
         size,
         entry_type,
         absolute_path: canonical_path,
+        canonical,
     })
 }
 
@@ -287,8 +1754,40 @@ pub fn get_absolute_path<P: AsRef<Path>>(path: P) -> Result<PathBuf, FileError>
     Ok(absolute_path)
 }
 
+// Collects every path that cannot be staged so an atomic batch fails as a whole, before the
+// clipboard is read back or written.
+fn validate_batch<P: AsRef<Path>>(
+    paths: Vec<P>,
+    follow_symlinks: bool,
+    quiet_unmatched: bool,
+) -> Result<(Vec<PathBuf>, Vec<AppWarning>), FileError> {
+    let (expanded_paths, warnings) = expand_paths(paths, true, quiet_unmatched)?;
+    let mut errors = Vec::new();
+    let mut remaining_warnings = Vec::new();
+    for warning in warnings {
+        match warning {
+            AppWarning::File(FileWarning::PathMissing { path }) => {
+                errors.push(FileError::PathNotFound { path })
+            }
+            warning => remaining_warnings.push(warning),
+        }
+    }
+    errors.extend(
+        expanded_paths
+            .iter()
+            .filter_map(|path| get_metadata(path, follow_symlinks).err()),
+    );
+    if errors.is_empty() {
+        Ok((expanded_paths, remaining_warnings))
+    } else {
+        Err(FileError::AtomicBatch { errors })
+    }
+}
+
 fn expand_paths<P: AsRef<Path>>(
     paths: Vec<P>,
+    keep_going: bool,
+    quiet_unmatched: bool,
 ) -> Result<(Vec<PathBuf>, Vec<AppWarning>), FileError> {
     let mut expanded = Vec::new();
     let mut warnings = Vec::new();
@@ -309,8 +1808,11 @@ fn expand_paths<P: AsRef<Path>>(
                         .collect::<Result<Vec<PathBuf>, FileError>>()?;
 
                     if matched_paths.is_empty() {
+                        if quiet_unmatched {
+                            continue;
+                        }
                         warnings.push(
-                            FileWarning::GlobUnmatched {
+                            FileWarning::PathMissing {
                                 path: path.as_ref().to_path_buf(),
                             }
                             .into(),
@@ -327,6 +1829,16 @@ fn expand_paths<P: AsRef<Path>>(
                     });
                 }
             }
+        } else if keep_going
+            && let Err(error) = symlink_metadata(path.as_ref())
+            && error.kind() == IoErrorKind::NotFound
+        {
+            warnings.push(
+                FileWarning::PathMissing {
+                    path: path.as_ref().to_path_buf(),
+                }
+                .into(),
+            );
         } else {
             expanded.push(path.as_ref().to_path_buf());
         }
@@ -335,94 +1847,490 @@ fn expand_paths<P: AsRef<Path>>(
     Ok((expanded, warnings))
 }
 
-fn check_validity(entry: &RecordEntry) -> Result<Option<FileWarning>, FileError> {
-    let Metadata {
-        modified,
-        size,
-        entry_type,
-        absolute_path,
-    } = get_metadata(&entry.path)?;
+pub fn handle_info(id: Uuid) -> Result<Option<EntryInfo>, AppError> {
+    let clipboard_entries = read_clipboard()?.unwrap_or(Vec::new());
+    let history_entries = read_history()?.unwrap_or(Vec::new());
+    let found = clipboard_entries
+        .into_iter()
+        .map(|entry| (RecordType::Clipboard, entry))
+        .chain(
+            history_entries
+                .into_iter()
+                .map(|entry| (RecordType::History, entry)),
+        )
+        .find(|(_, entry)| entry.id == id);
+    let Some((record_type, entry)) = found else {
+        return Ok(None);
+    };
 
-    if entry_type != entry.entry_type {
-        return Ok(Some(FileWarning::TypeMismatch {
-            path: absolute_path,
-            old_type: entry.entry_type.to_string(),
-            new_type: entry_type.to_string(),
-        }));
-    }
+    let metadata = get_metadata(&entry.path, false).ok();
+    let drift = match check_validity(&entry) {
+        Ok(warning) => warning.map(|warning| warning.to_string()),
+        Err(error) => Some(error.to_string()),
+    };
+    Ok(Some(EntryInfo {
+        record_type,
+        exists: metadata.is_some(),
+        current_size: metadata.as_ref().and_then(|metadata| metadata.size),
+        current_modified: metadata.map(|metadata| metadata.modified),
+        drift,
+        entry,
+    }))
+}
 
-    if let (Some(expected_size), Some(self_size)) = (size, entry.size)
-        && self_size != expected_size
-    {
-        return Ok(Some(FileWarning::SizeMismatch {
-            path: absolute_path,
-            old_size: self_size,
-            new_size: expected_size,
-        }));
-    }
+// Later pastes may sit on top of earlier ones, so the newest paste is reversed first. Entries
+// pasted before the paste time was recorded cannot be placed in time and are left alone.
+pub fn handle_undo_since(since: Duration) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
+    let _lock = lock_records()?;
+    let cutoff = SystemTime::now()
+        .checked_sub(since)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut history_entries = read_history()?.unwrap_or(Vec::new());
+    let mut targets: Vec<(&RecordEntry, &PathBuf, SystemTime)> = history_entries
+        .iter()
+        .filter_map(|entry| match (&entry.source, entry.pasted_at) {
+            (Some(source), Some(pasted_at)) if pasted_at >= cutoff => {
+                Some((entry, source, pasted_at))
+            }
+            _ => None,
+        })
+        .collect();
+    targets.sort_by_key(|(_, _, pasted_at)| Reverse(*pasted_at));
 
-    if modified
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        > entry
-            .timestamp
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    {
-        return Ok(Some(FileWarning::ModifiedMismatch {
-            path: absolute_path,
-        }));
+    let mut infos = Vec::new();
+    let mut warnings = Vec::new();
+    let mut undone = HashSet::new();
+    for (entry, source, pasted_at) in targets {
+        match undo_entry(entry, source, pasted_at) {
+            Ok(()) => {
+                undone.insert(entry.id);
+                infos.push(AppInfo::Undo {
+                    path: entry.path.clone(),
+                });
+            }
+            Err(warning) => warnings.push(AppWarning::File(warning)),
+        }
     }
 
-    Ok(None)
+    let skipped = warnings.len();
+    if !undone.is_empty() {
+        history_entries.retain(|entry| !undone.contains(&entry.id));
+        infos.extend(write_history(&history_entries)?);
+    }
+    infos.push(AppInfo::UndoSummary {
+        undone: undone.len(),
+        skipped,
+    });
+    Ok((infos, warnings))
 }
 
-fn get_collision_resolution_choice(path: &Path) -> CollisionResolutionChoice {
-    loop {
-        println!("[Warning]: Destination path already exists at: ");
-        println!("{}", path.to_string_lossy());
-        println!(
-            "Overwrite?\nY: yes; N: no; A: overwrite all remaining; S: skip all remaining; Q: quit"
-        );
-        let choice: String = read!();
-        if let Some(user_choice) = CollisionResolutionChoice::from_str(&choice) {
-            return user_choice;
-        }
+// The replayed paste is not recorded again, so the history keeps one entry per original paste.
+pub fn handle_replay(
+    id: Uuid,
+    destination: &Path,
+    options: &PasteOptions,
+) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
+    let history_entries = read_history()?.unwrap_or(Vec::new());
+    let Some(entry) = history_entries.into_iter().find(|entry| entry.id == id) else {
+        return Ok((
+            Vec::new(),
+            vec![AppWarning::Record(RecordWarning::EntryNotFound)],
+        ));
+    };
+    let Some(source) = entry.source.clone() else {
+        return Ok((
+            Vec::new(),
+            vec![AppWarning::File(FileWarning::ReplaySourceUnknown {
+                path: entry.path,
+            })],
+        ));
+    };
+    if symlink_metadata(&source).is_err() {
+        return Ok((
+            Vec::new(),
+            vec![AppWarning::File(FileWarning::ReplaySourceMissing {
+                path: source,
+            })],
+        ));
+    }
+    let entry = RecordEntry {
+        path: source,
+        source: None,
+        destination: None,
+        pasted_at: None,
+        ..entry
+    };
+    handle_paste(
+        destination,
+        Some(PasteContent {
+            entries: vec![entry],
+            source: RecordType::History,
+        }),
+        options,
+    )
+}
+
+// Copies and links are removed, and cut entries are moved back to where they were staged from.
+fn undo_entry(
+    entry: &RecordEntry,
+    source: &Path,
+    pasted_at: SystemTime,
+) -> Result<(), FileWarning> {
+    let metadata = symlink_metadata(&entry.path).map_err(|_| FileWarning::PathMissing {
+        path: entry.path.clone(),
+    })?;
+    if !entry.created_destination {
+        return Err(FileWarning::UndoPreexisting {
+            path: entry.path.clone(),
+        });
+    }
+    if modified_since(&entry.path, pasted_at) {
+        return Err(FileWarning::UndoModified {
+            path: entry.path.clone(),
+        });
+    }
+
+    let undo_error = |source| FileWarning::UndoFailed {
+        path: entry.path.clone(),
+        source,
+    };
+    match entry.operation {
+        Operation::Cut => {
+            if symlink_metadata(source).is_ok() {
+                return Err(FileWarning::UndoOriginalExists {
+                    path: entry.path.clone(),
+                    original: source.to_path_buf(),
+                });
+            }
+            if let Some(parent) = source.parent() {
+                create_dir_all(parent).map_err(undo_error)?;
+            }
+            rename(&entry.path, source).map_err(undo_error)
+        }
+        Operation::Copy | Operation::Link => if metadata.is_dir() {
+            remove_dir_all(&entry.path)
+        } else {
+            remove_file(&entry.path)
+        }
+        .map_err(undo_error),
+    }
+}
+
+// Looks at every file in a pasted tree, since editing a nested file leaves the top directory's
+// mtime alone. Unreadable children count as modified so undo errs on the side of keeping them.
+fn modified_since(path: &Path, since: SystemTime) -> bool {
+    let Ok(metadata) = symlink_metadata(path) else {
+        return true;
+    };
+    if metadata
+        .modified()
+        .map_or(true, |modified| modified > since)
+    {
+        return true;
+    }
+    metadata.is_dir()
+        && read_dir(path).map_or(true, |children| {
+            children
+                .map(|child| child.map(|child| child.path()))
+                .any(|child| child.map_or(true, |child| modified_since(&child, since)))
+        })
+}
+
+// The sort is stable, so entries with equal keys keep their clipboard order. Entries without a
+// recorded size sort before the smallest file.
+fn sort_entries(entries: &mut [RecordEntry], sort: PasteSort) {
+    match sort {
+        PasteSort::Path => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+        PasteSort::Size => entries.sort_by_key(|entry| entry.size),
+        PasteSort::Time => entries.sort_by_key(|entry| entry.timestamp),
+    }
+}
+
+// Uses the same keys as sort_entries, read from the paths since nothing is staged yet. Paths
+// that cannot be read sort first and fail with the usual error once they are staged.
+fn sort_paths(paths: &mut [PathBuf], sort: PasteSort, follow_symlinks: bool) {
+    match sort {
+        PasteSort::Path => paths.sort(),
+        PasteSort::Size => paths.sort_by_cached_key(|path| {
+            get_metadata(path, follow_symlinks)
+                .ok()
+                .and_then(|metadata| metadata.size)
+        }),
+        PasteSort::Time => paths.sort_by_cached_key(|path| {
+            get_metadata(path, follow_symlinks)
+                .ok()
+                .map(|metadata| metadata.modified)
+        }),
+    }
+}
+
+// Splits entries into those that can still be pasted and those whose source is gone, collecting
+// the drift warnings of valid entries along the way.
+fn filter_invalid_entries(
+    entries: Vec<RecordEntry>,
+) -> (Vec<RecordEntry>, Vec<RecordEntry>, Vec<FileWarning>) {
+    let mut valid_entries = Vec::new();
+    let mut invalid_entries = Vec::new();
+    let mut warnings = Vec::new();
+    for entry in entries {
+        match check_validity(&entry) {
+            Ok(warning) => {
+                warnings.extend(warning);
+                valid_entries.push(entry);
+            }
+            Err(_) => invalid_entries.push(entry),
+        }
+    }
+    (valid_entries, invalid_entries, warnings)
+}
+
+fn check_validity(entry: &RecordEntry) -> Result<Option<FileWarning>, FileError> {
+    let Metadata {
+        modified,
+        size,
+        entry_type,
+        absolute_path,
+        canonical: _,
+    } = get_metadata(&entry.path, false)?;
+
+    if entry_type != entry.entry_type {
+        return Ok(Some(FileWarning::TypeMismatch {
+            path: absolute_path,
+            old_type: entry.entry_type.to_string(),
+            new_type: entry_type.to_string(),
+        }));
+    }
+
+    if let (Some(expected_size), Some(self_size)) = (size, entry.size)
+        && self_size != expected_size
+    {
+        return Ok(Some(FileWarning::SizeMismatch {
+            path: absolute_path,
+            old_size: self_size,
+            new_size: expected_size,
+        }));
+    }
+
+    if modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        > entry
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    {
+        return Ok(Some(FileWarning::ModifiedMismatch {
+            path: absolute_path,
+        }));
+    }
+
+    Ok(None)
+}
+
+fn get_collision_resolution_choice(path: &Path) -> CollisionResolutionChoice {
+    loop {
+        println!("[Warning]: Destination path already exists at: ");
+        println!(
+            "{} ({})",
+            path.to_string_lossy(),
+            describe_existing_size(path)
+        );
+        println!(
+            "Overwrite?\nY: yes; N: no; A: overwrite all remaining; S: skip all remaining; Q: quit"
+        );
+        let choice: String = read!();
+        if let Some(user_choice) = CollisionResolutionChoice::from_str(&choice) {
+            return user_choice;
+        }
+        println!("Invalid input. Please try again.");
+    }
+}
+
+fn describe_existing_size(path: &Path) -> String {
+    match symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => "directory".to_string(),
+        Ok(metadata) => format_size(metadata.len()),
+        Err(_) => "unknown size".to_string(),
+    }
+}
+
+fn get_paste_confirmation_choice(from: &Path, to: &Path) -> PasteConfirmationChoice {
+    loop {
+        println!("[Info]: About to paste: ");
+        println!("{} -> {}", from.to_string_lossy(), to.to_string_lossy());
+        println!("Paste?\nY: yes; N: no; Q: quit");
+        let choice: String = read!();
+        if let Some(user_choice) = PasteConfirmationChoice::from_str(&choice) {
+            return user_choice;
+        }
         println!("Invalid input. Please try again.");
     }
 }
 
+// Without a terminal there is nobody to ask, so the paste goes ahead as it did before the prompt
+// existed.
+fn get_move_confirmation(count: usize, destination: &Path) -> bool {
+    let input = stdin();
+    let is_terminal = input.is_terminal();
+    confirm_move(count, destination, &mut input.lock(), is_terminal)
+}
+
+// Closed input (EOF) answers no, so the paste is aborted instead of asking forever.
+fn confirm_move(
+    count: usize,
+    destination: &Path,
+    input: &mut dyn BufRead,
+    is_terminal: bool,
+) -> bool {
+    if !is_terminal {
+        return true;
+    }
+    loop {
+        println!(
+            "[Info]: This will move {} files into the current directory: ",
+            count
+        );
+        println!("{}", destination.to_string_lossy());
+        println!("Continue?\nY: yes; N: no");
+        let mut choice = String::new();
+        match input.read_line(&mut choice) {
+            Ok(0) | Err(_) => return false,
+            Ok(_) => {}
+        }
+        match choice.trim().to_lowercase().as_str() {
+            "y" => return true,
+            "n" => return false,
+            _ => println!("Invalid input. Please try again."),
+        }
+    }
+}
+
+// Called with the destination and size of every regular file copied.
+type OnCopied<'a> = &'a mut dyn FnMut(&Path, u64);
+
 fn copy_operation(
     from: &PathBuf,
     to: &PathBuf,
     collision_resolution: Option<CollisionResolution>,
+    exclude_hidden: bool,
+    on_copied: Option<OnCopied>,
 ) -> Result<bool, FileError> {
     if to.exists() {
         match collision_resolution {
             Some(CollisionResolution::Overwrite) => (),
             Some(CollisionResolution::Skip) => return Ok(false),
-            None => unreachable!(),
+            Some(CollisionResolution::Rename) | None => unreachable!(),
         }
     }
-    if from.is_dir() {
-        copy_dir(from, to).map_err(|source| FileError::Copy {
+    if symlink_metadata(from).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        let copy_error = |source| FileError::Copy {
+            from_path: from.clone(),
+            to_path: to.clone(),
+            source,
+        };
+        let target = read_link(from).map_err(copy_error)?;
+        if symlink_metadata(to).is_ok() {
+            remove_file(to).map_err(copy_error)?;
+        }
+        symlink(target, to).map_err(copy_error)?;
+        Ok(true)
+    } else if from.is_dir() {
+        match on_copied {
+            Some(on_copied) => copy_dir_walk(from, to, exclude_hidden, on_copied),
+            None if exclude_hidden => copy_dir_walk(from, to, true, &mut |_, _| ()),
+            None => copy_dir(from, to),
+        }
+        .map_err(|source| FileError::Copy {
             from_path: from.clone(),
             to_path: to.clone(),
             source,
         })?;
         Ok(true)
     } else {
-        copy(from, to).map_err(|source| FileError::Copy {
+        let size = copy(from, to).map_err(|source| FileError::Copy {
             from_path: from.clone(),
             to_path: to.clone(),
             source,
         })?;
+        if let Some(on_copied) = on_copied {
+            on_copied(to, size);
+        }
         Ok(true)
     }
 }
 
+fn is_hidden(name: &OsStr) -> bool {
+    name.as_bytes().first() == Some(&b'.')
+}
+
+// Copies the tree one file at a time, so hidden entries can be left out and each copied file
+// can be reported.
+fn copy_dir_walk(
+    from: &Path,
+    to: &Path,
+    exclude_hidden: bool,
+    on_copied: OnCopied,
+) -> Result<(), IoError> {
+    create_dir_all(to)?;
+    for child in read_dir(from)? {
+        let child = child?;
+        if exclude_hidden && is_hidden(&child.file_name()) {
+            continue;
+        }
+        let from_child = child.path();
+        let to_child = to.join(child.file_name());
+        let file_type = child.file_type()?;
+        if file_type.is_symlink() {
+            symlink(read_link(&from_child)?, &to_child)?;
+        } else if file_type.is_dir() {
+            copy_dir_walk(&from_child, &to_child, exclude_hidden, on_copied)?;
+        } else {
+            let size = copy(&from_child, &to_child)?;
+            on_copied(&to_child, size);
+        }
+    }
+    Ok(())
+}
+
+// Returns whether the entry was pasted and whether it was copied because the link could not be
+// created. Only cross-device and unsupported-operation errors fall back to a copy.
+fn link_or_copy(
+    create_link: fn(&Path, &Path) -> Result<(), IoError>,
+    from: &PathBuf,
+    link_target: &Path,
+    to: &PathBuf,
+    collision_resolution: Option<CollisionResolution>,
+    exclude_hidden: bool,
+    fallback_copy: bool,
+) -> Result<(bool, bool), FileError> {
+    match create_link(link_target, to) {
+        Ok(_) => Ok((true, false)),
+        Err(error)
+            if collision_resolution == Some(CollisionResolution::Skip)
+                && error.kind() == IoErrorKind::AlreadyExists =>
+        {
+            Ok((false, false))
+        }
+        Err(error)
+            if fallback_copy
+                && matches!(
+                    error.kind(),
+                    IoErrorKind::CrossesDevices | IoErrorKind::Unsupported
+                ) =>
+        {
+            copy_operation(from, to, collision_resolution, exclude_hidden, None)
+                .map(|pasted| (pasted, true))
+        }
+        Err(error) => Err(FileError::Link {
+            from_path: from.clone(),
+            to_path: to.clone(),
+            source: error,
+        }),
+    }
+}
+
 fn move_operation(
     from: &PathBuf,
     to: &PathBuf,
@@ -432,7 +2340,7 @@ fn move_operation(
         match collision_resolution {
             Some(CollisionResolution::Overwrite) => (),
             Some(CollisionResolution::Skip) => return Ok(false),
-            None => unreachable!(),
+            Some(CollisionResolution::Rename) | None => unreachable!(),
         }
     }
     rename(from, to).map_err(|source| FileError::Move {
@@ -443,6 +2351,50 @@ fn move_operation(
     Ok(true)
 }
 
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+// A failed attempt can leave a partial copy behind, so retries always overwrite the target.
+fn with_retry<F>(
+    retries: u32,
+    backoff: Duration,
+    collision_resolution: Option<CollisionResolution>,
+    mut operation: F,
+) -> Result<bool, FileError>
+where
+    F: FnMut(Option<CollisionResolution>) -> Result<bool, FileError>,
+{
+    let mut result = operation(collision_resolution);
+    for attempt in 1..=retries {
+        match &result {
+            Err(error) if is_transient(error) => {
+                sleep(backoff * attempt);
+                result = operation(Some(CollisionResolution::Overwrite));
+            }
+            _ => break,
+        }
+    }
+    result
+}
+
+fn is_transient(error: &FileError) -> bool {
+    match error {
+        FileError::Copy { source, .. } | FileError::Move { source, .. } => matches!(
+            source.kind(),
+            IoErrorKind::Interrupted | IoErrorKind::WouldBlock | IoErrorKind::TimedOut
+        ),
+        _ => false,
+    }
+}
+
+fn source_vanished(error: &FileError, source_path: &Path) -> bool {
+    match error {
+        FileError::Copy { source, .. } | FileError::Move { source, .. } => {
+            source.kind() == IoErrorKind::NotFound && symlink_metadata(source_path).is_err()
+        }
+        _ => false,
+    }
+}
+
 fn ensure_dir(path: &Path) -> Result<(), IoError> {
     if path.exists() {
         if path.is_dir() {
@@ -468,6 +2420,7 @@ mod tests {
     use super::*;
     use crate::{
         models::Operation,
+        records::{MAX_CLIPBOARD_ENTRIES, read_entries},
         test_helpers::{
             create_file_and_get_metadata, create_mock_record_entry, create_test_file,
             get_test_entry, setup_test_env,
@@ -503,6 +2456,46 @@ mod tests {
         CollisionResolutionChoice::SkipAll
     }
 
+    // Simulates the source disappearing between the validity check and the copy by removing
+    // the matching file from the test env's source dir before answering the prompt.
+    fn mock_collision_remove_source(path: &Path) -> CollisionResolutionChoice {
+        let source_path = path
+            .parent()
+            .unwrap()
+            .with_file_name("source")
+            .join(path.file_name().unwrap());
+        std::fs::remove_file(source_path).unwrap();
+        CollisionResolutionChoice::Yes
+    }
+
+    // Confirms every entry except ones named "skip.txt", and stops at ones named "quit.txt".
+    fn mock_paste_confirmation_choice(from: &Path, _: &Path) -> PasteConfirmationChoice {
+        match from.file_name().and_then(OsStr::to_str) {
+            Some("skip.txt") => PasteConfirmationChoice::No,
+            Some("quit.txt") => PasteConfirmationChoice::Quit,
+            _ => PasteConfirmationChoice::Yes,
+        }
+    }
+
+    // Quits at the first prompt after checking that earlier pastes were already persisted.
+    fn mock_collision_quit_after_flush(_: &Path) -> CollisionResolutionChoice {
+        assert_eq!(read_history().unwrap().unwrap().len(), 1);
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 1);
+        CollisionResolutionChoice::Quit
+    }
+
+    fn mock_move_unreachable(_: usize, _: &Path) -> bool {
+        panic!("Move confirmation should not be shown for this paste")
+    }
+
+    fn mock_move_declined(_: usize, _: &Path) -> bool {
+        false
+    }
+
+    fn mock_collision_unreachable(_: &Path) -> CollisionResolutionChoice {
+        panic!("Collision prompt should not be shown when a conflict policy is set")
+    }
+
     #[test]
     #[serial]
     fn test_handle_transfer_copy() {
@@ -510,7 +2503,12 @@ mod tests {
         let file_path = env.source_dir.join("a.txt");
         create_test_file(&file_path, "a");
 
-        let (infos, warnings) = handle_transfer(vec![&file_path], Operation::Copy).unwrap();
+        let (infos, warnings) = handle_transfer(
+            vec![&file_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -530,9 +2528,13 @@ mod tests {
         let entry = get_test_entry(&file_path, Operation::Copy);
         write_clipboard(&[entry]).unwrap();
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -541,6 +2543,36 @@ mod tests {
 
         let history = read_history().unwrap().unwrap();
         assert_eq!(history.len(), 1);
+        assert_eq!(
+            history[0].destination,
+            Some(canonicalize(&env.dest_dir).unwrap())
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_removes_pasted_copies_and_links_from_clipboard() {
+        let env = setup_test_env();
+        let copied_path = env.source_dir.join("copied.txt");
+        let linked_path = env.source_dir.join("linked.txt");
+        create_test_file(&copied_path, "copied");
+        create_test_file(&linked_path, "linked");
+        write_clipboard(&[
+            get_test_entry(&copied_path, Operation::Copy),
+            get_test_entry(&linked_path, Operation::Link),
+        ])
+        .unwrap();
+
+        handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_unreachable,
+        )
+        .unwrap();
+
+        assert!(copied_path.exists());
+        assert!(read_clipboard().unwrap().unwrap().is_empty());
     }
 
     #[test]
@@ -552,9 +2584,13 @@ mod tests {
         let entry = get_test_entry(&file_path, Operation::Cut);
         write_clipboard(&[entry]).unwrap();
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -576,8 +2612,13 @@ mod tests {
         let entry = get_test_entry(&file_path, Operation::Link);
         write_clipboard(&[entry]).unwrap();
 
-        handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-            .unwrap();
+        handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         let dest_link_path = env.dest_dir.join("a.txt");
         assert!(dest_link_path.exists());
@@ -603,12 +2644,19 @@ mod tests {
         );
         write_clipboard(std::slice::from_ref(&entry)).unwrap();
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         assert!(infos.is_empty());
-        assert!(warnings.is_empty());
+        assert!(matches!(
+            warnings[..],
+            [AppWarning::Record(RecordWarning::NothingToPaste)]
+        ));
 
         let clipboard = read_clipboard().unwrap().unwrap();
         assert_eq!(clipboard.len(), 1);
@@ -626,9 +2674,13 @@ mod tests {
         let destination_file_path = env.dest_dir.join("a.txt");
         create_test_file(&destination_file_path, "a");
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_quit)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_quit,
+        )
+        .unwrap();
 
         assert!(infos.is_empty());
         assert!(warnings.is_empty());
@@ -649,9 +2701,13 @@ mod tests {
         let destination_file_path = env.dest_dir.join("a.txt");
         create_test_file(&destination_file_path, "destination content");
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_no)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_no,
+        )
+        .unwrap();
 
         assert!(infos.is_empty());
         assert!(warnings.is_empty());
@@ -685,6 +2741,7 @@ mod tests {
         let (infos, warnings) = handle_paste_with_prompt(
             &env.dest_dir,
             None,
+            &PasteOptions::default(),
             mock_collision_resolution_choice_skip_all,
         )
         .unwrap();
@@ -713,34 +2770,591 @@ mod tests {
         create_test_file(&file_c_path, "c");
 
         let glob_path = env.source_dir.join("*.txt");
-        let (expanded, warnings) = expand_paths(vec![glob_path.clone()]).unwrap();
+        let (expanded, warnings) = expand_paths(vec![glob_path.clone()], false, false).unwrap();
         assert_eq!(expanded.len(), 2);
         assert!(expanded.contains(&file_a_path));
         assert!(expanded.contains(&file_b_path));
         assert!(warnings.is_empty());
 
         let unmatched_glob_path = env.source_dir.join("*.md");
-        let (expanded, warnings) = expand_paths(vec![unmatched_glob_path.clone()]).unwrap();
+        let (expanded, warnings) =
+            expand_paths(vec![unmatched_glob_path.clone()], false, false).unwrap();
         assert!(expanded.is_empty());
         assert!(!warnings.is_empty());
         assert!(matches!(
             warnings[0],
-            AppWarning::File(FileWarning::GlobUnmatched { .. })
+            AppWarning::File(FileWarning::PathMissing { .. })
         ));
 
-        let (expanded, warnings) = expand_paths(vec![file_c_path.clone()]).unwrap();
+        let (expanded, warnings) = expand_paths(vec![file_c_path.clone()], false, false).unwrap();
         assert_eq!(expanded.len(), 1);
         assert_eq!(expanded[0], file_c_path);
         assert!(warnings.is_empty());
     }
 
-    #[test]
-    fn test_check_validity_happy_path() {
-        let dir = tempdir().expect("Failed to create temp dir");
-        let metadata = create_file_and_get_metadata(&dir, "valid.txt", "hello");
+    #[cfg(feature = "os-clipboard")]
+    fn mock_os_clipboard() -> Result<String, FileError> {
+        Ok("# copied from a file manager\na.txt\n\nmissing.txt\n  b.txt  \n".to_string())
+    }
 
-        let entry = create_mock_record_entry(
-            Some(metadata.absolute_path),
+    #[cfg(feature = "os-clipboard")]
+    fn mock_os_clipboard_uris() -> Result<String, FileError> {
+        Ok([
+            "file:///tmp/with%20space.txt",
+            "file://localhost/tmp/caf%C3%A9.txt",
+            "file://elsewhere/tmp/remote.txt",
+            "file:///tmp/100%.txt",
+        ]
+        .join("\r\n"))
+    }
+
+    #[cfg(feature = "os-clipboard")]
+    #[test]
+    fn test_get_clipboard_paths_decodes_file_uris() {
+        let paths = get_clipboard_paths(mock_os_clipboard_uris).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/tmp/with space.txt"),
+                PathBuf::from("/tmp/café.txt"),
+                PathBuf::from("file://elsewhere/tmp/remote.txt"),
+                PathBuf::from("/tmp/100%.txt"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "os-clipboard")]
+    #[test]
+    #[serial]
+    fn test_stage_clipboard_paths() {
+        let env = setup_test_env();
+        create_test_file(&env.source_dir.join("a.txt"), "a");
+        create_test_file(&env.source_dir.join("b.txt"), "b");
+
+        let paths = get_clipboard_paths(mock_os_clipboard).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("a.txt"),
+                PathBuf::from("missing.txt"),
+                PathBuf::from("b.txt"),
+            ]
+        );
+
+        let options = TransferOptions {
+            cwd: Some(env.source_dir.clone()),
+            keep_going: true,
+            from_clipboard_paths: true,
+            ..Default::default()
+        };
+        let (infos, warnings) = handle_transfer(paths, Operation::Copy, &options).unwrap();
+        assert_eq!(infos.len(), 2);
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::PathMissing { path })] if path.ends_with("missing.txt")
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_keeps_argument_order() {
+        let env = setup_test_env();
+        let names = ["earlier.txt", "a.txt", "b.txt", "c.txt"];
+        for name in names {
+            create_test_file(&env.source_dir.join(name), name);
+        }
+        let path = |name: &str| env.source_dir.join(name);
+        handle_transfer(
+            vec![path("earlier.txt")],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+
+        handle_transfer(
+            vec![path("a.txt"), path("b.txt"), path("c.txt")],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+
+        let listed: Vec<PathBuf> = read_entries(&RecordType::Clipboard)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(
+            listed,
+            ["a.txt", "b.txt", "c.txt", "earlier.txt"]
+                .map(path)
+                .to_vec()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_sort_on_stage() {
+        let env = setup_test_env();
+        let paths: Vec<PathBuf> = [("c.txt", "c"), ("a.txt", "aaa"), ("b.txt", "bb")]
+            .iter()
+            .map(|(name, content)| {
+                let path = env.source_dir.join(name);
+                create_test_file(&path, content);
+                path
+            })
+            .collect();
+        let clipboard_names = || -> Vec<String> {
+            read_clipboard()
+                .unwrap()
+                .unwrap()
+                .iter()
+                .map(|entry| {
+                    entry
+                        .path
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect()
+        };
+
+        for (sort, expected) in [
+            (PasteSort::Path, ["a.txt", "b.txt", "c.txt"]),
+            (PasteSort::Size, ["c.txt", "b.txt", "a.txt"]),
+        ] {
+            let options = TransferOptions {
+                sort_on_stage: Some(sort),
+                replace: true,
+                ..Default::default()
+            };
+            handle_transfer(paths.clone(), Operation::Copy, &options).unwrap();
+            assert_eq!(clipboard_names(), expected);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_quiet_unmatched() {
+        let env = setup_test_env();
+        let path = env.source_dir.join("a.txt");
+        create_test_file(&path, "a");
+        let options = TransferOptions {
+            quiet_unmatched: true,
+            ..Default::default()
+        };
+
+        let (_, warnings) = handle_transfer(
+            vec![env.source_dir.join("*.md"), env.source_dir.join("*.txt")],
+            Operation::Copy,
+            &options,
+        )
+        .unwrap();
+
+        assert!(warnings.is_empty());
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].path, path);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_only_entry_type() {
+        let env = setup_test_env();
+        create_test_file(&env.source_dir.join("a.txt"), "a");
+        create_test_file(&env.source_dir.join("b.txt"), "b");
+        create_dir_all(env.source_dir.join("sub")).unwrap();
+        let pattern = env.source_dir.join("*");
+
+        for (kept, staged, skipped) in [(EntryType::File, 2, 1), (EntryType::Directory, 1, 2)] {
+            let options = TransferOptions {
+                only_entry_type: Some(kept.clone()),
+                replace: true,
+                ..Default::default()
+            };
+            let (infos, _) = handle_transfer(vec![&pattern], Operation::Copy, &options).unwrap();
+
+            let clipboard = read_clipboard().unwrap().unwrap();
+            assert_eq!(clipboard.len(), staged);
+            assert!(clipboard.iter().all(|entry| entry.entry_type == kept));
+            assert!(infos.iter().any(|info| matches!(
+                info,
+                AppInfo::EntryTypeFiltered { count, .. } if *count == skipped
+            )));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_replace() {
+        let env = setup_test_env();
+        let old_path = env.source_dir.join("old.txt");
+        let new_paths = [env.source_dir.join("a.txt"), env.source_dir.join("b.txt")];
+        create_test_file(&old_path, "old");
+        for path in &new_paths {
+            create_test_file(path, "new");
+        }
+        handle_transfer(vec![&old_path], Operation::Cut, &TransferOptions::default()).unwrap();
+        let options = TransferOptions {
+            replace: true,
+            ..Default::default()
+        };
+
+        handle_transfer(new_paths.iter().collect(), Operation::Copy, &options).unwrap();
+
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 2);
+        assert!(
+            clipboard
+                .iter()
+                .all(|entry| entry.operation == Operation::Copy)
+        );
+        assert!(
+            clipboard
+                .iter()
+                .all(|entry| new_paths.contains(&entry.path))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_with_cwd() {
+        let env = setup_test_env();
+        create_test_file(&env.source_dir.join("a.txt"), "a");
+        create_test_file(&env.source_dir.join("b.txt"), "b");
+        create_test_file(&env.source_dir.join("c.log"), "c");
+        let absolute_path = env.dest_dir.join("d.txt");
+        create_test_file(&absolute_path, "d");
+        let options = TransferOptions {
+            cwd: Some(env.source_dir.clone()),
+            ..Default::default()
+        };
+
+        let (infos, warnings) = handle_transfer(
+            vec![PathBuf::from("*.txt"), absolute_path.clone()],
+            Operation::Copy,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 3);
+        assert!(warnings.is_empty());
+        let mut paths: Vec<PathBuf> = read_clipboard()
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                canonicalize(&absolute_path).unwrap(),
+                canonicalize(env.source_dir.join("a.txt")).unwrap(),
+                canonicalize(env.source_dir.join("b.txt")).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_expand_paths_keep_going_with_missing_literal() {
+        let env = setup_test_env();
+        let existing_path = env.source_dir.join("a.txt");
+        create_test_file(&existing_path, "a");
+        let missing_path = env.source_dir.join("missing.txt");
+        let unmatched_glob_path = env.source_dir.join("*.md");
+
+        let (expanded, warnings) = expand_paths(
+            vec![
+                existing_path.clone(),
+                missing_path.clone(),
+                unmatched_glob_path.clone(),
+            ],
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(expanded, vec![existing_path.clone()]);
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            &warnings[0],
+            AppWarning::File(FileWarning::PathMissing { path }) if path == &missing_path
+        ));
+        assert!(matches!(
+            &warnings[1],
+            AppWarning::File(FileWarning::PathMissing { path }) if path == &unmatched_glob_path
+        ));
+
+        let (expanded, warnings) = expand_paths(
+            vec![existing_path.clone(), missing_path.clone()],
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(expanded, vec![existing_path, missing_path]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_keep_going_with_missing_literal() {
+        let env = setup_test_env();
+        let existing_path = env.source_dir.join("a.txt");
+        create_test_file(&existing_path, "a");
+        let missing_path = env.source_dir.join("missing.txt");
+
+        let result = handle_transfer(
+            vec![&existing_path, &missing_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(AppError::File(FileError::PathNotFound { .. }))
+        ));
+        assert!(read_clipboard().unwrap().is_none());
+
+        let options = TransferOptions {
+            keep_going: true,
+            ..Default::default()
+        };
+        let (infos, warnings) = handle_transfer(
+            vec![&existing_path, &missing_path],
+            Operation::Copy,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_follow_into_history() {
+        let env = setup_test_env();
+        let file_path = env.dest_dir.join("a.txt");
+        create_test_file(&file_path, "a");
+        let pasted = RecordEntry {
+            path: canonicalize(&file_path).unwrap(),
+            timestamp: SystemTime::now() - Duration::from_secs(120),
+            ..get_test_entry(&file_path, Operation::Copy)
+        };
+        write_history(&[pasted]).unwrap();
+
+        let options = TransferOptions {
+            follow_into_history: Some(Duration::from_secs(600)),
+            ..Default::default()
+        };
+        let (infos, warnings) =
+            handle_transfer(vec![&file_path], Operation::Copy, &options).unwrap();
+        assert_eq!(infos.len(), 1);
+        // Timestamps are stored with second precision, so the seeded age can round down.
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::RecentlyPasted { ago, .. })]
+                if ago.as_secs() >= 119
+        ));
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 1);
+
+        let options = TransferOptions {
+            follow_into_history: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let (_, warnings) = handle_transfer(vec![&file_path], Operation::Copy, &options).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_follow_into_history_uses_paste_time() {
+        let env = setup_test_env();
+        let file_path = env.dest_dir.join("a.txt");
+        create_test_file(&file_path, "a");
+        let pasted = RecordEntry {
+            path: canonicalize(&file_path).unwrap(),
+            timestamp: SystemTime::now() - Duration::from_secs(3600),
+            pasted_at: Some(SystemTime::now() - Duration::from_secs(30)),
+            ..get_test_entry(&file_path, Operation::Copy)
+        };
+        write_history(&[pasted]).unwrap();
+
+        let options = TransferOptions {
+            follow_into_history: Some(Duration::from_secs(600)),
+            ..Default::default()
+        };
+        let (_, warnings) = handle_transfer(vec![&file_path], Operation::Copy, &options).unwrap();
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::RecentlyPasted { ago, .. })]
+                if ago.as_secs() < 600
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_atomic_batch_leaves_clipboard_untouched() {
+        let env = setup_test_env();
+        let staged_path = env.source_dir.join("staged.txt");
+        let existing_path = env.source_dir.join("a.txt");
+        create_test_file(&staged_path, "staged");
+        create_test_file(&existing_path, "a");
+        handle_transfer(
+            vec![&staged_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+
+        let options = TransferOptions {
+            atomic_batch: true,
+            ..Default::default()
+        };
+        let result = handle_transfer(
+            vec![
+                existing_path.clone(),
+                env.source_dir.join("missing1.txt"),
+                env.source_dir.join("missing2.txt"),
+            ],
+            Operation::Cut,
+            &options,
+        );
+        let Err(AppError::File(FileError::AtomicBatch { errors })) = result else {
+            panic!("expected an atomic batch error");
+        };
+        assert_eq!(errors.len(), 2);
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].path, canonicalize(&staged_path).unwrap());
+
+        let (infos, _) = handle_transfer(vec![&existing_path], Operation::Cut, &options).unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_filter_invalid_entries() {
+        let dir = tempdir().unwrap();
+        let valid_path = dir.path().join("valid.txt");
+        let drifted_path = dir.path().join("drifted.txt");
+        create_test_file(&valid_path, "valid");
+        create_test_file(&drifted_path, "drifted");
+        let valid = get_test_entry(&valid_path, Operation::Copy);
+        let drifted = RecordEntry {
+            size: Some(1),
+            ..get_test_entry(&drifted_path, Operation::Copy)
+        };
+        let missing =
+            create_mock_record_entry(Some(dir.path().join("missing.txt")), None, None, None, None);
+
+        let (valid_entries, invalid_entries, warnings) =
+            filter_invalid_entries(vec![valid.clone(), missing.clone(), drifted.clone()]);
+        let ids =
+            |entries: &[RecordEntry]| entries.iter().map(|entry| entry.id).collect::<Vec<_>>();
+        assert_eq!(ids(&valid_entries), vec![valid.id, drifted.id]);
+        assert_eq!(ids(&invalid_entries), vec![missing.id]);
+        assert!(matches!(&warnings[..], [FileWarning::SizeMismatch { .. }]));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_keeps_invalid_entries() {
+        let env = setup_test_env();
+        let valid_path = env.source_dir.join("a.txt");
+        create_test_file(&valid_path, "a");
+        let valid = get_test_entry(&valid_path, Operation::Copy);
+        let missing = create_mock_record_entry(
+            Some(env.source_dir.join("missing.txt")),
+            Some(Operation::Copy),
+            None,
+            None,
+            None,
+        );
+        write_clipboard(&[missing.clone(), valid]).unwrap();
+
+        let (infos, _) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_unreachable,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(env.dest_dir.join("a.txt").exists());
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].id, missing.id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_into_temp_destination() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "a");
+        write_clipboard(&[get_test_entry(&file_path, Operation::Copy)]).unwrap();
+
+        let destination = create_temp_destination().unwrap();
+        assert!(destination.starts_with(temp_dir()));
+        let (infos, _) = handle_paste(&destination, None, &PasteOptions::default()).unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(destination.join("a.txt")).unwrap(),
+            "a"
+        );
+        let other_destination = create_temp_destination().unwrap();
+        assert_ne!(other_destination, destination);
+        remove_dir_all(&destination).unwrap();
+        remove_dir_all(&other_destination).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_sort() {
+        let env = setup_test_env();
+        let mut entries = Vec::new();
+        for (name, content) in [("b.txt", "ccc"), ("c.txt", "a"), ("a.txt", "bb")] {
+            let file_path = env.source_dir.join(name);
+            create_test_file(&file_path, content);
+            entries.push(get_test_entry(&file_path, Operation::Copy));
+        }
+        let pasted_names = |infos: Vec<AppInfo>| {
+            infos
+                .into_iter()
+                .map(|info| match info {
+                    AppInfo::Paste { path } => path.file_name().unwrap().to_owned(),
+                    info => panic!("unexpected info {:?}", info),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (sort, expected) in [
+            (PasteSort::Size, ["c.txt", "a.txt", "b.txt"]),
+            (PasteSort::Path, ["a.txt", "b.txt", "c.txt"]),
+        ] {
+            write_clipboard(&entries).unwrap();
+            let options = PasteOptions {
+                sort: Some(sort),
+                conflict: Some(ConflictPolicy::Overwrite),
+                ..Default::default()
+            };
+            let (infos, _) =
+                handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                    .unwrap();
+            assert_eq!(pasted_names(infos), expected);
+        }
+    }
+
+    #[test]
+    fn test_check_validity_happy_path() {
+        let dir = tempdir().expect("Failed to create temp dir");
+        let metadata = create_file_and_get_metadata(&dir, "valid.txt", "hello");
+
+        let entry = create_mock_record_entry(
+            Some(metadata.absolute_path),
             Some(Operation::Copy),
             Some(metadata.entry_type),
             Some(metadata.modified),
@@ -855,7 +3469,7 @@ mod tests {
         let mut file = File::create(&file_path).unwrap();
         writeln!(file, "hello").unwrap();
 
-        let metadata = get_metadata(&file_path).unwrap();
+        let metadata = get_metadata(&file_path, false).unwrap();
 
         assert_eq!(metadata.entry_type, EntryType::File);
         assert_eq!(metadata.size, Some(6)); // "hello\n"
@@ -867,7 +3481,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let dir_path = dir.path();
 
-        let metadata = get_metadata(dir_path).unwrap();
+        let metadata = get_metadata(dir_path, false).unwrap();
 
         assert_eq!(metadata.entry_type, EntryType::Directory);
         assert_eq!(metadata.size, None);
@@ -884,9 +3498,45 @@ mod tests {
         let symlink_path = dir.path().join("link.txt");
         symlink(&file_path, &symlink_path).unwrap();
 
-        let metadata = get_metadata(&symlink_path).unwrap();
+        let metadata = get_metadata(&symlink_path, false).unwrap();
+
+        assert_eq!(metadata.entry_type, EntryType::Symlink);
+    }
+
+    #[test]
+    fn test_get_metadata_follow_symlinks_classification() {
+        let dir = tempdir().unwrap();
+        let target_dir = dir.path().join("target");
+        create_dir_all(&target_dir).unwrap();
+        let symlink_path = dir.path().join("link");
+        symlink(&target_dir, &symlink_path).unwrap();
+
+        let not_followed = get_metadata(&symlink_path, false).unwrap();
+        assert_eq!(not_followed.entry_type, EntryType::Symlink);
+        assert_eq!(
+            not_followed.absolute_path,
+            canonicalize(dir.path()).unwrap().join("link")
+        );
+
+        let followed = get_metadata(&symlink_path, true).unwrap();
+        assert_eq!(followed.entry_type, EntryType::Directory);
+        assert_eq!(followed.absolute_path, canonicalize(&target_dir).unwrap());
+    }
 
+    #[test]
+    fn test_get_metadata_for_dangling_symlink() {
+        let dir = tempdir().unwrap();
+        let symlink_path = dir.path().join("dangling");
+        symlink(dir.path().join("missing"), &symlink_path).unwrap();
+
+        let metadata = get_metadata(&symlink_path, false).unwrap();
         assert_eq!(metadata.entry_type, EntryType::Symlink);
+        assert!(metadata.canonical);
+
+        let followed = get_metadata(&symlink_path, true).unwrap();
+        assert_eq!(followed.entry_type, EntryType::Symlink);
+        assert_eq!(followed.absolute_path, metadata.absolute_path);
+        assert!(!followed.canonical);
     }
 
     #[test]
@@ -894,7 +3544,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("non_existent.txt");
 
-        let result = get_metadata(&file_path);
+        let result = get_metadata(&file_path, false);
         assert!(result.is_err());
 
         match result.unwrap_err() {
@@ -918,6 +3568,7 @@ mod tests {
         let (infos, warnings) = handle_paste_with_prompt(
             &env.dest_dir,
             None,
+            &PasteOptions::default(),
             mock_collision_resolution_choice_overwrite_all,
         )
         .unwrap();
@@ -941,9 +3592,13 @@ mod tests {
 
         let dest_path = env.dest_dir.join("b.txt");
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&dest_path, None, mock_collision_resolution_choice_yes)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &dest_path,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -966,9 +3621,13 @@ mod tests {
         let entry = get_test_entry(&source_dir, Operation::Copy);
         write_clipboard(&[entry]).unwrap();
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -991,12 +3650,16 @@ mod tests {
         let entry = get_test_entry(&source_dir, Operation::Cut);
         write_clipboard(&[entry]).unwrap();
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-                .unwrap();
-
-        assert_eq!(infos.len(), 1);
-        assert!(warnings.is_empty());
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(warnings.is_empty());
 
         let dest_dir = env.dest_dir.join("dir_a");
         assert!(dest_dir.exists());
@@ -1005,6 +3668,203 @@ mod tests {
         assert!(!source_dir.exists());
     }
 
+    #[test]
+    #[serial]
+    fn test_handle_transfer_if_newer() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("restaged.txt");
+        create_test_file(&file_path, "content");
+        let options = TransferOptions {
+            if_newer: true,
+            ..Default::default()
+        };
+
+        handle_transfer(vec![&file_path], Operation::Copy, &options).unwrap();
+        let staged = read_clipboard().unwrap().unwrap();
+        assert_eq!(staged.len(), 1);
+
+        let (infos, _) = handle_transfer(vec![&file_path], Operation::Copy, &options).unwrap();
+        assert!(matches!(infos[0], AppInfo::NotNewer { .. }));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].id, staged[0].id);
+
+        File::options()
+            .write(true)
+            .open(&file_path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+        let (infos, _) = handle_transfer(vec![&file_path], Operation::Copy, &options).unwrap();
+        assert!(matches!(infos[0], AppInfo::Copy { .. }));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_ne!(clipboard[0].id, staged[0].id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_symlink_without_follow() {
+        let env = setup_test_env();
+        let target_path = env.source_dir.join("target.txt");
+        let link_path = env.source_dir.join("link.txt");
+        create_test_file(&target_path, "target");
+        symlink(&target_path, &link_path).unwrap();
+
+        handle_transfer(
+            vec![&link_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard[0].entry_type, EntryType::Symlink);
+        assert_eq!(clipboard[0].path.file_name().unwrap(), "link.txt");
+
+        handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_unreachable,
+        )
+        .unwrap();
+        let pasted_path = env.dest_dir.join("link.txt");
+        assert!(
+            symlink_metadata(&pasted_path)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+        assert_eq!(read_link(&pasted_path).unwrap(), target_path);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_symlink_with_follow() {
+        let env = setup_test_env();
+        let target_path = env.source_dir.join("target.txt");
+        let link_path = env.source_dir.join("link.txt");
+        create_test_file(&target_path, "target");
+        symlink(&target_path, &link_path).unwrap();
+
+        let options = TransferOptions {
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        handle_transfer(vec![&link_path], Operation::Copy, &options).unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard[0].entry_type, EntryType::File);
+        assert_eq!(clipboard[0].path, canonicalize(&target_path).unwrap());
+
+        handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_unreachable,
+        )
+        .unwrap();
+        let pasted_path = env.dest_dir.join("target.txt");
+        assert!(
+            symlink_metadata(&pasted_path)
+                .unwrap()
+                .file_type()
+                .is_file()
+        );
+        assert_eq!(std::fs::read_to_string(&pasted_path).unwrap(), "target");
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_watch_stages_new_files() {
+        let env = setup_test_env();
+        create_test_file(&env.source_dir.join("existing.txt"), "existing");
+        let new_path = env.source_dir.join("new.txt");
+        let mut polls = 0;
+        let mut staged = Vec::new();
+
+        handle_watch(
+            vec![&env.source_dir],
+            &TransferOptions::default(),
+            Duration::from_millis(10),
+            |infos, _| {
+                polls += 1;
+                if polls == 1 {
+                    create_test_file(&new_path, "new");
+                }
+                staged.extend(infos);
+                polls < 3
+            },
+        )
+        .unwrap();
+
+        assert_eq!(staged.len(), 1);
+        assert!(matches!(&staged[0], AppInfo::Copy { path, .. } if *path == new_path));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].path.file_name().unwrap(), "new.txt");
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_dedup_by_content() {
+        let env = setup_test_env();
+        let first_path = env.source_dir.join("first.txt");
+        let second_path = env.source_dir.join("second.txt");
+        let other_path = env.source_dir.join("other.txt");
+        create_test_file(&first_path, "same");
+        create_test_file(&second_path, "same");
+        create_test_file(&other_path, "different");
+        let options = TransferOptions {
+            dedup_by: Some(DedupMode::Content),
+            ..Default::default()
+        };
+
+        let (infos, _) = handle_transfer(
+            vec![&first_path, &second_path, &other_path],
+            Operation::Copy,
+            &options,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            &infos[1],
+            AppInfo::Deduplicated { path, original } if *path == second_path && *original == first_path
+        ));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 2);
+        assert!(
+            clipboard
+                .iter()
+                .all(|entry| entry.path.file_name().unwrap() != "second.txt")
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_exclude_larger_than() {
+        let env = setup_test_env();
+        let small_path = env.source_dir.join("small.txt");
+        let large_path = env.source_dir.join("large.txt");
+        create_test_file(&small_path, "small");
+        create_test_file(&large_path, &"x".repeat(100));
+        let options = TransferOptions {
+            exclude_larger_than: Some(50),
+            ..Default::default()
+        };
+
+        let (infos, _) =
+            handle_transfer(vec![&small_path, &large_path], Operation::Copy, &options).unwrap();
+
+        assert!(matches!(infos[0], AppInfo::Copy { .. }));
+        assert!(matches!(
+            infos[1],
+            AppInfo::ExcludedLarger { size: 100, .. }
+        ));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].path.file_name().unwrap(), "small.txt");
+    }
+
     #[test]
     #[serial]
     fn test_handle_transfer_cut() {
@@ -1012,7 +3872,12 @@ mod tests {
         let file_path = env.source_dir.join("cut_test.txt");
         create_test_file(&file_path, "cut content");
 
-        let (infos, warnings) = handle_transfer(vec![&file_path], Operation::Cut).unwrap();
+        let (infos, warnings) = handle_transfer(
+            vec![&file_path],
+            Operation::Cut,
+            &TransferOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -1030,7 +3895,12 @@ mod tests {
         let file_path = env.source_dir.join("link_test.txt");
         create_test_file(&file_path, "link content");
 
-        let (infos, warnings) = handle_transfer(vec![&file_path], Operation::Link).unwrap();
+        let (infos, warnings) = handle_transfer(
+            vec![&file_path],
+            Operation::Link,
+            &TransferOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -1052,8 +3922,12 @@ mod tests {
         create_test_file(&file2, "two");
         create_test_file(&file3, "three");
 
-        let (infos, warnings) =
-            handle_transfer(vec![&file1, &file2, &file3], Operation::Copy).unwrap();
+        let (infos, warnings) = handle_transfer(
+            vec![&file1, &file2, &file3],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 3);
         assert!(warnings.is_empty());
@@ -1071,7 +3945,12 @@ mod tests {
         create_test_file(&env.source_dir.join("glob3.txt"), "text");
 
         let glob_pattern = env.source_dir.join("*.rs");
-        let (infos, warnings) = handle_transfer(vec![glob_pattern], Operation::Copy).unwrap();
+        let (infos, warnings) = handle_transfer(
+            vec![glob_pattern],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 2);
         assert!(warnings.is_empty());
@@ -1087,13 +3966,18 @@ mod tests {
         create_test_file(&env.source_dir.join("file.txt"), "content");
 
         let glob_pattern = env.source_dir.join("*.rs");
-        let (infos, warnings) = handle_transfer(vec![glob_pattern], Operation::Copy).unwrap();
+        let (infos, warnings) = handle_transfer(
+            vec![glob_pattern],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
 
         assert!(infos.is_empty());
         assert_eq!(warnings.len(), 1);
         assert!(matches!(
             warnings[0],
-            AppWarning::File(FileWarning::GlobUnmatched { .. })
+            AppWarning::File(FileWarning::PathMissing { .. })
         ));
     }
 
@@ -1113,8 +3997,12 @@ mod tests {
         let non_dir_dest = env.dest_dir.join("single_file.txt");
         create_test_file(&non_dir_dest, "destination");
 
-        let result =
-            handle_paste_with_prompt(&non_dir_dest, None, mock_collision_resolution_choice_yes);
+        let result = handle_paste_with_prompt(
+            &non_dir_dest,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        );
 
         assert!(result.is_err());
         match result.unwrap_err() {
@@ -1137,9 +4025,13 @@ mod tests {
         let dest_file = env.dest_dir.join("overwrite.txt");
         create_test_file(&dest_file, "original");
 
-        let (infos, warnings) =
-            handle_paste_with_prompt(&env.dest_dir, None, mock_collision_resolution_choice_yes)
-                .unwrap();
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
 
         assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
@@ -1148,194 +4040,2185 @@ mod tests {
         assert_eq!(content, "source");
     }
 
+    #[test]
+    fn test_render_template() {
+        let entry = create_mock_record_entry(
+            Some(PathBuf::from("/tmp/report.txt")),
+            Some(Operation::Cut),
+            None,
+            None,
+            None,
+        );
+        let date = Local::now().format("%Y-%m-%d").to_string();
+
+        assert_eq!(
+            render_template("{date}-{name}", &entry, 0).unwrap(),
+            format!("{}-report.txt", date)
+        );
+        assert_eq!(
+            render_template("{stem}_{op}_{index}.{ext}", &entry, 2).unwrap(),
+            "report_cut_3.txt"
+        );
+        assert_eq!(render_template("plain", &entry, 0).unwrap(), "plain");
+        assert!(matches!(
+            render_template("{unknown}", &entry, 0),
+            Err(FileError::InvalidTemplate { .. })
+        ));
+        assert!(matches!(
+            render_template("{name", &entry, 0),
+            Err(FileError::InvalidTemplate { .. })
+        ));
+        assert_eq!(
+            render_template("{op}/{name}", &entry, 0).unwrap(),
+            "cut/report.txt"
+        );
+        for template in ["../{name}", "/tmp/{name}", "./{name}", "{stem}/", ""] {
+            assert!(matches!(
+                render_template(template, &entry, 0),
+                Err(FileError::InvalidTemplatePath { .. })
+            ));
+        }
+    }
+
     #[test]
     #[serial]
-    fn test_handle_paste_overwrite_all_with_multiple_files() {
+    fn test_handle_paste_with_template() {
         let env = setup_test_env();
-        let file1 = env.source_dir.join("file1.txt");
-        let file2 = env.source_dir.join("file2.txt");
-        create_test_file(&file1, "source1");
-        create_test_file(&file2, "source2");
+        let first_path = env.source_dir.join("a.txt");
+        let second_path = env.source_dir.join("b.txt");
+        create_test_file(&first_path, "a");
+        create_test_file(&second_path, "b");
+        create_test_file(&env.dest_dir.join("1-a.txt"), "original");
+        write_clipboard(&[
+            get_test_entry(&first_path, Operation::Copy),
+            get_test_entry(&second_path, Operation::Copy),
+        ])
+        .unwrap();
 
-        let entry1 = get_test_entry(&file1, Operation::Copy);
-        let entry2 = get_test_entry(&file2, Operation::Copy);
-        write_clipboard(&[entry1, entry2]).unwrap();
+        let options = PasteOptions {
+            template: Some("{index}-{name}".to_string()),
+            conflict: Some(ConflictPolicy::Rename),
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
 
-        let dest1 = env.dest_dir.join("file1.txt");
-        let dest2 = env.dest_dir.join("file2.txt");
-        create_test_file(&dest1, "dest1");
-        create_test_file(&dest2, "dest2");
+        assert_eq!(infos.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("1-a (1).txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("2-b.txt")).unwrap(),
+            "b"
+        );
+    }
 
+    #[test]
+    #[serial]
+    fn test_handle_paste_empty_clipboard() {
+        let env = setup_test_env();
         let (infos, warnings) = handle_paste_with_prompt(
             &env.dest_dir,
             None,
-            mock_collision_resolution_choice_overwrite_all,
+            &PasteOptions::default(),
+            mock_collision_unreachable,
         )
         .unwrap();
 
-        assert_eq!(infos.len(), 2);
-        assert!(warnings.is_empty());
-
-        assert_eq!(std::fs::read_to_string(&dest1).unwrap(), "source1");
-        assert_eq!(std::fs::read_to_string(&dest2).unwrap(), "source2");
+        assert!(infos.is_empty());
+        assert!(matches!(
+            warnings[..],
+            [AppWarning::Record(RecordWarning::NothingToPaste)]
+        ));
+        assert!(read_history().unwrap().is_none());
     }
 
     #[test]
     #[serial]
-    fn test_expand_paths_with_mixed_patterns() {
+    fn test_handle_paste_everything_excluded() {
         let env = setup_test_env();
-        create_test_file(&env.source_dir.join("a.txt"), "a");
-        create_test_file(&env.source_dir.join("b.txt"), "b");
-        create_test_file(&env.source_dir.join("c.log"), "c");
-        create_test_file(&env.source_dir.join("specific.txt"), "specific");
-
-        let glob_path = env.source_dir.join("*.log");
-        let specific_path = env.source_dir.join("specific.txt");
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "a");
+        handle_transfer(
+            vec![&file_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        let options = PasteOptions {
+            exclude: vec!["*a.txt".to_string()],
+            ..Default::default()
+        };
 
-        let (expanded, warnings) = expand_paths(vec![glob_path, specific_path.clone()]).unwrap();
+        let (infos, warnings) = handle_paste(&env.dest_dir, None, &options).unwrap();
 
-        assert_eq!(expanded.len(), 2);
-        assert!(expanded.contains(&env.source_dir.join("c.log")));
-        assert!(expanded.contains(&specific_path));
-        assert!(warnings.is_empty());
+        assert!(infos.is_empty());
+        assert!(matches!(
+            warnings[..],
+            [AppWarning::Record(RecordWarning::NothingToPaste)]
+        ));
+        assert!(!env.dest_dir.join("a.txt").exists());
     }
 
     #[test]
-    fn test_get_metadata_relative_path() {
-        let dir = tempdir().unwrap();
-        let file_path = dir.path().join("relative_test.txt");
-        File::create(&file_path).unwrap();
+    #[serial]
+    fn test_handle_paste_exclude() {
+        let env = setup_test_env();
+        let kept_path = env.source_dir.join("keep.txt");
+        let first_excluded_path = env.source_dir.join("skip.log");
+        let second_excluded_path = env.source_dir.join("other.tmp");
+        create_test_file(&kept_path, "keep");
+        create_test_file(&first_excluded_path, "skip");
+        create_test_file(&second_excluded_path, "other");
+        write_clipboard(&[
+            get_test_entry(&kept_path, Operation::Copy),
+            get_test_entry(&first_excluded_path, Operation::Copy),
+            get_test_entry(&second_excluded_path, Operation::Copy),
+        ])
+        .unwrap();
 
-        let original_dir = std::env::current_dir().unwrap();
-        std::env::set_current_dir(dir.path()).unwrap();
+        let options = PasteOptions {
+            exclude: vec!["**/*.log".to_string(), "*.tmp".to_string()],
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
 
-        let metadata = get_metadata("relative_test.txt").unwrap();
-        assert_eq!(metadata.entry_type, EntryType::File);
-        assert!(metadata.absolute_path.is_absolute());
+        assert_eq!(infos.len(), 1);
+        assert!(env.dest_dir.join("keep.txt").exists());
+        assert!(!env.dest_dir.join("skip.log").exists());
+        assert!(!env.dest_dir.join("other.tmp").exists());
 
-        std::env::set_current_dir(original_dir).unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        let mut remaining: Vec<_> = clipboard
+            .iter()
+            .map(|entry| entry.path.file_name().unwrap().to_owned())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["other.tmp", "skip.log"]);
     }
 
     #[test]
-    fn test_get_absolute_path_relative() {
-        let dir = tempdir().unwrap();
-        let original_dir = std::env::current_dir().unwrap();
-        std::env::set_current_dir(dir.path()).unwrap();
-
-        let relative_path = PathBuf::from("relative_file.txt");
-        let absolute = get_absolute_path(&relative_path).unwrap();
-
-        assert!(absolute.is_absolute());
-        assert!(absolute.ends_with("relative_file.txt"));
+    #[serial]
+    fn test_handle_paste_invalid_exclude_pattern() {
+        setup_test_env();
+        let options = PasteOptions {
+            exclude: vec!["[".to_string()],
+            ..Default::default()
+        };
+        let result = handle_paste_with_prompt(".", None, &options, mock_collision_unreachable);
 
-        std::env::set_current_dir(original_dir).unwrap();
+        assert!(matches!(
+            result,
+            Err(AppError::File(FileError::GlobInvalidPattern { .. }))
+        ));
     }
 
     #[test]
-    fn test_get_absolute_path_already_absolute() {
-        let absolute_path = PathBuf::from("/tmp/absolute.txt");
-        let result = get_absolute_path(&absolute_path).unwrap();
-        assert_eq!(result, absolute_path);
+    #[serial]
+    fn test_handle_transfer_warns_when_clipboard_is_capped() {
+        let env = setup_test_env();
+        let existing_entries: Vec<RecordEntry> = (0..MAX_CLIPBOARD_ENTRIES)
+            .map(|_| create_mock_record_entry(None, None, None, None, None))
+            .collect();
+        write_clipboard(&existing_entries).unwrap();
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|index| {
+                let path = env.source_dir.join(format!("new_{}.txt", index));
+                create_test_file(&path, "new");
+                path
+            })
+            .collect();
+
+        let (_, warnings) =
+            handle_transfer(paths, Operation::Copy, &TransferOptions::default()).unwrap();
+
+        assert!(matches!(
+            warnings[..],
+            [AppWarning::Record(RecordWarning::ClipboardCapped {
+                dropped: 3,
+                max: MAX_CLIPBOARD_ENTRIES
+            })]
+        ));
+        assert_eq!(
+            read_clipboard().unwrap().unwrap().len(),
+            MAX_CLIPBOARD_ENTRIES
+        );
     }
 
     #[test]
     #[serial]
-    fn test_handle_paste_with_paste_content_clipboard() {
+    fn test_handle_transfer_max_entries_override() {
         let env = setup_test_env();
-        let file_path = env.source_dir.join("paste_content.txt");
-        create_test_file(&file_path, "content");
-        let entry = get_test_entry(&file_path, Operation::Copy);
-
-        let paste_content = PasteContent {
-            entries: vec![entry],
-            source: RecordType::Clipboard,
+        let existing_entries: Vec<RecordEntry> = (0..5)
+            .map(|_| create_mock_record_entry(None, None, None, None, None))
+            .collect();
+        write_clipboard(&existing_entries).unwrap();
+        let path = env.source_dir.join("newest.txt");
+        create_test_file(&path, "newest");
+
+        let options = TransferOptions {
+            max_entries: Some(2),
+            ..Default::default()
         };
+        let (_, warnings) = handle_transfer(vec![path.clone()], Operation::Copy, &options).unwrap();
 
-        let (infos, warnings) = handle_paste_with_prompt(
-            &env.dest_dir,
-            Some(paste_content),
-            mock_collision_resolution_choice_yes,
+        assert!(matches!(
+            warnings[..],
+            [AppWarning::Record(RecordWarning::ClipboardCapped {
+                dropped: 4,
+                max: 2
+            })]
+        ));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 2);
+        assert_eq!(clipboard[0].path, canonicalize(&path).unwrap());
+        assert_eq!(clipboard[1].id, existing_entries[0].id);
+
+        let other_path = env.source_dir.join("other.txt");
+        create_test_file(&other_path, "other");
+        let (_, warnings) = handle_transfer(
+            vec![other_path],
+            Operation::Copy,
+            &TransferOptions::default(),
         )
         .unwrap();
-
-        assert_eq!(infos.len(), 1);
         assert!(warnings.is_empty());
-        assert!(env.dest_dir.join("paste_content.txt").exists());
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 3);
     }
 
     #[test]
     #[serial]
-    fn test_handle_paste_with_paste_content_history() {
+    fn test_handle_transfer_below_cap_has_no_warning() {
         let env = setup_test_env();
-        let file_path = env.source_dir.join("history_paste.txt");
-        create_test_file(&file_path, "history content");
-        let entry = get_test_entry(&file_path, Operation::Copy);
+        let path = env.source_dir.join("single.txt");
+        create_test_file(&path, "single");
 
-        let paste_content = PasteContent {
-            entries: vec![entry],
+        let (_, warnings) =
+            handle_transfer(vec![path], Operation::Copy, &TransferOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[cfg(feature = "ownership")]
+    #[test]
+    fn test_resolve_ownership() {
+        assert_eq!(resolve_ownership(&Ownership::default()).unwrap(), None);
+        assert_eq!(
+            resolve_ownership(&Ownership {
+                owner: Some("0".to_string()),
+                group: Some("root".to_string()),
+            })
+            .unwrap(),
+            Some((Some(Uid::from_raw(0)), Some(Gid::from_raw(0))))
+        );
+        assert!(matches!(
+            resolve_ownership(&Ownership {
+                owner: Some("no-such-user-for-clp".to_string()),
+                group: None,
+            }),
+            Err(FileError::UnknownOwner { .. })
+        ));
+    }
+
+    #[cfg(feature = "ownership")]
+    #[test]
+    #[serial]
+    fn test_handle_paste_with_ownership() {
+        use std::os::unix::fs::MetadataExt;
+
+        if !Uid::effective().is_root() {
+            return;
+        }
+        let env = setup_test_env();
+        let source_dir = env.source_dir.join("owned");
+        create_dir_all(&source_dir).unwrap();
+        create_test_file(&source_dir.join("file.txt"), "content");
+        write_clipboard(&[get_test_entry(&source_dir, Operation::Copy)]).unwrap();
+
+        let options = PasteOptions {
+            ownership: Ownership {
+                owner: Some("65534".to_string()),
+                group: Some("65534".to_string()),
+            },
+            ..Default::default()
+        };
+        let (_, warnings) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert!(warnings.is_empty());
+        for path in [
+            env.dest_dir.join("owned"),
+            env.dest_dir.join("owned/file.txt"),
+        ] {
+            let metadata = symlink_metadata(path).unwrap();
+            assert_eq!((metadata.uid(), metadata.gid()), (65534, 65534));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_verify_type_skips_changed_entry() {
+        let env = setup_test_env();
+        let swapped_path = env.source_dir.join("swapped");
+        create_test_file(&swapped_path, "file");
+        let kept_path = env.source_dir.join("kept.txt");
+        create_test_file(&kept_path, "kept");
+        let swapped_entry = get_test_entry(&swapped_path, Operation::Copy);
+        write_clipboard(&[
+            swapped_entry.clone(),
+            get_test_entry(&kept_path, Operation::Copy),
+        ])
+        .unwrap();
+        remove_file(&swapped_path).unwrap();
+        create_dir_all(&swapped_path).unwrap();
+
+        let options = PasteOptions {
+            verify_type: true,
+            ..Default::default()
+        };
+        let (infos, warnings) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::TypeChangedSkipped { .. })]
+        ));
+        assert!(env.dest_dir.join("kept.txt").exists());
+        assert!(!env.dest_dir.join("swapped").exists());
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].id, swapped_entry.id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_with_chmod() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("file.txt");
+        create_test_file(&file_path, "content");
+        let source_dir = env.source_dir.join("assets");
+        create_dir_all(&source_dir).unwrap();
+        create_test_file(&source_dir.join("style.css"), "body {}");
+        write_clipboard(&[
+            get_test_entry(&file_path, Operation::Copy),
+            get_test_entry(&source_dir, Operation::Copy),
+        ])
+        .unwrap();
+
+        let options = PasteOptions {
+            file_mode: Some(0o600),
+            dir_mode: Some(0o600),
+            ..Default::default()
+        };
+        let (_, warnings) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert!(warnings.is_empty());
+        let mode = |path: PathBuf| metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode(env.dest_dir.join("file.txt")), 0o600);
+        assert_eq!(mode(env.dest_dir.join("assets")), 0o600);
+        set_permissions(env.dest_dir.join("assets"), Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(mode(env.dest_dir.join("assets/style.css")), 0o600);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_info() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("info.txt");
+        create_test_file(&file_path, "content");
+        let entry = get_test_entry(&file_path, Operation::Copy);
+        let missing_entry = create_mock_record_entry(
+            Some(env.source_dir.join("missing.txt")),
+            None,
+            None,
+            None,
+            None,
+        );
+        write_clipboard(std::slice::from_ref(&entry)).unwrap();
+        write_history(std::slice::from_ref(&missing_entry)).unwrap();
+
+        let info = handle_info(entry.id).unwrap().unwrap();
+        assert_eq!(info.record_type, RecordType::Clipboard);
+        assert!(info.exists);
+        assert_eq!(info.current_size, Some(7));
+        assert!(info.current_modified.is_some());
+
+        let info = handle_info(missing_entry.id).unwrap().unwrap();
+        assert_eq!(info.record_type, RecordType::History);
+        assert!(!info.exists);
+        assert!(info.drift.is_some());
+
+        assert!(handle_info(Uuid::new_v4()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_count_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        create_dir_all(root.join("nested")).unwrap();
+        create_test_file(&root.join("a.txt"), "a");
+        create_test_file(&root.join("nested").join("b.txt"), "b");
+        symlink(&root, root.join("nested").join("loop")).unwrap();
+
+        assert_eq!(count_files(&root, u64::MAX), 5);
+        assert_eq!(count_files(&root, 2), 2);
+        assert_eq!(count_files(&root.join("a.txt"), u64::MAX), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_check_available_inodes() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("file.txt");
+        create_test_file(&file_path, "content");
+        let entries = [get_test_entry(&file_path, Operation::Copy)];
+
+        assert!(check_available_inodes(&env.dest_dir, &entries).is_none());
+    }
+
+    #[test]
+    fn test_get_common_root() {
+        let paths = [Path::new("/a/b"), Path::new("/a/d"), Path::new("/a/b/c")];
+        assert_eq!(
+            get_common_root(paths.into_iter()),
+            Some(PathBuf::from("/a"))
+        );
+        assert_eq!(
+            get_common_root([Path::new("/a/b")].into_iter()),
+            Some(PathBuf::from("/a/b"))
+        );
+        assert_eq!(get_common_root(std::iter::empty()), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_preserve_structure() {
+        let env = setup_test_env();
+        let root = env.source_dir.join("a");
+        create_dir_all(root.join("b")).unwrap();
+        create_dir_all(root.join("d")).unwrap();
+        create_test_file(&root.join("b").join("c.txt"), "c");
+        create_test_file(&root.join("d").join("e.txt"), "e");
+        write_clipboard(&[
+            get_test_entry(&root.join("b").join("c.txt"), Operation::Copy),
+            get_test_entry(&root.join("d").join("e.txt"), Operation::Copy),
+        ])
+        .unwrap();
+
+        let options = PasteOptions {
+            preserve_structure: true,
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("b").join("c.txt")).unwrap(),
+            "c"
+        );
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("d").join("e.txt")).unwrap(),
+            "e"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_group_by_source_dir() {
+        let env = setup_test_env();
+        let first_dir = env.source_dir.join("first");
+        let second_dir = env.source_dir.join("second");
+        create_dir_all(&first_dir).unwrap();
+        create_dir_all(&second_dir).unwrap();
+        create_test_file(&first_dir.join("same.txt"), "first");
+        create_test_file(&second_dir.join("same.txt"), "second");
+        write_clipboard(&[
+            get_test_entry(&first_dir.join("same.txt"), Operation::Copy),
+            get_test_entry(&second_dir.join("same.txt"), Operation::Copy),
+        ])
+        .unwrap();
+
+        let options = PasteOptions {
+            group_by_source_dir: true,
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("first").join("same.txt")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("second").join("same.txt")).unwrap(),
+            "second"
+        );
+
+        let history = read_history().unwrap().unwrap();
+        let dest_dir = canonicalize(&env.dest_dir).unwrap();
+        assert!(
+            history
+                .iter()
+                .any(|entry| entry.path == dest_dir.join("first").join("same.txt"))
+        );
+        assert!(
+            history
+                .iter()
+                .any(|entry| entry.path == dest_dir.join("second").join("same.txt"))
+        );
+    }
+
+    #[test]
+    fn test_get_relative_link_target() {
+        let dir = tempdir().unwrap();
+        let base = canonicalize(dir.path()).unwrap();
+        create_dir_all(base.join("links").join("nested")).unwrap();
+
+        assert_eq!(
+            get_relative_link_target(
+                &base.join("src").join("a.txt"),
+                &base.join("links").join("a.txt")
+            ),
+            PathBuf::from("../src/a.txt")
+        );
+        assert_eq!(
+            get_relative_link_target(
+                &base.join("src").join("a.txt"),
+                &base.join("links").join("nested").join("a.txt")
+            ),
+            PathBuf::from("../../src/a.txt")
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_link_to() {
+        let env = setup_test_env();
+        let first_path = env.source_dir.join("first.txt");
+        let second_path = env.source_dir.join("second.txt");
+        create_test_file(&first_path, "first");
+        create_test_file(&second_path, "second");
+        let link_dir = env.home_dir.path().join("farm");
+        create_dir_all(&link_dir).unwrap();
+
+        let (infos, warnings) =
+            handle_link_to(vec![env.source_dir.join("*.txt")], link_dir.clone(), true).unwrap();
+
+        assert_eq!(infos.len(), 2);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            read_link(link_dir.join("first.txt")).unwrap(),
+            PathBuf::from("../source/first.txt")
+        );
+        assert_eq!(
+            std::fs::read_to_string(link_dir.join("second.txt")).unwrap(),
+            "second"
+        );
+        assert!(read_clipboard().unwrap().unwrap_or_default().is_empty());
+        assert!(read_history().unwrap().unwrap_or_default().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_cut_into_default_destination_needs_confirmation() {
+        let env = setup_test_env();
+        let paths = [env.source_dir.join("a.txt"), env.source_dir.join("b.txt")];
+        for path in &paths {
+            create_test_file(path, "content");
+        }
+        handle_transfer(
+            paths.iter().collect(),
+            Operation::Cut,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        let options = PasteOptions {
+            default_destination: true,
+            ..Default::default()
+        };
+
+        let (infos, warnings, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_declined,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(infos.is_empty());
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::MoveIntoCwdDeclined {
+                count: 2
+            })]
+        ));
+        assert!(paths.iter().all(|path| path.exists()));
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 2);
+
+        let options = PasteOptions {
+            non_interactive: true,
+            ..options
+        };
+        let (infos, _, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(infos.len(), 2);
+        assert!(env.dest_dir.join("a.txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_cut_into_default_destination_with_closed_stdin() {
+        let env = setup_test_env();
+        let path = env.source_dir.join("a.txt");
+        create_test_file(&path, "content");
+        handle_transfer(vec![&path], Operation::Cut, &TransferOptions::default()).unwrap();
+        let options = PasteOptions {
+            default_destination: true,
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        let (infos, warnings, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            |count, destination| confirm_move(count, destination, &mut std::io::empty(), true),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(infos.is_empty());
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::MoveIntoCwdDeclined {
+                count: 1
+            })]
+        ));
+        assert!(path.exists());
+        assert!(confirm_move(1, &env.dest_dir, &mut "y\n".as_bytes(), true));
+        assert!(confirm_move(1, &env.dest_dir, &mut std::io::empty(), false));
+
+        // A conflict policy makes the paste scripted, so it never asks.
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Skip),
+            ..options
+        };
+        let (infos, _, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(matches!(&infos[..], [AppInfo::Paste { .. }]));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_non_interactive() {
+        let env = setup_test_env();
+        let entries: Vec<RecordEntry> = ["skip.txt", "taken.txt"]
+            .iter()
+            .map(|name| {
+                let path = env.source_dir.join(name);
+                create_test_file(&path, "new");
+                get_test_entry(&path, Operation::Copy)
+            })
+            .collect();
+        write_clipboard(&entries).unwrap();
+        create_test_file(&env.dest_dir.join("taken.txt"), "old");
+        let options = PasteOptions {
+            prompt_each: true,
+            non_interactive: true,
+            ..Default::default()
+        };
+
+        let (infos, _, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 2);
+        for name in ["skip.txt", "taken.txt"] {
+            assert_eq!(
+                std::fs::read_to_string(env.dest_dir.join(name)).unwrap(),
+                "new"
+            );
+        }
+        assert!(read_clipboard().unwrap().unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_as_overrides_operation() {
+        for (staged, paste_as) in [
+            (Operation::Link, Operation::Copy),
+            (Operation::Copy, Operation::Cut),
+            (Operation::Cut, Operation::Link),
+        ] {
+            let env = setup_test_env();
+            let source_path = env.source_dir.join("file.txt");
+            let dest_path = env.dest_dir.join("file.txt");
+            create_test_file(&source_path, "content");
+            write_clipboard(&[get_test_entry(&source_path, staged)]).unwrap();
+            let options = PasteOptions {
+                paste_as: Some(paste_as.clone()),
+                ..Default::default()
+            };
+
+            handle_paste_with_prompts(
+                &env.dest_dir,
+                None,
+                &options,
+                mock_collision_unreachable,
+                mock_paste_confirmation_choice,
+                mock_move_unreachable,
+                &mut Vec::new(),
+            )
+            .unwrap();
+
+            match paste_as {
+                Operation::Copy => {
+                    assert!(source_path.is_file());
+                    assert!(!dest_path.is_symlink());
+                }
+                Operation::Cut => assert!(!source_path.exists()),
+                Operation::Link => {
+                    assert!(source_path.is_file());
+                    assert_eq!(std::fs::read_link(&dest_path).unwrap(), source_path);
+                }
+            }
+            assert_eq!(std::fs::read_to_string(&dest_path).unwrap(), "content");
+            assert_eq!(read_history().unwrap().unwrap()[0].operation, paste_as);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_parents() {
+        let env = setup_test_env();
+        let source_path = env.source_dir.join("notes.txt");
+        create_test_file(&source_path, "content");
+        write_clipboard(&[get_test_entry(&source_path, Operation::Copy)]).unwrap();
+        let mut options = PasteOptions {
+            template: Some("{op}/{stem}/{name}".to_string()),
+            ..Default::default()
+        };
+
+        let result = handle_paste(&env.dest_dir, None, &options);
+        assert!(matches!(
+            result,
+            Err(AppError::File(FileError::ParentMissing { path }))
+                if path == env.dest_dir.join("copy").join("notes")
+        ));
+        assert!(!env.dest_dir.join("copy").exists());
+
+        options.parents = true;
+        handle_paste(&env.dest_dir, None, &options).unwrap();
+
+        let target = env.dest_dir.join("copy").join("notes").join("notes.txt");
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "content");
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_destination_exists_ok() {
+        let env = setup_test_env();
+        let source_path = env.source_dir.join("same.txt");
+        let dest_path = env.dest_dir.join("same.txt");
+        create_test_file(&source_path, "content");
+        create_test_file(&dest_path, "content");
+        write_clipboard(&[get_test_entry(&source_path, Operation::Copy)]).unwrap();
+        let options = PasteOptions {
+            destination_exists_ok: true,
+            ..Default::default()
+        };
+
+        let (infos, warnings, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert!(warnings.is_empty());
+        assert!(matches!(&infos[0], AppInfo::Paste { path } if *path == dest_path));
+        assert!(matches!(&infos[1], AppInfo::AlreadyUpToDate { path } if *path == dest_path));
+        assert!(read_clipboard().unwrap().unwrap_or_default().is_empty());
+        assert_eq!(read_history().unwrap().unwrap()[0].path, dest_path);
+        assert_eq!(std::fs::read_to_string(&dest_path).unwrap(), "content");
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_progress_json() {
+        let env = setup_test_env();
+        let entries: Vec<RecordEntry> = [("a.txt", "aaa"), ("b.txt", "bb")]
+            .iter()
+            .map(|(name, content)| {
+                let path = env.source_dir.join(name);
+                create_test_file(&path, content);
+                get_test_entry(&path, Operation::Copy)
+            })
+            .collect();
+        write_clipboard(&entries).unwrap();
+        let options = PasteOptions {
+            progress_json: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut output,
+        )
+        .unwrap();
+
+        let events: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["bytes_done"], 3);
+        assert_eq!(events[1]["bytes_done"], 5);
+        assert!(events.iter().all(|event| event["bytes_total"] == 5));
+        assert_eq!(
+            events[1]["current_path"],
+            env.dest_dir.join("b.txt").to_str().unwrap()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_progress_json_directory() {
+        let env = setup_test_env();
+        let project = env.source_dir.join("project");
+        create_dir_all(project.join("nested")).unwrap();
+        create_test_file(&project.join("a.txt"), "aaa");
+        create_test_file(&project.join("nested").join("b.txt"), "bb");
+        let entry = RecordEntry {
+            size: None,
+            ..get_test_entry(&project, Operation::Copy)
+        };
+        write_clipboard(&[entry]).unwrap();
+        let options = PasteOptions {
+            progress_json: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut output,
+        )
+        .unwrap();
+
+        let events: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|event| event["bytes_total"] == 5));
+        assert_eq!(events[1]["bytes_done"], 5);
+        assert!(events.iter().all(|event| {
+            event["current_path"]
+                .as_str()
+                .unwrap()
+                .starts_with(env.dest_dir.join("project").to_str().unwrap())
+        }));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_prompt_each() {
+        let env = setup_test_env();
+        let paths: Vec<PathBuf> = ["paste.txt", "skip.txt", "quit.txt", "after.txt"]
+            .iter()
+            .map(|name| env.source_dir.join(name))
+            .collect();
+        for path in &paths {
+            create_test_file(path, "content");
+        }
+        let entries: Vec<RecordEntry> = paths
+            .iter()
+            .map(|path| get_test_entry(path, Operation::Copy))
+            .collect();
+        write_clipboard(&entries).unwrap();
+
+        let options = PasteOptions {
+            prompt_each: true,
+            ..Default::default()
+        };
+        let (infos, _, _) = handle_paste_with_prompts(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_unreachable,
+            mock_paste_confirmation_choice,
+            mock_move_unreachable,
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(env.dest_dir.join("paste.txt").exists());
+        assert!(!env.dest_dir.join("skip.txt").exists());
+        assert!(!env.dest_dir.join("quit.txt").exists());
+        assert!(!env.dest_dir.join("after.txt").exists());
+
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 3);
+        assert!(clipboard.iter().all(|entry| entry.id != entries[0].id));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_persists_state_before_prompting() {
+        let env = setup_test_env();
+        let first_path = env.source_dir.join("first.txt");
+        let second_path = env.source_dir.join("second.txt");
+        create_test_file(&first_path, "first");
+        create_test_file(&second_path, "second");
+        create_test_file(&env.dest_dir.join("second.txt"), "original");
+        write_clipboard(&[
+            get_test_entry(&first_path, Operation::Cut),
+            get_test_entry(&second_path, Operation::Cut),
+        ])
+        .unwrap();
+
+        let (infos, _) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_quit_after_flush,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(env.dest_dir.join("first.txt").exists());
+        assert!(second_path.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_source_vanished() {
+        let env = setup_test_env();
+        let vanished_path = env.source_dir.join("vanished.txt");
+        let kept_path = env.source_dir.join("kept.txt");
+        create_test_file(&vanished_path, "vanished");
+        create_test_file(&kept_path, "kept");
+        create_test_file(&env.dest_dir.join("vanished.txt"), "original");
+        write_clipboard(&[
+            get_test_entry(&vanished_path, Operation::Copy),
+            get_test_entry(&kept_path, Operation::Copy),
+        ])
+        .unwrap();
+
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_remove_source,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            &warnings[0],
+            AppWarning::File(FileWarning::SourceVanished { path }) if path.ends_with("vanished.txt")
+        ));
+        assert!(env.dest_dir.join("kept.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("vanished.txt")).unwrap(),
+            "original"
+        );
+    }
+
+    #[test]
+    fn test_copy_operation_error_includes_kind() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("file.txt");
+        create_test_file(&file_path, "content");
+
+        let not_found = copy_operation(
+            &dir.path().join("missing.txt"),
+            &dir.path().join("dest.txt"),
+            None,
+            false,
+            None,
+        )
+        .unwrap_err();
+        let not_a_directory =
+            copy_operation(&file_path, &file_path.join("dest.txt"), None, false, None).unwrap_err();
+
+        assert!(not_found.to_string().contains("(entity not found)"));
+        assert!(not_a_directory.to_string().contains("(not a directory)"));
+        assert_ne!(not_found.to_string(), not_a_directory.to_string());
+    }
+
+    fn mock_link_crosses_devices(_: &Path, _: &Path) -> Result<(), IoError> {
+        Err(IoError::from(IoErrorKind::CrossesDevices))
+    }
+
+    fn mock_link_denied(_: &Path, _: &Path) -> Result<(), IoError> {
+        Err(IoError::from(IoErrorKind::PermissionDenied))
+    }
+
+    #[test]
+    fn test_link_or_copy_falls_back_to_copy() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("source.txt");
+        create_test_file(&from, "linked");
+
+        let to = dir.path().join("copied.txt");
+        let result = link_or_copy(
+            mock_link_crosses_devices,
+            &from,
+            &from,
+            &to,
+            None,
+            false,
+            true,
+        )
+        .unwrap();
+        assert_eq!(result, (true, true));
+        assert!(!to.is_symlink());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "linked");
+
+        let not_allowed = dir.path().join("not_allowed.txt");
+        let error = link_or_copy(
+            mock_link_crosses_devices,
+            &from,
+            &from,
+            &not_allowed,
+            None,
+            false,
+            false,
+        )
+        .unwrap_err();
+        assert!(matches!(error, FileError::Link { .. }));
+
+        let denied = dir.path().join("denied.txt");
+        let error =
+            link_or_copy(mock_link_denied, &from, &from, &denied, None, false, true).unwrap_err();
+        assert!(matches!(error, FileError::Link { .. }));
+        assert!(!not_allowed.exists() && !denied.exists());
+    }
+
+    #[test]
+    fn test_move_operation_error_includes_kind() {
+        let dir = tempdir().unwrap();
+        let from_dir = dir.path().join("from");
+        let to_dir = dir.path().join("to");
+        create_dir_all(&from_dir).unwrap();
+        create_dir_all(&to_dir).unwrap();
+        create_test_file(&to_dir.join("file.txt"), "content");
+
+        let error =
+            move_operation(&from_dir, &to_dir, Some(CollisionResolution::Overwrite)).unwrap_err();
+
+        assert!(matches!(error, FileError::Move { .. }));
+        assert!(error.to_string().contains("(directory not empty)"));
+    }
+
+    #[test]
+    fn test_source_vanished_requires_missing_source() {
+        let dir = tempdir().unwrap();
+        let existing_path = dir.path().join("existing.txt");
+        create_test_file(&existing_path, "content");
+        let not_found = |path: &Path| FileError::Copy {
+            from_path: path.to_path_buf(),
+            to_path: dir.path().join("dest"),
+            source: IoError::from(IoErrorKind::NotFound),
+        };
+
+        let missing_path = dir.path().join("missing.txt");
+        assert!(source_vanished(&not_found(&missing_path), &missing_path));
+        assert!(!source_vanished(&not_found(&existing_path), &existing_path));
+        assert!(!source_vanished(
+            &FileError::Cwd {
+                source: IoError::from(IoErrorKind::NotFound)
+            },
+            &missing_path
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_overwrite_all_with_multiple_files() {
+        let env = setup_test_env();
+        let file1 = env.source_dir.join("file1.txt");
+        let file2 = env.source_dir.join("file2.txt");
+        create_test_file(&file1, "source1");
+        create_test_file(&file2, "source2");
+
+        let entry1 = get_test_entry(&file1, Operation::Copy);
+        let entry2 = get_test_entry(&file2, Operation::Copy);
+        write_clipboard(&[entry1, entry2]).unwrap();
+
+        let dest1 = env.dest_dir.join("file1.txt");
+        let dest2 = env.dest_dir.join("file2.txt");
+        create_test_file(&dest1, "dest1");
+        create_test_file(&dest2, "dest2");
+
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_overwrite_all,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 2);
+        assert!(warnings.is_empty());
+
+        assert_eq!(std::fs::read_to_string(&dest1).unwrap(), "source1");
+        assert_eq!(std::fs::read_to_string(&dest2).unwrap(), "source2");
+    }
+
+    #[test]
+    #[serial]
+    fn test_expand_paths_with_mixed_patterns() {
+        let env = setup_test_env();
+        create_test_file(&env.source_dir.join("a.txt"), "a");
+        create_test_file(&env.source_dir.join("b.txt"), "b");
+        create_test_file(&env.source_dir.join("c.log"), "c");
+        create_test_file(&env.source_dir.join("specific.txt"), "specific");
+
+        let glob_path = env.source_dir.join("*.log");
+        let specific_path = env.source_dir.join("specific.txt");
+
+        let (expanded, warnings) =
+            expand_paths(vec![glob_path, specific_path.clone()], false, false).unwrap();
+
+        assert_eq!(expanded.len(), 2);
+        assert!(expanded.contains(&env.source_dir.join("c.log")));
+        assert!(expanded.contains(&specific_path));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_get_metadata_relative_path() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("relative_test.txt");
+        File::create(&file_path).unwrap();
+
+        let original_dir = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.path()).unwrap();
+
+        let metadata = get_metadata("relative_test.txt", false).unwrap();
+        assert_eq!(metadata.entry_type, EntryType::File);
+        assert!(metadata.absolute_path.is_absolute());
+
+        std::env::set_current_dir(original_dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_absolute_path_relative() {
+        let dir = tempdir().unwrap();
+        let original_dir = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.path()).unwrap();
+
+        let relative_path = PathBuf::from("relative_file.txt");
+        let absolute = get_absolute_path(&relative_path).unwrap();
+
+        assert!(absolute.is_absolute());
+        assert!(absolute.ends_with("relative_file.txt"));
+
+        std::env::set_current_dir(original_dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_absolute_path_already_absolute() {
+        let absolute_path = PathBuf::from("/tmp/absolute.txt");
+        let result = get_absolute_path(&absolute_path).unwrap();
+        assert_eq!(result, absolute_path);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_with_paste_content_clipboard() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("paste_content.txt");
+        create_test_file(&file_path, "content");
+        let entry = get_test_entry(&file_path, Operation::Copy);
+
+        let paste_content = PasteContent {
+            entries: vec![entry],
+            source: RecordType::Clipboard,
+        };
+
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            Some(paste_content),
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(warnings.is_empty());
+        assert!(env.dest_dir.join("paste_content.txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_with_paste_content_history() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("history_paste.txt");
+        create_test_file(&file_path, "history content");
+        let entry = get_test_entry(&file_path, Operation::Copy);
+
+        let paste_content = PasteContent {
+            entries: vec![entry],
             source: RecordType::History,
         };
 
         let (infos, warnings) = handle_paste_with_prompt(
             &env.dest_dir,
-            Some(paste_content),
+            Some(paste_content),
+            &PasteOptions::default(),
+            mock_collision_resolution_choice_yes,
+        )
+        .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert!(warnings.is_empty());
+        assert!(env.dest_dir.join("history_paste.txt").exists());
+    }
+
+    #[test]
+    fn test_copy_operation_no_collision() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("source.txt");
+        let to = dir.path().join("dest.txt");
+        create_test_file(&from, "copy test");
+
+        let result = copy_operation(&from, &to, None, false, None).unwrap();
+        assert!(result);
+        assert!(from.exists());
+        assert!(to.exists());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "copy test");
+    }
+
+    #[test]
+    fn test_move_operation_no_collision() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("source.txt");
+        let to = dir.path().join("dest.txt");
+        create_test_file(&from, "move test");
+
+        let result = move_operation(&from, &to, None).unwrap();
+        assert!(result);
+        assert!(!from.exists());
+        assert!(to.exists());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "move test");
+    }
+
+    #[test]
+    fn test_ensure_dir_with_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("subdir").join("file.txt");
+
+        ensure_dir(&file_path).unwrap();
+
+        assert!(file_path.parent().unwrap().exists());
+        assert!(file_path.parent().unwrap().is_dir());
+    }
+
+    #[test]
+    fn test_ensure_dir_existing_directory() {
+        let dir = tempdir().unwrap();
+        create_dir_all(dir.path().join("existing")).unwrap();
+        let dir_path = dir.path().join("existing");
+
+        ensure_dir(&dir_path).unwrap();
+
+        assert!(dir_path.exists());
+        assert!(dir_path.is_dir());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_conflict_overwrite() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "source");
+        write_clipboard(&[get_test_entry(&file_path, Operation::Copy)]).unwrap();
+        let dest_file = env.dest_dir.join("a.txt");
+        create_test_file(&dest_file, "original");
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Overwrite),
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert_eq!(std::fs::read_to_string(&dest_file).unwrap(), "source");
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_conflict_skip() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "source");
+        let entry = get_test_entry(&file_path, Operation::Copy);
+        write_clipboard(std::slice::from_ref(&entry)).unwrap();
+        let dest_file = env.dest_dir.join("a.txt");
+        create_test_file(&dest_file, "original");
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Skip),
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert!(infos.is_empty());
+        assert_eq!(std::fs::read_to_string(&dest_file).unwrap(), "original");
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard[0].id, entry.id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_conflict_log() {
+        let env = setup_test_env();
+        let mut entries = Vec::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let file_path = env.source_dir.join(name);
+            create_test_file(&file_path, "source");
+            entries.push(get_test_entry(&file_path, Operation::Copy));
+        }
+        write_clipboard(&entries).unwrap();
+        create_test_file(&env.dest_dir.join("a.txt"), "original");
+        create_test_file(&env.dest_dir.join("b.txt"), "original");
+        let log_path = env.source_dir.join("conflicts.log");
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Skip),
+            conflict_log: Some(log_path.clone()),
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, name) in lines.iter().zip(["a.txt", "b.txt"]) {
+            assert_eq!(
+                *line,
+                format!(
+                    "{}\t{}\tskip",
+                    entries
+                        .iter()
+                        .find(|entry| entry.path.ends_with(name))
+                        .unwrap()
+                        .path
+                        .display(),
+                    env.dest_dir.join(name).display()
+                )
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_conflict_rename() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "source");
+        write_clipboard(&[get_test_entry(&file_path, Operation::Copy)]).unwrap();
+        let dest_file = env.dest_dir.join("a.txt");
+        create_test_file(&dest_file, "original");
+        create_test_file(&env.dest_dir.join("a (1).txt"), "first rename");
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Rename),
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        let renamed_file = env.dest_dir.join("a (2).txt");
+        assert_eq!(std::fs::read_to_string(&renamed_file).unwrap(), "source");
+        assert_eq!(std::fs::read_to_string(&dest_file).unwrap(), "original");
+
+        let history = read_history().unwrap().unwrap();
+        assert_eq!(history[0].path, renamed_file);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_conflict_fail() {
+        let env = setup_test_env();
+        let file_a = env.source_dir.join("a.txt");
+        let file_b = env.source_dir.join("b.txt");
+        create_test_file(&file_a, "a");
+        create_test_file(&file_b, "b");
+        write_clipboard(&[
+            get_test_entry(&file_a, Operation::Copy),
+            get_test_entry(&file_b, Operation::Copy),
+        ])
+        .unwrap();
+        create_test_file(&env.dest_dir.join("b.txt"), "existing");
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Fail),
+            ..Default::default()
+        };
+        let result =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable);
+
+        assert!(matches!(
+            result,
+            Err(AppError::File(FileError::DestinationExists { .. }))
+        ));
+        assert!(!env.dest_dir.join("a.txt").exists());
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 2);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_conflict_rename_without_collision() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "source");
+        write_clipboard(&[get_test_entry(&file_path, Operation::Copy)]).unwrap();
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Rename),
+            ..Default::default()
+        };
+        handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+            .unwrap();
+
+        assert!(env.dest_dir.join("a.txt").exists());
+        assert!(!env.dest_dir.join("a (1).txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_merge_directories() {
+        let env = setup_test_env();
+        let source_dir = env.source_dir.join("dir_a");
+        create_dir_all(source_dir.join("sub")).unwrap();
+        create_test_file(&source_dir.join("a.txt"), "source a");
+        create_test_file(&source_dir.join("sub").join("b.txt"), "source b");
+
+        let dest_dir = env.dest_dir.join("dir_a");
+        create_dir_all(dest_dir.join("sub")).unwrap();
+        create_test_file(&dest_dir.join("a.txt"), "destination a");
+        create_test_file(&dest_dir.join("c.txt"), "destination c");
+        create_test_file(&dest_dir.join("sub").join("d.txt"), "destination d");
+
+        write_clipboard(&[get_test_entry(&source_dir, Operation::Copy)]).unwrap();
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Overwrite),
+            merge: true,
+            ..Default::default()
+        };
+        let (infos, _) =
+            handle_paste_with_prompt(&env.dest_dir, None, &options, mock_collision_unreachable)
+                .unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.join("a.txt")).unwrap(),
+            "source a"
+        );
+        assert!(dest_dir.join("c.txt").exists());
+        assert!(dest_dir.join("sub").join("b.txt").exists());
+        assert!(dest_dir.join("sub").join("d.txt").exists());
+        assert!(source_dir.join("a.txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_merge_directories_cut_with_prompt() {
+        let env = setup_test_env();
+        let source_dir = env.source_dir.join("dir_a");
+        create_dir_all(source_dir.join("sub")).unwrap();
+        create_test_file(&source_dir.join("a.txt"), "source a");
+        create_test_file(&source_dir.join("sub").join("b.txt"), "source b");
+
+        let dest_dir = env.dest_dir.join("dir_a");
+        create_dir_all(&dest_dir).unwrap();
+        create_test_file(&dest_dir.join("a.txt"), "destination a");
+
+        write_clipboard(&[get_test_entry(&source_dir, Operation::Cut)]).unwrap();
+
+        let options = PasteOptions {
+            merge: true,
+            ..Default::default()
+        };
+        let (infos, warnings) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &options,
+            mock_collision_resolution_choice_no,
+        )
+        .unwrap();
+
+        // The declined a.txt is still in the source, so the cut is not recorded as pasted.
+        assert!(infos.is_empty());
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::CutMergeIncomplete { path, .. })] if *path == source_dir
+        ));
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.join("a.txt")).unwrap(),
+            "destination a"
+        );
+        assert!(dest_dir.join("sub").join("b.txt").exists());
+        assert!(source_dir.join("a.txt").exists());
+        assert!(!source_dir.join("sub").exists());
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 1);
+        assert!(read_history().unwrap().unwrap_or_default().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_paste_merge_directories_cut_with_conflict_skip() {
+        let env = setup_test_env();
+        let source_dir = env.source_dir.join("dir_a");
+        create_dir_all(&source_dir).unwrap();
+        create_test_file(&source_dir.join("a.txt"), "source a");
+        create_test_file(&source_dir.join("b.txt"), "source b");
+        let dest_dir = env.dest_dir.join("dir_a");
+        create_dir_all(&dest_dir).unwrap();
+        create_test_file(&dest_dir.join("a.txt"), "destination a");
+        write_clipboard(&[get_test_entry(&source_dir, Operation::Cut)]).unwrap();
+
+        let options = PasteOptions {
+            merge: true,
+            conflict: Some(ConflictPolicy::Skip),
+            ..Default::default()
+        };
+        let (infos, warnings) = handle_paste(&env.dest_dir, None, &options).unwrap();
+
+        assert!(infos.is_empty());
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::CutMergeIncomplete { .. })]
+        ));
+        assert!(dest_dir.join("b.txt").exists());
+        assert!(source_dir.join("a.txt").exists());
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_stage_content_and_paste() {
+        let env = setup_test_env();
+        let staged_path = stage_content("out.txt", &b"generated output"[..]).unwrap();
+        assert!(staged_path.ends_with("out.txt"));
+        assert!(staged_path.starts_with(get_content_dir().unwrap()));
+
+        handle_transfer(
+            vec![&staged_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        let (infos, _) = handle_paste_with_prompt(
+            &env.dest_dir,
+            None,
+            &PasteOptions::default(),
             mock_collision_resolution_choice_yes,
         )
         .unwrap();
 
         assert_eq!(infos.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("out.txt")).unwrap(),
+            "generated output"
+        );
+        assert!(!staged_path.exists());
+        assert!(!staged_path.parent().unwrap().exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_snapshot_survives_edits_to_original() {
+        let env = setup_test_env();
+        let path = env.source_dir.join("notes.txt");
+        create_test_file(&path, "original");
+        let options = TransferOptions {
+            snapshot: true,
+            ..Default::default()
+        };
+
+        handle_transfer(vec![&path], Operation::Copy, &options).unwrap();
+        handle_transfer(vec![&path], Operation::Copy, &options).unwrap();
+        create_test_file(&path, "edited");
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 2);
+        assert_eq!(clipboard[0].path, clipboard[1].path);
+        let snapshot_path = clipboard[0].path.clone();
+        assert!(snapshot_path.starts_with(get_content_dir().unwrap()));
+        assert!(snapshot_path.ends_with("notes.txt"));
+
+        let first_dest = env.dest_dir.join("first");
+        create_dir_all(&first_dest).unwrap();
+        handle_paste(
+            &first_dest,
+            Some(PasteContent {
+                entries: vec![clipboard[0].clone()],
+                source: RecordType::Clipboard,
+            }),
+            &PasteOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(first_dest.join("notes.txt")).unwrap(),
+            "original"
+        );
+        assert!(snapshot_path.exists());
+
+        handle_paste(&env.dest_dir, None, &PasteOptions::default()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("notes.txt")).unwrap(),
+            "original"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "edited");
+        assert!(!snapshot_path.parent().unwrap().exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_undo_since_reverses_recent_pastes() {
+        let env = setup_test_env();
+        let copied = [env.source_dir.join("a.txt"), env.source_dir.join("b.txt")];
+        let cut = env.source_dir.join("c.txt");
+        let edited = env.source_dir.join("d.txt");
+        for path in copied.iter().chain([&cut, &edited]) {
+            create_test_file(path, "content");
+        }
+        handle_transfer(vec![&cut], Operation::Cut, &TransferOptions::default()).unwrap();
+        handle_transfer(
+            copied.iter().chain([&edited]).collect(),
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        handle_paste(&env.dest_dir, None, &PasteOptions::default()).unwrap();
+        assert!(!cut.exists());
+
+        // Pretend d.txt was pasted a minute ago, so its current mtime counts as an edit.
+        let mut history = read_history().unwrap().unwrap();
+        for entry in history
+            .iter_mut()
+            .filter(|entry| entry.path.ends_with("d.txt"))
+        {
+            entry.pasted_at = Some(SystemTime::now() - Duration::from_secs(60));
+        }
+        write_history(&history).unwrap();
+
+        let (infos, warnings) = handle_undo_since(Duration::from_secs(600)).unwrap();
+
+        assert!(matches!(
+            infos.last(),
+            Some(AppInfo::UndoSummary {
+                undone: 3,
+                skipped: 1
+            })
+        ));
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::UndoModified { path })] if path.ends_with("d.txt")
+        ));
+        assert!(!env.dest_dir.join("a.txt").exists());
+        assert!(!env.dest_dir.join("b.txt").exists());
+        assert!(!env.dest_dir.join("c.txt").exists());
+        assert!(env.dest_dir.join("d.txt").exists());
+        assert_eq!(std::fs::read_to_string(&cut).unwrap(), "content");
+        assert!(copied.iter().all(|path| path.exists()));
+        let history = read_history().unwrap().unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].path.ends_with("d.txt"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_undo_since_keeps_merged_destination() {
+        let env = setup_test_env();
+        let source = env.source_dir.join("project");
+        create_dir_all(&source).unwrap();
+        create_test_file(&source.join("new.txt"), "new");
+        let destination = env.dest_dir.join("project");
+        create_dir_all(&destination).unwrap();
+        create_test_file(&destination.join("old.txt"), "old");
+        let nested = env.source_dir.join("nested");
+        create_dir_all(&nested).unwrap();
+        create_test_file(&nested.join("inner.txt"), "inner");
+        handle_transfer(
+            vec![&source, &nested],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        let options = PasteOptions {
+            merge: true,
+            ..Default::default()
+        };
+        handle_paste(&env.dest_dir, None, &options).unwrap();
+        // Editing a nested file leaves the pasted directory's own mtime alone.
+        std::thread::sleep(Duration::from_millis(20));
+        create_test_file(&env.dest_dir.join("nested").join("inner.txt"), "edited");
+
+        let (infos, warnings) = handle_undo_since(Duration::from_secs(600)).unwrap();
+
+        assert!(matches!(
+            infos.last(),
+            Some(AppInfo::UndoSummary {
+                undone: 0,
+                skipped: 2
+            })
+        ));
+        assert!(warnings.iter().any(|warning| matches!(
+            warning,
+            AppWarning::File(FileWarning::UndoPreexisting { path }) if *path == destination
+        )));
+        assert!(warnings.iter().any(|warning| matches!(
+            warning,
+            AppWarning::File(FileWarning::UndoModified { path }) if path.ends_with("nested")
+        )));
+        assert_eq!(
+            std::fs::read_to_string(destination.join("old.txt")).unwrap(),
+            "old"
+        );
+        assert!(destination.join("new.txt").exists());
+        assert!(env.dest_dir.join("nested").join("inner.txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_replay_pastes_source_again() {
+        let env = setup_test_env();
+        let source = env.source_dir.join("a.txt");
+        create_test_file(&source, "content");
+        handle_transfer(vec![&source], Operation::Copy, &TransferOptions::default()).unwrap();
+        handle_paste(&env.dest_dir, None, &PasteOptions::default()).unwrap();
+        let id = read_history().unwrap().unwrap()[0].id;
+        let replay_dir = env.home_dir.path().join("replay");
+        create_dir_all(&replay_dir).unwrap();
+
+        let (infos, warnings) = handle_replay(id, &replay_dir, &PasteOptions::default()).unwrap();
+
         assert!(warnings.is_empty());
-        assert!(env.dest_dir.join("history_paste.txt").exists());
+        assert!(
+            matches!(&infos[..], [AppInfo::Paste { path }] if *path == replay_dir.join("a.txt"))
+        );
+        assert_eq!(
+            std::fs::read_to_string(replay_dir.join("a.txt")).unwrap(),
+            "content"
+        );
+        assert_eq!(read_history().unwrap().unwrap().len(), 1);
+
+        std::fs::remove_file(&source).unwrap();
+        let (infos, warnings) = handle_replay(
+            id,
+            &env.home_dir.path().join("other"),
+            &PasteOptions::default(),
+        )
+        .unwrap();
+        assert!(infos.is_empty());
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::ReplaySourceMissing { path })] if *path == source
+        ));
     }
 
     #[test]
-    fn test_copy_operation_no_collision() {
+    #[serial]
+    fn test_handle_replay_keeps_shared_snapshot() {
+        let env = setup_test_env();
+        let file_a = env.source_dir.join("a.txt");
+        let file_b = env.source_dir.join("b.txt");
+        create_test_file(&file_a, "same");
+        create_test_file(&file_b, "same");
+        let options = TransferOptions {
+            snapshot: true,
+            ..Default::default()
+        };
+        handle_transfer(vec![&file_a, &file_b], Operation::Copy, &options).unwrap();
+        let first_dest = env.dest_dir.join("first");
+        create_dir_all(&first_dest).unwrap();
+        let options = PasteOptions {
+            exclude: vec!["*b.txt".to_string()],
+            ..Default::default()
+        };
+        handle_paste(&first_dest, None, &options).unwrap();
+        let id = read_history().unwrap().unwrap()[0].id;
+        let replay_dir = env.dest_dir.join("replay");
+        create_dir_all(&replay_dir).unwrap();
+
+        handle_replay(id, &replay_dir, &PasteOptions::default()).unwrap();
+        assert!(replay_dir.join("a.txt").exists());
+
+        let (infos, _) = handle_paste(&env.dest_dir, None, &PasteOptions::default()).unwrap();
+        assert!(
+            infos
+                .iter()
+                .any(|info| matches!(info, AppInfo::Paste { path } if path.ends_with("b.txt")))
+        );
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("b.txt")).unwrap(),
+            "same"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_paste_directory_excluding_hidden() {
+        let env = setup_test_env();
+        let project = env.source_dir.join("project");
+        create_dir_all(project.join(".git")).unwrap();
+        create_dir_all(project.join("src")).unwrap();
+        create_test_file(&project.join(".env"), "secret");
+        create_test_file(&project.join(".git/config"), "config");
+        create_test_file(&project.join("README.md"), "readme");
+        create_test_file(&project.join("src/main.rs"), "main");
+        create_test_file(&project.join("src/.cache"), "cache");
+        let options = TransferOptions {
+            exclude_hidden: true,
+            ..Default::default()
+        };
+
+        handle_transfer(vec![&project], Operation::Copy, &options).unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert!(clipboard[0].exclude_hidden);
+        handle_paste(&env.dest_dir, None, &PasteOptions::default()).unwrap();
+
+        let pasted = env.dest_dir.join("project");
+        assert!(pasted.join("README.md").exists());
+        assert!(pasted.join("src/main.rs").exists());
+        assert!(!pasted.join(".env").exists());
+        assert!(!pasted.join(".git").exists());
+        assert!(!pasted.join("src/.cache").exists());
+        assert!(project.join(".env").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_transfer_dangling_symlink() {
+        let env = setup_test_env();
+        let link_path = env.source_dir.join("dangling");
+        symlink(env.source_dir.join("missing.txt"), &link_path).unwrap();
+
+        let (infos, warnings) = handle_transfer(
+            vec![&link_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(infos.len(), 1);
+        assert!(warnings.is_empty());
+
+        let (infos, warnings) = handle_transfer(
+            vec![&link_path],
+            Operation::Link,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(infos.len(), 1);
+        assert!(matches!(
+            &warnings[..],
+            [AppWarning::File(FileWarning::NonCanonical { .. })]
+        ));
+
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 2);
+        assert_eq!(clipboard[0].entry_type, EntryType::Symlink);
+        assert!(clipboard[0].non_canonical);
+        assert!(!clipboard[1].non_canonical);
+    }
+
+    #[test]
+    fn test_with_retry() {
+        let transient_error = || FileError::Copy {
+            from_path: PathBuf::from("/tmp/a"),
+            to_path: PathBuf::from("/tmp/b"),
+            source: IoError::from(IoErrorKind::Interrupted),
+        };
+
+        let mut resolutions = Vec::new();
+        let result = with_retry(2, Duration::ZERO, None, |resolution| {
+            resolutions.push(resolution);
+            if resolutions.len() == 1 {
+                Err(transient_error())
+            } else {
+                Ok(true)
+            }
+        });
+        assert!(matches!(result, Ok(true)));
+        assert_eq!(resolutions, [None, Some(CollisionResolution::Overwrite)]);
+
+        let mut attempts = 0;
+        let result = with_retry(2, Duration::ZERO, None, |_| {
+            attempts += 1;
+            Err(transient_error())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result = with_retry(2, Duration::ZERO, None, |_| {
+            attempts += 1;
+            Err(FileError::Copy {
+                from_path: PathBuf::from("/tmp/a"),
+                to_path: PathBuf::from("/tmp/b"),
+                source: IoError::from(IoErrorKind::PermissionDenied),
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_stage_content_invalid_name() {
+        let _env = setup_test_env();
+        for name in ["", "dir/out.txt", ".."] {
+            let result = stage_content(name, &b"content"[..]);
+            assert!(matches!(
+                result,
+                Err(AppError::File(FileError::InvalidName { .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_get_recursive_size() {
         let dir = tempdir().unwrap();
-        let from = dir.path().join("source.txt");
-        let to = dir.path().join("dest.txt");
-        create_test_file(&from, "copy test");
+        create_dir_all(dir.path().join("nested").join("deeper")).unwrap();
+        create_test_file(&dir.path().join("a.txt"), "12345");
+        create_test_file(&dir.path().join("nested").join("b.txt"), "123");
+        create_test_file(
+            &dir.path().join("nested").join("deeper").join("c.txt"),
+            "12",
+        );
 
-        let result = copy_operation(&from, &to, None).unwrap();
-        assert!(result);
-        assert!(from.exists());
-        assert!(to.exists());
-        assert_eq!(std::fs::read_to_string(&to).unwrap(), "copy test");
+        assert_eq!(get_recursive_size(dir.path(), None), (10, false));
+        assert_eq!(
+            get_recursive_size(&dir.path().join("a.txt"), None),
+            (5, false)
+        );
+        assert_eq!(
+            get_recursive_size(&dir.path().join("missing"), None),
+            (0, false)
+        );
     }
 
     #[test]
-    fn test_move_operation_no_collision() {
+    fn test_get_recursive_size_following_symlinks_skips_cycle() {
         let dir = tempdir().unwrap();
-        let from = dir.path().join("source.txt");
-        let to = dir.path().join("dest.txt");
-        create_test_file(&from, "move test");
+        let nested_dir = dir.path().join("nested");
+        create_dir_all(&nested_dir).unwrap();
+        create_test_file(&dir.path().join("a.txt"), "12345");
+        create_test_file(&nested_dir.join("b.txt"), "123");
+        symlink(dir.path(), nested_dir.join("loop")).unwrap();
+
+        let (size, truncated, cycles) = get_recursive_size_following_symlinks(dir.path(), None);
+        assert_eq!(size, 8);
+        assert!(!truncated);
+        assert_eq!(cycles, vec![nested_dir.join("loop")]);
+
+        let (size, truncated) = get_recursive_size(dir.path(), None);
+        assert!(size > 8);
+        assert!(!truncated);
+    }
 
-        let result = move_operation(&from, &to, None).unwrap();
-        assert!(result);
-        assert!(!from.exists());
-        assert!(to.exists());
-        assert_eq!(std::fs::read_to_string(&to).unwrap(), "move test");
+    #[test]
+    fn test_get_recursive_size_following_symlinks_counts_shared_dir_once() {
+        let dir = tempdir().unwrap();
+        let shared_dir = dir.path().join("shared");
+        create_dir_all(&shared_dir).unwrap();
+        create_test_file(&shared_dir.join("a.txt"), "12345");
+        symlink(&shared_dir, dir.path().join("first")).unwrap();
+        symlink(&shared_dir, dir.path().join("second")).unwrap();
+
+        let (size, truncated, cycles) = get_recursive_size_following_symlinks(dir.path(), None);
+        assert_eq!(size, 5);
+        assert!(!truncated);
+        assert!(cycles.is_empty());
     }
 
     #[test]
-    fn test_ensure_dir_with_file() {
+    fn test_get_recursive_size_with_max_depth() {
         let dir = tempdir().unwrap();
-        let file_path = dir.path().join("subdir").join("file.txt");
+        create_dir_all(dir.path().join("nested").join("deeper")).unwrap();
+        create_test_file(&dir.path().join("a.txt"), "12345");
+        create_test_file(&dir.path().join("nested").join("b.txt"), "123");
+        create_test_file(
+            &dir.path().join("nested").join("deeper").join("c.txt"),
+            "12",
+        );
 
-        ensure_dir(&file_path).unwrap();
+        assert_eq!(get_recursive_size(dir.path(), Some(0)), (0, true));
+        assert_eq!(get_recursive_size(dir.path(), Some(1)), (5, true));
+        assert_eq!(get_recursive_size(dir.path(), Some(2)), (8, true));
+        assert_eq!(get_recursive_size(dir.path(), Some(3)), (10, false));
+    }
 
-        assert!(file_path.parent().unwrap().exists());
-        assert!(file_path.parent().unwrap().is_dir());
+    #[test]
+    #[serial]
+    fn test_handle_transfer_directory_with_max_depth() {
+        let env = setup_test_env();
+        let staged_dir = env.source_dir.join("dir_a");
+        create_dir_all(staged_dir.join("nested")).unwrap();
+        create_test_file(&staged_dir.join("a.txt"), "12345");
+        create_test_file(&staged_dir.join("nested").join("b.txt"), "123");
+
+        handle_transfer(
+            vec![&staged_dir],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard[0].size, Some(8));
+        assert!(!clipboard[0].size_is_lower_bound);
+
+        let options = TransferOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        handle_transfer(vec![&staged_dir], Operation::Copy, &options).unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard[0].size, Some(5));
+        assert!(clipboard[0].size_is_lower_bound);
     }
 
     #[test]
-    fn test_ensure_dir_existing_directory() {
+    #[serial]
+    fn test_handle_paste_space_check_uses_recorded_size() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "a");
+        let entry = RecordEntry {
+            size: Some(u64::MAX),
+            ..get_test_entry(&file_path, Operation::Copy)
+        };
+        let options = PasteOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let paste_content = |entry: &RecordEntry| PasteContent {
+            entries: vec![entry.clone()],
+            source: RecordType::History,
+        };
+
+        let result = handle_paste(&env.dest_dir, Some(paste_content(&entry)), &options);
+        assert!(matches!(
+            result,
+            Err(AppError::File(FileError::InsufficientSpace {
+                required: u64::MAX,
+                ..
+            }))
+        ));
+
+        // A lower bound is not trusted, so the file is measured again.
+        let entry = RecordEntry {
+            size_is_lower_bound: true,
+            ..entry
+        };
+        handle_paste(&env.dest_dir, Some(paste_content(&entry)), &options).unwrap();
+        assert!(env.dest_dir.join("a.txt").exists());
+    }
+
+    #[test]
+    fn test_check_available_space() {
         let dir = tempdir().unwrap();
-        create_dir_all(dir.path().join("existing")).unwrap();
-        let dir_path = dir.path().join("existing");
+        assert!(check_available_space(dir.path(), 0).is_none());
 
-        ensure_dir(&dir_path).unwrap();
+        let warning = check_available_space(&dir.path().join("not_yet_created"), u64::MAX);
+        assert!(matches!(
+            warning,
+            Some(FileWarning::InsufficientSpace {
+                required: u64::MAX,
+                ..
+            })
+        ));
+    }
 
-        assert!(dir_path.exists());
-        assert!(dir_path.is_dir());
+    #[test]
+    fn test_get_renamed_path() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.tar");
+        assert_eq!(get_renamed_path(&path), dir.path().join("report (1).tar"));
+
+        create_test_file(&dir.path().join("report (1).tar"), "");
+        assert_eq!(get_renamed_path(&path), dir.path().join("report (2).tar"));
+
+        let no_extension = dir.path().join("README");
+        assert_eq!(
+            get_renamed_path(&no_extension),
+            dir.path().join("README (1)")
+        );
     }
 }
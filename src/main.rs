@@ -1,10 +1,14 @@
 use clap::CommandFactory;
 use clap_complete::{Shell, generate};
 use std::{
-    error::Error,
-    io::{self, BufRead, IsTerminal},
-    path::PathBuf,
+    ffi::OsString,
+    fs::{File, create_dir_all, write},
+    io::{self, BufRead, IsTerminal, Write},
+    os::unix::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    process::ExitCode,
     str::FromStr,
+    time::Duration,
 };
 
 mod cli;
@@ -17,15 +21,34 @@ mod tui;
 #[cfg(test)]
 pub mod test_helpers;
 
+#[cfg(feature = "os-clipboard")]
+use files::{get_clipboard_paths, read_os_clipboard};
 use {
     cli::{Cli, handle_cli},
-    errors::{AppError, AppInfo, AppWarning},
-    files::{handle_paste, handle_transfer},
-    models::{Action, Operation, RecordType},
-    records::clear_records,
-    tui::Tui,
+    errors::{
+        AppError, AppInfo, AppWarning, EXIT_NOTHING_TO_PASTE, EXIT_USAGE_ERROR, EXIT_WARNINGS,
+        FileError, RecordError, RecordWarning,
+    },
+    files::{
+        create_temp_destination, get_os_clipboard_backend, handle_info, handle_link_to,
+        handle_paste, handle_paste_with_outcomes, handle_replay, handle_transfer,
+        handle_undo_since, handle_watch, stage_content,
+    },
+    models::{
+        Action, EntryInfo, ListOptions, Operation, PasteOutcome, PasteSummary, RecordBenchmark,
+        RecordEntry, RecordType, StoragePaths, SummaryWarning, format_entry_size, format_size,
+        format_timestamp, format_timestamp_with,
+    },
+    records::{
+        benchmark_records, clear_history_older_than, clear_records, dedup_clipboard,
+        export_history_csv, get_state_dir, get_storage_paths, handle_reorder, migrate_record_files,
+        prune_records, read_entries,
+    },
+    tui::{DisplayRow, Tui, filter_entries, get_display_rows, group_entries},
 };
 
+const WATCH_INTERVAL: u64 = 500;
+
 fn read_paths_from_reader<R: BufRead>(reader: R) -> Vec<PathBuf> {
     let lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
     lines
@@ -36,84 +59,535 @@ fn read_paths_from_reader<R: BufRead>(reader: R) -> Vec<PathBuf> {
         .collect()
 }
 
-fn read_piped_paths() -> Vec<PathBuf> {
-    if !io::stdin().is_terminal() {
-        read_paths_from_reader(io::stdin().lock())
-    } else {
+// NUL is the only byte that cannot appear in a path, so this keeps spaces and newlines intact.
+fn read_null_paths_from_reader<R: BufRead>(reader: R) -> Vec<PathBuf> {
+    reader
+        .split(b'\0')
+        .map_while(Result::ok)
+        .filter(|bytes| !bytes.is_empty())
+        .map(|bytes| PathBuf::from(OsString::from_vec(bytes)))
+        .collect()
+}
+
+fn read_piped_paths(null_delimited: bool) -> Vec<PathBuf> {
+    if io::stdin().is_terminal() {
         vec![]
+    } else if null_delimited {
+        read_null_paths_from_reader(io::stdin().lock())
+    } else {
+        read_paths_from_reader(io::stdin().lock())
+    }
+}
+
+fn write_cd_file(cd_file: &Path, infos: &[AppInfo]) -> Result<(), FileError> {
+    let pasted_paths: Vec<&PathBuf> = infos
+        .iter()
+        .filter_map(|info| match info {
+            AppInfo::Paste { path } => Some(path),
+            _ => None,
+        })
+        .collect();
+    let directory = match pasted_paths.as_slice() {
+        [path] if path.is_dir() => path.to_string_lossy().into_owned(),
+        _ => String::new(),
+    };
+    write(cd_file, directory).map_err(|source| FileError::WriteCdFile {
+        path: cd_file.to_path_buf(),
+        source,
+    })
+}
+
+fn parse_completions_args(args: &[String]) -> Result<(Shell, Option<PathBuf>), String> {
+    let mut shell = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => return Err("--output requires a path".to_string()),
+            },
+            _ if shell.is_none() => {
+                shell = Some(Shell::from_str(arg).map_err(|_| {
+                    format!(
+                        "Invalid shell '{}'; possible values: [bash, elvish, fish, powershell, zsh]",
+                        arg
+                    )
+                })?);
+            }
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+    let shell = shell.unwrap_or_else(|| Shell::from_env().unwrap_or(Shell::Bash));
+    Ok((shell, output))
+}
+
+fn write_completions(shell: Shell, output: Option<&Path>) -> Result<(), AppError> {
+    let Some(output) = output else {
+        generate(shell, &mut Cli::command(), "clp", &mut io::stdout());
+        return Ok(());
+    };
+    let write_error = |source| FileError::WriteCompletions {
+        path: output.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = output.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        create_dir_all(parent).map_err(write_error)?;
+    }
+    let mut file = File::create(output).map_err(write_error)?;
+    generate(shell, &mut Cli::command(), "clp", &mut file);
+    Ok(())
+}
+
+fn format_entries(entries: &[RecordEntry], options: &ListOptions) -> Result<String, AppError> {
+    let time_format = options.time_format.as_deref();
+    if options.json {
+        let values = entries
+            .iter()
+            .map(|entry| entry_to_json(entry, time_format))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(serde_json::to_string_pretty(&values)
+            .map_err(|source| RecordError::SerializeJson { source })?);
+    }
+    let format_path = |entry: &RecordEntry| {
+        let path = entry.path.to_string_lossy();
+        match time_format {
+            Some(time_format) => format!(
+                "{}\t{}",
+                format_timestamp_with(entry.timestamp, time_format),
+                path
+            ),
+            None => path.into_owned(),
+        }
+    };
+    let paths = get_display_rows(entries, options.group_by)
+        .into_iter()
+        .map(|row| match row {
+            DisplayRow::Header(operation) => format!("{}:", operation),
+            DisplayRow::Entry(index) => format_path(&entries[index]),
+        });
+    if options.null {
+        return Ok(paths.map(|path| path + "\0").collect());
+    }
+    Ok(paths.collect::<Vec<String>>().join("\n"))
+}
+
+fn entry_to_json(
+    entry: &RecordEntry,
+    time_format: Option<&str>,
+) -> Result<serde_json::Value, RecordError> {
+    let mut value =
+        serde_json::to_value(entry).map_err(|source| RecordError::SerializeJson { source })?;
+    if let Some(time_format) = time_format {
+        value["timestamp"] = format_timestamp_with(entry.timestamp, time_format).into();
+    }
+    Ok(value)
+}
+
+// Each line is flushed as soon as it is written, so consumers can process entries while the
+// rest are still being printed. A write error, e.g. from a closed pipe, ends the stream.
+fn write_entries_json_lines<W: Write>(
+    entries: &[RecordEntry],
+    time_format: Option<&str>,
+    writer: &mut W,
+) -> Result<(), AppError> {
+    for entry in entries {
+        let line = serde_json::to_string(&entry_to_json(entry, time_format)?)
+            .map_err(|source| RecordError::SerializeJson { source })?;
+        if writeln!(writer, "{}", line)
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Ids of newly staged entries, one per line, so scripts can reference them later.
+fn format_entry_ids(infos: &[AppInfo]) -> String {
+    infos
+        .iter()
+        .filter_map(|info| match info {
+            AppInfo::Copy { id, .. } | AppInfo::Cut { id, .. } | AppInfo::Link { id, .. } => {
+                Some(id.to_string())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Warnings go to stderr so that stdout only carries the ids.
+fn print_entry_ids(infos: &[AppInfo], warnings: &[AppWarning]) {
+    let ids = format_entry_ids(infos);
+    if !ids.is_empty() {
+        println!("{}", ids);
+    }
+    for warning in warnings {
+        eprintln!("[Warning]: {}", warning);
+    }
+}
+
+fn format_entry_info(info: &EntryInfo, json: bool) -> Result<String, AppError> {
+    if json {
+        return Ok(serde_json::to_string_pretty(info)
+            .map_err(|source| RecordError::SerializeJson { source })?);
+    }
+    let entry = &info.entry;
+    let mut lines = vec![
+        format!("ID: {}", entry.id),
+        format!("Record: {}", info.record_type),
+        format!("Operation: {}", entry.operation),
+        format!("Type: {}", entry.entry_type),
+        format!("Size: {}", format_entry_size(entry)),
+        format!("Staged: {}", format_timestamp(entry.timestamp)),
+        format!("Path: {}", entry.path.display()),
+    ];
+    if let Some(destination) = &entry.destination {
+        lines.push(format!("Destination: {}", destination.display()));
+    }
+    if entry.non_canonical {
+        lines.push("Canonical: no".to_string());
+    }
+    lines.push(format!(
+        "Exists: {}",
+        if info.exists { "yes" } else { "no" }
+    ));
+    if info.exists {
+        lines.push(format!(
+            "Current size: {}",
+            info.current_size
+                .map(format_size)
+                .unwrap_or("directory".to_string())
+        ));
+    }
+    if let Some(modified) = info.current_modified {
+        lines.push(format!("Modified: {}", format_timestamp(modified)));
+    }
+    lines.push(format!(
+        "Drift: {}",
+        info.drift.as_deref().unwrap_or("none")
+    ));
+    Ok(lines.join("\n"))
+}
+
+fn get_version_info(verbose: bool) -> Result<String, AppError> {
+    let mut lines = vec![format!("clp {}", env!("CARGO_PKG_VERSION"))];
+    if verbose {
+        lines.push(format!("State directory: {}", get_state_dir()?.display()));
+        lines.push(format!(
+            "OS clipboard backend: {}",
+            get_os_clipboard_backend().unwrap_or("unavailable")
+        ));
+        lines.push(format!(
+            "Clipboard entries: {}",
+            read_entries(&RecordType::Clipboard)?.len()
+        ));
+        lines.push(format!(
+            "History entries: {}",
+            read_entries(&RecordType::History)?.len()
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn format_storage_paths(paths: &StoragePaths, json: bool) -> Result<String, AppError> {
+    if json {
+        return Ok(serde_json::to_string_pretty(paths)
+            .map_err(|source| RecordError::SerializeJson { source })?);
+    }
+    Ok([&paths.clipboard, &paths.history, &paths.state_dir]
+        .map(|path| path.display().to_string())
+        .join("\n"))
+}
+
+fn format_benchmark(benchmark: &RecordBenchmark) -> String {
+    let line = |action: &str, elapsed: Duration| {
+        let total = benchmark.entries as f64 * benchmark.rounds as f64;
+        format!(
+            "[{}] {} {} entries {} times in {:.1} ms ({:.0} entries/s)",
+            benchmark.backend,
+            action,
+            benchmark.entries,
+            benchmark.rounds,
+            elapsed.as_secs_f64() * 1000.0,
+            total / elapsed.as_secs_f64().max(f64::EPSILON)
+        )
+    };
+    [line("Wrote", benchmark.write), line("Read", benchmark.read)].join("\n")
+}
+
+// Counted from the per-entry outcomes, so entries left out with --exclude are in no bucket.
+fn summarize_paste(
+    outcomes: &[(PathBuf, PasteOutcome)],
+    infos: &[AppInfo],
+    warnings: &[AppWarning],
+) -> PasteSummary {
+    let count = |kind: PasteOutcome| {
+        outcomes
+            .iter()
+            .filter(|(_, outcome)| *outcome == kind)
+            .count()
+    };
+    PasteSummary {
+        pasted: count(PasteOutcome::Pasted),
+        skipped: count(PasteOutcome::Skipped),
+        failed: count(PasteOutcome::Failed),
+        destinations: infos
+            .iter()
+            .filter_map(|info| match info {
+                AppInfo::Paste { path } => Some(path.clone()),
+                _ => None,
+            })
+            .collect(),
+        warnings: warnings
+            .iter()
+            .map(|warning| SummaryWarning {
+                kind: warning.kind(),
+                message: warning.to_string(),
+            })
+            .collect(),
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn format_paste_summary(
+    outcomes: &[(PathBuf, PasteOutcome)],
+    infos: &[AppInfo],
+    warnings: &[AppWarning],
+) -> Result<String, AppError> {
+    Ok(
+        serde_json::to_string_pretty(&summarize_paste(outcomes, infos, warnings))
+            .map_err(|source| RecordError::SerializeJson { source })?,
+    )
+}
+
+fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
     if args.len() >= 2 && args[1] == "completions" {
-        let shell = if args.len() > 2 {
-            Shell::from_str(args[2].as_str()).expect(
-                "Invalid shell provided; possible values: [bash, elvish, fish, powershell, zsh]",
-            )
-        } else {
-            Shell::from_env().unwrap_or(Shell::Bash)
+        let (shell, output) = match parse_completions_args(&args[2..]) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                eprintln!("[Error]: {}", message);
+                return ExitCode::from(EXIT_USAGE_ERROR);
+            }
         };
-        generate(shell, &mut Cli::command(), "clp", &mut io::stdout());
-        return Ok(());
+        if let Err(error) = write_completions(shell, output.as_deref()) {
+            eprintln!("[Error]: {}", error);
+            return error.exit_code();
+        }
+        return ExitCode::SUCCESS;
     }
 
-    color_eyre::install()?;
+    if let Err(error) = color_eyre::install() {
+        eprintln!("[Error]: {}", error);
+        return ExitCode::FAILURE;
+    }
     let mut app_warnings: Vec<AppWarning> = Vec::new();
     let mut app_infos: Vec<AppInfo> = Vec::new();
+    let mut strict = false;
+    let mut fail_if_empty = false;
+    let mut quiet = false;
 
     let result: Result<(), AppError> = (|| {
         let action = handle_cli();
+        // Printed to stderr so piped output such as `clp list --plain` stays clean.
+        for info in migrate_record_files()? {
+            eprintln!("[Info]: {}", info);
+        }
         match action {
-            Action::Copy(paths) => {
-                let paths = [paths, read_piped_paths()].concat();
+            Action::Copy { paths, options } => {
+                let paths = match &options.stdin_content_name {
+                    Some(name) => vec![stage_content(name, io::stdin().lock())?],
+                    #[cfg(feature = "os-clipboard")]
+                    None if options.from_clipboard_paths => {
+                        [paths, get_clipboard_paths(read_os_clipboard)?].concat()
+                    }
+                    None => [paths, read_piped_paths(options.stdin_null)].concat(),
+                };
                 if paths.is_empty() {
                     eprintln!("[Warning]: No paths provided. Usage: clp copy <paths...>");
                     return Ok(());
                 }
-                let (copy_infos, copy_warnings) = handle_transfer(paths, Operation::Copy)?;
+                if options.watch {
+                    println!("[Info]: Watching for new files. Press Ctrl+c to stop.");
+                    return handle_watch(
+                        paths,
+                        &options,
+                        Duration::from_millis(WATCH_INTERVAL),
+                        |infos, warnings| {
+                            if options.print_id {
+                                print_entry_ids(&infos, &warnings);
+                            } else {
+                                print_messages(&infos, &warnings);
+                            }
+                            true
+                        },
+                    );
+                }
+                let (copy_infos, copy_warnings) =
+                    handle_transfer(paths, Operation::Copy, &options)?;
+                if options.print_id {
+                    print_entry_ids(&copy_infos, &copy_warnings);
+                    return Ok(());
+                }
                 app_infos.extend(copy_infos);
                 app_warnings.extend(copy_warnings);
             }
-            Action::Cut(paths) => {
-                let paths = [paths, read_piped_paths()].concat();
+            Action::Cut { paths, options } => {
+                let paths = [paths, read_piped_paths(options.stdin_null)].concat();
                 if paths.is_empty() {
                     eprintln!("[Warning]: No paths provided. Usage: clp cut <paths...>");
                     return Ok(());
                 }
-                let (cut_infos, cut_warnings) = handle_transfer(paths, Operation::Cut)?;
+                let (cut_infos, cut_warnings) = handle_transfer(paths, Operation::Cut, &options)?;
+                if options.print_id {
+                    print_entry_ids(&cut_infos, &cut_warnings);
+                    return Ok(());
+                }
                 app_infos.extend(cut_infos);
                 app_warnings.extend(cut_warnings);
             }
-            Action::Link(paths) => {
-                let paths = [paths, read_piped_paths()].concat();
+            Action::Link { paths, options } => {
+                let paths = [paths, read_piped_paths(options.stdin_null)].concat();
                 if paths.is_empty() {
                     eprintln!("[Warning]: No paths provided. Usage: clp link <paths...>");
                     return Ok(());
                 }
-                let (link_infos, link_warnings) = handle_transfer(paths, Operation::Link)?;
+                let (link_infos, link_warnings) =
+                    handle_transfer(paths, Operation::Link, &options)?;
+                if options.print_id {
+                    print_entry_ids(&link_infos, &link_warnings);
+                    return Ok(());
+                }
                 app_infos.extend(link_infos);
                 app_warnings.extend(link_warnings);
             }
-            Action::Paste(path) => {
-                let (paste_infos, paste_warnings) = handle_paste(path, None)?;
+            Action::Stage {
+                paths,
+                link_to,
+                relative,
+            } => {
+                let (stage_infos, stage_warnings) = handle_link_to(paths, link_to, relative)?;
+                app_infos.extend(stage_infos);
+                app_warnings.extend(stage_warnings);
+            }
+            Action::Paste { path, options } => {
+                strict = options.strict;
+                fail_if_empty = options.fail_if_empty;
+                if options.into_tmp {
+                    let path = create_temp_destination()?;
+                    let (_, paste_warnings) = handle_paste(&path, None, &options)?;
+                    println!("{}", path.display());
+                    for warning in paste_warnings {
+                        eprintln!("[Warning]: {}", warning);
+                    }
+                    return Ok(());
+                }
+                let (paste_infos, paste_warnings, outcomes) =
+                    handle_paste_with_outcomes(path, None, &options)?;
+                if let Some(cd_file) = &options.cd_file {
+                    write_cd_file(cd_file, &paste_infos)?;
+                }
+                if options.summary_json {
+                    println!(
+                        "{}",
+                        format_paste_summary(&outcomes, &paste_infos, &paste_warnings)?
+                    );
+                    quiet = true;
+                }
                 app_infos.extend(paste_infos);
                 app_warnings.extend(paste_warnings);
             }
-            Action::Clipboard => {
-                let (tui_infos, tui_warnings) = Tui::new(RecordType::Clipboard)?.run()?;
+            Action::List { options } => {
+                if options.json_lines {
+                    let entries =
+                        filter_entries(read_entries(&RecordType::Clipboard)?, &options.operation);
+                    let time_format = options.time_format.as_deref();
+                    return write_entries_json_lines(&entries, time_format, &mut io::stdout());
+                }
+                if options.plain || options.json {
+                    let entries = group_entries(
+                        filter_entries(read_entries(&RecordType::Clipboard)?, &options.operation),
+                        options.group_by,
+                    );
+                    let formatted = format_entries(&entries, &options)?;
+                    if options.null {
+                        print!("{}", formatted);
+                    } else {
+                        println!("{}", formatted);
+                    }
+                    return Ok(());
+                }
+                let (tui_infos, tui_warnings) = Tui::new(RecordType::Clipboard, &options)?.run()?;
                 app_infos.extend(tui_infos);
                 app_warnings.extend(tui_warnings);
             }
-            Action::History => {
-                let (tui_infos, tui_warnings) = Tui::new(RecordType::History)?.run()?;
+            Action::History { options } => {
+                if options.json_lines {
+                    let entries = read_entries(&RecordType::History)?;
+                    return write_entries_json_lines(&entries, None, &mut io::stdout());
+                }
+                let (tui_infos, tui_warnings) = Tui::new(RecordType::History, &options)?.run()?;
                 app_infos.extend(tui_infos);
                 app_warnings.extend(tui_warnings);
             }
+            Action::ExportHistoryCsv { path } => {
+                let export_infos = export_history_csv(&path)?;
+                app_infos.extend(export_infos);
+            }
+            Action::UndoSince { since } => {
+                let (undo_infos, undo_warnings) = handle_undo_since(since)?;
+                app_infos.extend(undo_infos);
+                app_warnings.extend(undo_warnings);
+            }
+            Action::Replay {
+                id,
+                destination,
+                options,
+            } => {
+                let (replay_infos, replay_warnings) = handle_replay(id, &destination, &options)?;
+                app_infos.extend(replay_infos);
+                app_warnings.extend(replay_warnings);
+            }
+            Action::ClearHistory { older_than } => {
+                let clear_infos = clear_history_older_than(older_than)?;
+                app_infos.extend(clear_infos);
+            }
             Action::Clear => {
                 let clear_infos = clear_records()?;
                 app_infos.extend(clear_infos);
             }
+            Action::Version { verbose } => {
+                println!("{}", get_version_info(verbose)?);
+            }
+            Action::Bench { entries, rounds } => {
+                for benchmark in benchmark_records(entries, rounds)? {
+                    println!("{}", format_benchmark(&benchmark));
+                }
+            }
+            Action::Paths { json } => {
+                println!("{}", format_storage_paths(&get_storage_paths()?, json)?);
+            }
+            Action::Prune { history } => {
+                let prune_infos = prune_records(history)?;
+                app_infos.extend(prune_infos);
+            }
+            Action::Info { id, json } => match handle_info(id)? {
+                Some(info) => println!("{}", format_entry_info(&info, json)?),
+                None => app_warnings.push(AppWarning::Record(RecordWarning::EntryNotFound)),
+            },
+            Action::Reorder { id, position } => {
+                let (reorder_infos, reorder_warnings) = handle_reorder(id, position)?;
+                app_infos.extend(reorder_infos);
+                app_warnings.extend(reorder_warnings);
+            }
+            Action::Dedup => {
+                let dedup_infos = dedup_clipboard()?;
+                app_infos.extend(dedup_infos);
+            }
         }
         Ok(())
     })();
@@ -122,9 +596,32 @@ fn main() -> Result<(), Box<dyn Error>> {
         eprintln!("[Error]: {}", error);
         #[cfg(debug_assertions)]
         eprintln!("DEBUG INFO: {:#?}", error);
-        return Err(Box::from(error));
+        return error.exit_code();
     }
 
+    if !quiet {
+        print_messages(&app_infos, &app_warnings);
+    }
+    match get_warning_exit_code(&app_warnings, strict, fail_if_empty) {
+        Some(code) => ExitCode::from(code),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+fn get_warning_exit_code(warnings: &[AppWarning], strict: bool, fail_if_empty: bool) -> Option<u8> {
+    let nothing_to_paste = warnings
+        .iter()
+        .any(|warning| matches!(warning, AppWarning::Record(RecordWarning::NothingToPaste)));
+    if fail_if_empty && nothing_to_paste {
+        Some(EXIT_NOTHING_TO_PASTE)
+    } else if strict && !warnings.is_empty() {
+        Some(EXIT_WARNINGS)
+    } else {
+        None
+    }
+}
+
+fn print_messages(app_infos: &[AppInfo], app_warnings: &[AppWarning]) {
     if !app_infos.is_empty() {
         println!("[Info]: ");
         for info in app_infos {
@@ -140,13 +637,32 @@ fn main() -> Result<(), Box<dyn Error>> {
             println!("DEBUG INFO: {:#?}", warning);
         }
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        models::{ConflictPolicy, GroupBy, PasteOptions, TransferOptions},
+        test_helpers::{create_mock_record_entry, setup_test_env},
+    };
+    use serial_test::serial;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_read_null_paths_from_reader() {
+        let input = b"a b.txt\0line\nbreak.txt\0\0last.txt";
+        let paths = read_null_paths_from_reader(&input[..]);
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("a b.txt"),
+                PathBuf::from("line\nbreak.txt"),
+                PathBuf::from("last.txt"),
+            ]
+        );
+        assert_ne!(paths, read_paths_from_reader(&input[..]));
+    }
 
     #[test]
     fn test_read_paths_from_reader_single_line_spaces() {
@@ -247,4 +763,403 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_write_cd_file_single_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let cd_file = dir.path().join("cd");
+        let infos = vec![AppInfo::Paste {
+            path: dir.path().to_path_buf(),
+        }];
+
+        write_cd_file(&cd_file, &infos).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&cd_file).unwrap(),
+            dir.path().to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_write_cd_file_not_a_single_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let cd_file = dir.path().join("cd");
+        let file_path = dir.path().join("file.txt");
+        std::fs::write(&file_path, "content").unwrap();
+
+        write_cd_file(&cd_file, &[AppInfo::Paste { path: file_path }]).unwrap();
+        assert_eq!(std::fs::read_to_string(&cd_file).unwrap(), "");
+
+        let infos = vec![
+            AppInfo::Paste {
+                path: dir.path().to_path_buf(),
+            },
+            AppInfo::Paste {
+                path: dir.path().to_path_buf(),
+            },
+        ];
+        write_cd_file(&cd_file, &infos).unwrap();
+        assert_eq!(std::fs::read_to_string(&cd_file).unwrap(), "");
+    }
+
+    #[test]
+    fn test_parse_completions_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_completions_args(&args(&["zsh"])).unwrap(),
+            (Shell::Zsh, None)
+        );
+        assert_eq!(
+            parse_completions_args(&args(&["fish", "--output", "/tmp/clp.fish"])).unwrap(),
+            (Shell::Fish, Some(PathBuf::from("/tmp/clp.fish")))
+        );
+        assert_eq!(
+            parse_completions_args(&args(&["-o", "_clp", "bash"])).unwrap(),
+            (Shell::Bash, Some(PathBuf::from("_clp")))
+        );
+        assert!(parse_completions_args(&args(&["zsh", "--output"])).is_err());
+        assert!(parse_completions_args(&args(&["zsh", "bash"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_completions_args_invalid_shell() {
+        let error = parse_completions_args(&["foobar".to_string()]).unwrap_err();
+        assert!(error.contains("'foobar'"));
+        assert!(error.contains("bash, elvish, fish, powershell, zsh"));
+    }
+
+    #[test]
+    fn test_write_completions_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("nested").join("_clp");
+
+        write_completions(Shell::Zsh, Some(&output)).unwrap();
+
+        let content = std::fs::read_to_string(&output).unwrap();
+        assert!(content.contains("clp"));
+    }
+
+    #[test]
+    fn test_format_entries_plain() {
+        let entries = vec![
+            create_mock_record_entry(Some(PathBuf::from("/tmp/a.txt")), None, None, None, None),
+            create_mock_record_entry(Some(PathBuf::from("/tmp/b c.txt")), None, None, None, None),
+        ];
+        let options = ListOptions {
+            plain: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_entries(&entries, &options).unwrap(),
+            "/tmp/a.txt\n/tmp/b c.txt"
+        );
+    }
+
+    #[test]
+    fn test_format_entries_grouped() {
+        let entries = vec![
+            create_mock_record_entry(
+                Some(PathBuf::from("/tmp/a.txt")),
+                Some(Operation::Copy),
+                None,
+                None,
+                None,
+            ),
+            create_mock_record_entry(
+                Some(PathBuf::from("/tmp/b.txt")),
+                Some(Operation::Link),
+                None,
+                None,
+                None,
+            ),
+        ];
+        let options = ListOptions {
+            plain: true,
+            group_by: Some(GroupBy::Operation),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_entries(&entries, &options).unwrap(),
+            "copy:\n/tmp/a.txt\nlink:\n/tmp/b.txt"
+        );
+    }
+
+    #[test]
+    fn test_format_entries_null() {
+        let entries = vec![
+            create_mock_record_entry(Some(PathBuf::from("/tmp/a b.txt")), None, None, None, None),
+            create_mock_record_entry(Some(PathBuf::from("/tmp/c\nd.txt")), None, None, None, None),
+        ];
+        let options = ListOptions {
+            plain: true,
+            null: true,
+            ..Default::default()
+        };
+        let formatted = format_entries(&entries, &options).unwrap();
+        assert_eq!(formatted, "/tmp/a b.txt\0/tmp/c\nd.txt\0");
+
+        let round_trip = read_null_paths_from_reader(formatted.as_bytes());
+        assert_eq!(
+            round_trip,
+            vec![
+                PathBuf::from("/tmp/a b.txt"),
+                PathBuf::from("/tmp/c\nd.txt")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_entries_with_time_format() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(86400 * 365);
+        let entries = vec![create_mock_record_entry(
+            Some(PathBuf::from("/tmp/a.txt")),
+            None,
+            None,
+            Some(timestamp),
+            None,
+        )];
+        let options = ListOptions {
+            plain: true,
+            time_format: Some("%Y".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_entries(&entries, &options).unwrap(),
+            "1971\t/tmp/a.txt"
+        );
+
+        let options = ListOptions {
+            json: true,
+            time_format: Some("%Y".to_string()),
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&format_entries(&entries, &options).unwrap()).unwrap();
+        assert_eq!(json[0]["timestamp"], "1971");
+    }
+
+    #[test]
+    fn test_write_entries_json_lines() {
+        let entries = vec![
+            create_mock_record_entry(Some(PathBuf::from("/tmp/a.txt")), None, None, None, None),
+            create_mock_record_entry(Some(PathBuf::from("/tmp/b\nc.txt")), None, None, None, None),
+        ];
+        let mut output = Vec::new();
+        write_entries_json_lines(&entries, None, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), entries.len());
+        for (line, entry) in lines.iter().zip(&entries) {
+            let parsed: RecordEntry = serde_json::from_str(line).unwrap();
+            assert_eq!(parsed.id, entry.id);
+            assert_eq!(parsed.path, entry.path);
+        }
+
+        let mut output = Vec::new();
+        write_entries_json_lines(&entries[..1], Some("%Y"), &mut output).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert!(value["timestamp"].is_string());
+    }
+
+    #[test]
+    #[serial]
+    fn test_format_paste_summary_after_mixed_paste() {
+        let env = setup_test_env();
+        let pasted = env.source_dir.join("pasted.txt");
+        let existing = env.source_dir.join("existing.txt");
+        let changed = env.source_dir.join("changed");
+        let excluded = env.source_dir.join("excluded.log");
+        for path in [&pasted, &existing, &changed, &excluded] {
+            write(path, "content").unwrap();
+        }
+        write(env.dest_dir.join("existing.txt"), "kept").unwrap();
+        handle_transfer(
+            vec![&pasted, &existing, &changed, &excluded],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+        std::fs::remove_file(&changed).unwrap();
+        create_dir_all(&changed).unwrap();
+
+        let options = PasteOptions {
+            conflict: Some(ConflictPolicy::Skip),
+            verify_type: true,
+            exclude: vec!["*.log".to_string()],
+            ..Default::default()
+        };
+        let (infos, warnings, outcomes) =
+            handle_paste_with_outcomes(&env.dest_dir, None, &options).unwrap();
+        let summary = format_paste_summary(&outcomes, &infos, &warnings).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(json["pasted"], 1);
+        assert_eq!(json["skipped"], 2);
+        assert_eq!(json["failed"], 0);
+        assert_eq!(
+            json["destinations"],
+            serde_json::json!([env.dest_dir.join("pasted.txt")])
+        );
+        assert_eq!(json["warnings"][0]["kind"], "type_changed_skipped");
+    }
+
+    #[test]
+    fn test_format_entries_json() {
+        let entries = vec![create_mock_record_entry(
+            Some(PathBuf::from("/tmp/a.txt")),
+            None,
+            None,
+            None,
+            None,
+        )];
+        let options = ListOptions {
+            json: true,
+            ..Default::default()
+        };
+        let json = format_entries(&entries, &options).unwrap();
+        let parsed: Vec<RecordEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0].id, entries[0].id);
+        assert_eq!(parsed[0].path, entries[0].path);
+    }
+
+    #[test]
+    fn test_format_entry_info() {
+        let entry = create_mock_record_entry(
+            Some(PathBuf::from("/tmp/a.txt")),
+            None,
+            None,
+            None,
+            Some(10),
+        );
+        let info = EntryInfo {
+            record_type: RecordType::History,
+            entry: entry.clone(),
+            exists: false,
+            current_size: None,
+            current_modified: None,
+            drift: Some("missing".to_string()),
+        };
+
+        let plain = format_entry_info(&info, false).unwrap();
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines[0], format!("ID: {}", entry.id));
+        assert_eq!(lines[1], "Record: history");
+        assert_eq!(lines[4], "Size: 10 B");
+        assert_eq!(lines[6], "Path: /tmp/a.txt");
+        assert_eq!(lines[7], "Exists: no");
+        assert_eq!(lines[8], "Drift: missing");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_entry_info(&info, true).unwrap()).unwrap();
+        assert_eq!(json["id"], entry.id.to_string());
+        assert_eq!(json["record_type"], "history");
+        assert_eq!(json["exists"], false);
+        assert_eq!(json["drift"], "missing");
+    }
+
+    #[test]
+    fn test_get_version_info() {
+        let version_info = get_version_info(false).unwrap();
+        assert_eq!(version_info, format!("clp {}", env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_version_info_verbose() {
+        let env = setup_test_env();
+        records::write_clipboard(&[create_mock_record_entry(None, None, None, None, None)])
+            .unwrap();
+
+        let version_info = get_version_info(true).unwrap();
+        let lines: Vec<&str> = version_info.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[1],
+            format!("State directory: {}", env.state_dir.display())
+        );
+        assert!(lines[2].starts_with("OS clipboard backend: "));
+        assert_eq!(lines[3], "Clipboard entries: 1");
+        assert_eq!(lines[4], "History entries: 0");
+    }
+
+    #[test]
+    #[serial]
+    fn test_format_storage_paths() {
+        let env = setup_test_env();
+        let clipboard = records::get_storage_path(RecordType::Clipboard).unwrap();
+        let history = records::get_storage_path(RecordType::History).unwrap();
+        let paths = get_storage_paths().unwrap();
+
+        let plain = format_storage_paths(&paths, false).unwrap();
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                clipboard.to_str().unwrap(),
+                history.to_str().unwrap(),
+                env.state_dir.to_str().unwrap(),
+            ]
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_storage_paths(&paths, true).unwrap()).unwrap();
+        assert_eq!(json["clipboard"], clipboard.to_str().unwrap());
+        assert_eq!(json["history"], history.to_str().unwrap());
+        assert_eq!(json["state_dir"], env.state_dir.to_str().unwrap());
+    }
+
+    #[test]
+    fn test_get_warning_exit_code() {
+        let nothing_to_paste = [AppWarning::Record(RecordWarning::NothingToPaste)];
+        let other = [AppWarning::Record(RecordWarning::EntryNotFound)];
+
+        assert_eq!(get_warning_exit_code(&nothing_to_paste, false, false), None);
+        assert_eq!(
+            get_warning_exit_code(&nothing_to_paste, false, true),
+            Some(EXIT_NOTHING_TO_PASTE)
+        );
+        assert_eq!(
+            get_warning_exit_code(&nothing_to_paste, true, true),
+            Some(EXIT_NOTHING_TO_PASTE)
+        );
+        assert_eq!(get_warning_exit_code(&other, false, true), None);
+        assert_eq!(
+            get_warning_exit_code(&other, true, false),
+            Some(EXIT_WARNINGS)
+        );
+        assert_eq!(get_warning_exit_code(&[], true, true), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_format_entry_ids() {
+        let env = setup_test_env();
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt"]
+            .iter()
+            .map(|name| env.source_dir.join(name))
+            .collect();
+        for path in &paths {
+            write(path, "content").unwrap();
+        }
+
+        let (infos, _) = handle_transfer(
+            paths,
+            Operation::Copy,
+            &TransferOptions {
+                print_id: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let printed = format_entry_ids(&infos);
+        let clipboard = read_entries(&RecordType::Clipboard).unwrap();
+        let mut printed_ids: Vec<&str> = printed.lines().collect();
+        let mut clipboard_ids: Vec<String> =
+            clipboard.iter().map(|entry| entry.id.to_string()).collect();
+        printed_ids.sort();
+        clipboard_ids.sort();
+        assert_eq!(printed_ids, clipboard_ids);
+        assert_eq!(format_entry_ids(&[AppInfo::Dedup { count: 1 }]), "");
+    }
 }
@@ -1,10 +1,21 @@
+use chrono::{
+    DateTime, Local,
+    format::{Item, StrftimeItems},
+};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use serde_with::{TimestampSeconds, serde_as};
-use std::{path::PathBuf, time::SystemTime};
+use serde_with::{TimestampSeconds, TimestampSecondsWithFrac, formats::Flexible, serde_as};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 use strum_macros::Display;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Display)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display, ValueEnum,
+)]
 pub enum Operation {
     #[strum(to_string = "copy")]
     Copy,
@@ -21,7 +32,8 @@ pub enum EntryType {
     Symlink,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Display)]
+#[serde(rename_all = "lowercase")]
 pub enum RecordType {
     #[strum(to_string = "clipboard")]
     Clipboard,
@@ -29,12 +41,21 @@ pub enum RecordType {
     History,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ReorderPosition {
+    #[strum(to_string = "front")]
+    Front,
+    #[strum(to_string = "back")]
+    Back,
+}
+
 #[derive(Debug, Clone)]
 pub struct Metadata {
     pub modified: SystemTime,
     pub size: Option<u64>,
     pub entry_type: EntryType,
     pub absolute_path: PathBuf,
+    pub canonical: bool,
 }
 
 #[serde_as]
@@ -43,26 +64,182 @@ pub struct RecordEntry {
     #[serde_as(as = "TimestampSeconds")]
     pub timestamp: SystemTime,
     pub size: Option<u64>,
+    #[serde(default)]
+    pub size_is_lower_bound: bool,
+    #[serde(default)]
+    pub non_canonical: bool,
+    #[serde(default)]
+    pub exclude_hidden: bool,
     pub operation: Operation,
     pub entry_type: EntryType,
     pub path: PathBuf,
+    #[serde(default)]
+    pub destination: Option<PathBuf>,
+    #[serde(default)]
+    pub source: Option<PathBuf>,
+    // Kept with sub-second precision so undo can tell edits made right after the paste.
+    #[serde_as(as = "Option<TimestampSecondsWithFrac<f64, Flexible>>")]
+    #[serde(default)]
+    pub pasted_at: Option<SystemTime>,
+    // Undo only deletes or moves back what the paste created; a merged, overwritten or
+    // pre-existing destination is left alone.
+    #[serde(default)]
+    pub created_destination: bool,
+    #[serde(default)]
+    pub note: Option<String>,
     pub id: Uuid,
 }
 
+#[serde_as]
+#[derive(Serialize, Debug, Clone)]
+pub struct EntryInfo {
+    pub record_type: RecordType,
+    #[serde(flatten)]
+    pub entry: RecordEntry,
+    pub exists: bool,
+    pub current_size: Option<u64>,
+    #[serde_as(as = "Option<TimestampSeconds>")]
+    pub current_modified: Option<SystemTime>,
+    pub drift: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProgressEvent {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current_path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordBenchmark {
+    pub backend: &'static str,
+    pub entries: usize,
+    pub rounds: u32,
+    pub write: Duration,
+    pub read: Duration,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoragePaths {
+    pub state_dir: PathBuf,
+    pub clipboard: PathBuf,
+    pub history: PathBuf,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PasteSummary {
+    pub pasted: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub destinations: Vec<PathBuf>,
+    pub warnings: Vec<SummaryWarning>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SummaryWarning {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+pub const RECORD_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct RecordData {
+    #[serde(default)]
+    pub version: u32,
     pub entries: Vec<RecordEntry>,
 }
 
+// One line of an append-only record log. A put adds an entry in front of the others, or replaces
+// the entry with the same id in place; a remove is a tombstone for an earlier put.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordLogLine {
+    Version(u32),
+    Put(RecordEntry),
+    Remove(Uuid),
+}
+
+#[derive(Serialize, Debug)]
+pub struct HumanReadableRecordEntry<'a> {
+    #[serde(flatten)]
+    pub entry: &'a RecordEntry,
+    pub timestamp_iso: String,
+    pub size_human: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HumanReadableRecordData<'a> {
+    pub version: u32,
+    pub entries: Vec<HumanReadableRecordEntry<'a>>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Action {
-    Copy(Vec<PathBuf>),
-    Cut(Vec<PathBuf>),
-    Link(Vec<PathBuf>),
-    Paste(PathBuf),
-    Clipboard,
-    History,
+    Copy {
+        paths: Vec<PathBuf>,
+        options: TransferOptions,
+    },
+    Cut {
+        paths: Vec<PathBuf>,
+        options: TransferOptions,
+    },
+    Link {
+        paths: Vec<PathBuf>,
+        options: TransferOptions,
+    },
+    Paste {
+        path: PathBuf,
+        options: PasteOptions,
+    },
+    List {
+        options: ListOptions,
+    },
+    History {
+        options: ListOptions,
+    },
+    ExportHistoryCsv {
+        path: PathBuf,
+    },
+    ClearHistory {
+        older_than: Duration,
+    },
+    UndoSince {
+        since: Duration,
+    },
+    Replay {
+        id: Uuid,
+        destination: PathBuf,
+        options: PasteOptions,
+    },
+    Stage {
+        paths: Vec<PathBuf>,
+        link_to: PathBuf,
+        relative: bool,
+    },
     Clear,
+    Prune {
+        history: bool,
+    },
+    Dedup,
+    Reorder {
+        id: Uuid,
+        position: ReorderPosition,
+    },
+    Info {
+        id: Uuid,
+        json: bool,
+    },
+    Version {
+        verbose: bool,
+    },
+    Paths {
+        json: bool,
+    },
+    Bench {
+        entries: usize,
+        rounds: u32,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -71,10 +248,129 @@ pub struct PasteContent {
     pub source: RecordType,
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Default)]
+pub struct TransferOptions {
+    pub stdin_content_name: Option<String>,
+    pub keep_going: bool,
+    pub atomic_batch: bool,
+    pub max_depth: Option<usize>,
+    pub recursive_symlink_safe: bool,
+    pub if_newer: bool,
+    pub follow_symlinks: bool,
+    pub exclude_larger_than: Option<u64>,
+    pub dedup_by: Option<DedupMode>,
+    pub watch: bool,
+    pub stdin_null: bool,
+    pub max_entries: Option<usize>,
+    pub follow_into_history: Option<Duration>,
+    pub print_id: bool,
+    pub snapshot: bool,
+    pub cwd: Option<PathBuf>,
+    pub exclude_hidden: bool,
+    pub replace: bool,
+    #[cfg(feature = "os-clipboard")]
+    pub from_clipboard_paths: bool,
+    pub sort_on_stage: Option<PasteSort>,
+    pub only_entry_type: Option<EntryType>,
+    pub quiet_unmatched: bool,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub enum DedupMode {
+    Content,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub follow: bool,
+    pub operation: Option<Operation>,
+    pub plain: bool,
+    pub json: bool,
+    pub json_lines: bool,
+    pub select_all: bool,
+    pub null: bool,
+    pub time_format: Option<String>,
+    pub group_by: Option<GroupBy>,
+    pub non_interactive: bool,
+    pub watch_validity: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub enum GroupBy {
+    Operation,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PasteOptions {
+    pub conflict: Option<ConflictPolicy>,
+    pub merge: bool,
+    pub strict: bool,
+    pub verify_type: bool,
+    pub retry: u32,
+    pub cd_file: Option<PathBuf>,
+    pub prompt_each: bool,
+    pub template: Option<String>,
+    pub group_by_source_dir: bool,
+    pub relative_links: bool,
+    pub link_fallback_copy: bool,
+    pub destination_exists_ok: bool,
+    pub parents: bool,
+    pub paste_as: Option<Operation>,
+    pub exclude: Vec<String>,
+    pub fail_if_empty: bool,
+    pub preserve_structure: bool,
+    pub ownership: Ownership,
+    pub file_mode: Option<u32>,
+    pub dir_mode: Option<u32>,
+    pub verify_free_inodes: bool,
+    pub conflict_log: Option<PathBuf>,
+    pub sort: Option<PasteSort>,
+    pub into_tmp: bool,
+    pub summary_json: bool,
+    pub progress_json: bool,
+    pub non_interactive: bool,
+    pub default_destination: bool,
+    pub renames: HashMap<Uuid, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ownership {
+    pub owner: Option<String>,
+    pub group: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub enum PasteSort {
+    Path,
+    Size,
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub enum ConflictPolicy {
+    Overwrite,
+    Skip,
+    Rename,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Display)]
 pub enum CollisionResolution {
+    #[strum(to_string = "skip")]
     Skip,
+    #[strum(to_string = "overwrite")]
     Overwrite,
+    #[strum(to_string = "rename")]
+    Rename,
+}
+
+// What happened to each staged entry that a paste considered. Excluded entries are left out.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PasteOutcome {
+    Pasted,
+    Skipped,
+    Failed,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -99,10 +395,162 @@ impl CollisionResolutionChoice {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasteConfirmationChoice {
+    Yes,
+    No,
+    Quit,
+}
+
+impl PasteConfirmationChoice {
+    pub fn from_str(input: &str) -> Option<PasteConfirmationChoice> {
+        match input.to_lowercase().as_str() {
+            "y" => Some(PasteConfirmationChoice::Yes),
+            "n" => Some(PasteConfirmationChoice::No),
+            "q" => Some(PasteConfirmationChoice::Quit),
+            _ => None,
+        }
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Rounds down to the largest whole unit, matching the units accepted by `--older-than`.
+pub fn format_duration(duration: Duration) -> String {
+    const UNITS: [(u64, &str); 4] = [(24 * 60 * 60, "d"), (60 * 60, "h"), (60, "m"), (1, "s")];
+    let seconds = duration.as_secs();
+    UNITS
+        .iter()
+        .find(|(unit_seconds, _)| seconds >= *unit_seconds)
+        .map(|(unit_seconds, unit)| format!("{}{}", seconds / unit_seconds, unit))
+        .unwrap_or_else(|| "0s".to_string())
+}
+
+pub fn format_entry_size(entry: &RecordEntry) -> String {
+    match entry.size {
+        None => "directory".to_string(),
+        Some(size) if entry.size_is_lower_bound => format!(">= {}", format_size(size)),
+        Some(size) => format_size(size),
+    }
+}
+
+pub const ISO_TIME_FORMAT: &str = "%+";
+const DISPLAY_TIME_FORMAT: &str = "%a, %b %d %Y, %H:%M:%S";
+
+pub fn format_timestamp(timestamp: SystemTime) -> String {
+    format_timestamp_with(timestamp, DISPLAY_TIME_FORMAT)
+}
+
+// The format must have been checked with `validate_time_format`, since chrono panics while
+// rendering an invalid one.
+pub fn format_timestamp_with(timestamp: SystemTime, format: &str) -> String {
+    let local_datetime: DateTime<Local> = timestamp.into();
+    local_datetime.format(format).to_string()
+}
+
+pub fn validate_time_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| item == Item::Error)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_timestamp_with() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(86400 * 365);
+        let local_datetime: DateTime<Local> = timestamp.into();
+        assert_eq!(format_timestamp_with(timestamp, "%Y"), "1971");
+        assert_eq!(
+            format_timestamp_with(timestamp, ISO_TIME_FORMAT),
+            local_datetime.to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn test_validate_time_format() {
+        assert!(validate_time_format("%Y-%m-%d %H:%M"));
+        assert!(validate_time_format(ISO_TIME_FORMAT));
+        assert!(!validate_time_format("%Q"));
+        assert!(!validate_time_format("%"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+        assert_eq!(format_size(2 * 1024 * 1024 * 1024 * 1024), "2.0 TiB");
+        assert_eq!(format_size(u64::MAX), "16777216.0 TiB");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(150)), "2m");
+        assert_eq!(format_duration(Duration::from_secs(3 * 60 * 60 + 5)), "3h");
+        assert_eq!(format_duration(Duration::from_secs(2 * 24 * 60 * 60)), "2d");
+    }
+
+    #[test]
+    fn test_format_entry_size() {
+        let mut entry = RecordEntry {
+            id: Uuid::new_v4(),
+            timestamp: SystemTime::now(),
+            size: None,
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: None,
+            operation: Operation::Copy,
+            entry_type: EntryType::Directory,
+            path: PathBuf::from("/tmp/dir"),
+        };
+        assert_eq!(format_entry_size(&entry), "directory");
+        entry.size = Some(2048);
+        assert_eq!(format_entry_size(&entry), "2.0 KiB");
+        entry.size_is_lower_bound = true;
+        assert_eq!(format_entry_size(&entry), ">= 2.0 KiB");
+    }
+
+    #[test]
+    fn test_paste_confirmation_choice_from_str() {
+        assert_eq!(
+            PasteConfirmationChoice::from_str("Y"),
+            Some(PasteConfirmationChoice::Yes)
+        );
+        assert_eq!(
+            PasteConfirmationChoice::from_str("n"),
+            Some(PasteConfirmationChoice::No)
+        );
+        assert_eq!(
+            PasteConfirmationChoice::from_str("q"),
+            Some(PasteConfirmationChoice::Quit)
+        );
+        assert_eq!(PasteConfirmationChoice::from_str("a"), None);
+    }
+
     #[test]
     fn test_collision_resolution_choice_from_str_valid() {
         assert_eq!(
@@ -199,6 +647,14 @@ mod tests {
             id,
             timestamp,
             size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: None,
             operation: Operation::Copy,
             entry_type: EntryType::File,
             path: PathBuf::from("/tmp/test.txt"),
@@ -207,6 +663,14 @@ mod tests {
             id,
             timestamp,
             size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: None,
             operation: Operation::Copy,
             entry_type: EntryType::File,
             path: PathBuf::from("/tmp/test.txt"),
@@ -223,6 +687,14 @@ mod tests {
             id,
             timestamp,
             size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: None,
             operation: Operation::Copy,
             entry_type: EntryType::File,
             path: PathBuf::from("/tmp/test.txt"),
@@ -249,11 +721,20 @@ mod tests {
             id: Uuid::new_v4(),
             timestamp: SystemTime::now(),
             size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: None,
             operation: Operation::Copy,
             entry_type: EntryType::File,
             path: PathBuf::from("/tmp/test.txt"),
         };
         let data = RecordData {
+            version: RECORD_SCHEMA_VERSION,
             entries: vec![entry],
         };
 
@@ -261,12 +742,112 @@ mod tests {
         assert!(serialized.is_ok());
     }
 
+    #[test]
+    fn test_record_entry_note_round_trip() {
+        let entry = RecordEntry {
+            id: Uuid::new_v4(),
+            timestamp: SystemTime::UNIX_EPOCH,
+            size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: Some("for Q3 review".to_string()),
+            created_destination: false,
+            destination: None,
+            operation: Operation::Copy,
+            entry_type: EntryType::File,
+            path: PathBuf::from("/tmp/report.pdf"),
+        };
+        let data = RecordData {
+            version: RECORD_SCHEMA_VERSION,
+            entries: vec![entry.clone()],
+        };
+
+        let serialized = toml::to_string(&data).unwrap();
+        assert!(serialized.contains("note = \"for Q3 review\""));
+        let deserialized: RecordData = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.entries, vec![entry.clone()]);
+
+        let without_note = RecordEntry {
+            note: None,
+            created_destination: false,
+            ..entry
+        };
+        let serialized = toml::to_string(&RecordData {
+            version: RECORD_SCHEMA_VERSION,
+            entries: vec![without_note.clone()],
+        })
+        .unwrap();
+        assert!(!serialized.contains("note"));
+        let deserialized: RecordData = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.entries, vec![without_note]);
+    }
+
+    #[test]
+    fn test_record_entry_deserialize_without_size_is_lower_bound() {
+        let id = Uuid::new_v4();
+        let toml_string = format!(
+            r#"
+            [[entries]]
+            timestamp = 0
+            size = 100
+            operation = "Copy"
+            entry_type = "Directory"
+            path = "/tmp/dir"
+            id = "{}"
+            "#,
+            id
+        );
+        let data: RecordData = toml::from_str(&toml_string).unwrap();
+        assert_eq!(data.entries[0].id, id);
+        assert!(!data.entries[0].size_is_lower_bound);
+        assert_eq!(data.entries[0].destination, None);
+    }
+
+    #[test]
+    fn test_record_entry_destination_round_trip() {
+        let entry = RecordEntry {
+            id: Uuid::new_v4(),
+            timestamp: SystemTime::UNIX_EPOCH,
+            size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: Some(PathBuf::from("/tmp/dest")),
+            operation: Operation::Copy,
+            entry_type: EntryType::File,
+            path: PathBuf::from("/tmp/dest/test.txt"),
+        };
+        let data = RecordData {
+            version: RECORD_SCHEMA_VERSION,
+            entries: vec![entry.clone()],
+        };
+
+        let serialized = toml::to_string(&data).unwrap();
+        let deserialized: RecordData = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.entries[0], entry);
+    }
+
     #[test]
     fn test_paste_content_creation() {
         let entry = RecordEntry {
             id: Uuid::new_v4(),
             timestamp: SystemTime::now(),
             size: Some(100),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            destination: None,
             operation: Operation::Copy,
             entry_type: EntryType::File,
             path: PathBuf::from("/tmp/test.txt"),
@@ -287,6 +868,7 @@ mod tests {
             size: Some(1024),
             entry_type: EntryType::File,
             absolute_path: PathBuf::from("/tmp/test.txt"),
+            canonical: true,
         };
 
         assert_eq!(metadata.size, Some(1024));
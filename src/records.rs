@@ -1,22 +1,47 @@
-use dirs::state_dir;
+use chrono::{DateTime, Local};
+use dirs::{home_dir, state_dir};
+use serde::Serialize;
 use std::{
-    fs::{File, create_dir_all, remove_dir, remove_file},
+    collections::{HashMap, HashSet, VecDeque},
+    env,
+    fs::{
+        File, OpenOptions, copy, create_dir_all, metadata, remove_dir, remove_dir_all, remove_file,
+        rename,
+    },
     io::{ErrorKind, Read, Write},
     path::{Path, PathBuf},
-    sync::Mutex,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
 };
 use toml::{de::from_str as toml_from_str, ser::to_string as toml_to_string};
 use uuid::Uuid;
 
 use crate::{
     errors::{AppError, AppInfo, AppWarning, RecordError, RecordWarning},
-    models::{RecordData, RecordEntry, RecordType},
+    files::get_metadata,
+    models::{
+        EntryType, HumanReadableRecordData, HumanReadableRecordEntry, ISO_TIME_FORMAT, Operation,
+        RECORD_SCHEMA_VERSION, RecordBenchmark, RecordData, RecordEntry, RecordLogLine, RecordType,
+        ReorderPosition, StoragePaths, format_size, format_timestamp_with,
+    },
 };
 
 static CLIPBOARD_MUTEX: Mutex<()> = Mutex::new(());
 static HISTORY_MUTEX: Mutex<()> = Mutex::new(());
-
-const MAX_CLIPBOARD_ENTRIES: usize = 200;
+static RECORD_MIGRATION: OnceLock<()> = OnceLock::new();
+static RECORD_LOCK: Mutex<(usize, Option<File>)> = Mutex::new((0, None));
+
+pub const MAX_CLIPBOARD_ENTRIES: usize = 200;
+pub const MAX_HISTORY_ENTRIES: usize = 200;
+const HUMAN_READABLE_ENV: &str = "FILE_CLIPPER_HUMAN_READABLE";
+const STORAGE_ENV: &str = "FILE_CLIPPER_STORAGE";
+// A log is rewritten once it holds this many lines per live entry, plus some slack so small
+// records are not compacted on every write.
+const LOG_COMPACT_FACTOR: usize = 2;
+const LOG_COMPACT_SLACK: usize = 32;
+const CONTENT_DIR: &str = "content";
+const LOCK_FILE: &str = "file_clipper.lock";
+const LEGACY_STATE_DIR: &str = ".local/state/file_clipper";
 
 pub fn read_entries(mode: &RecordType) -> Result<Vec<RecordEntry>, AppError> {
     let entries = match mode {
@@ -35,14 +60,39 @@ pub fn read_history() -> Result<Option<Vec<RecordEntry>>, RecordError> {
 }
 
 pub fn write_clipboard(entries: &[RecordEntry]) -> Result<(), RecordError> {
-    write_records(entries, RecordType::Clipboard)
+    write_records(entries, RecordType::Clipboard, MAX_CLIPBOARD_ENTRIES).map(|_| ())
+}
+
+pub fn write_clipboard_with_max(
+    entries: &[RecordEntry],
+    max_entries: Option<usize>,
+) -> Result<(), RecordError> {
+    write_records(entries, RecordType::Clipboard, get_max_entries(max_entries)).map(|_| ())
+}
+
+// A per-run limit can only shrink the clipboard, never lift the global cap.
+pub fn get_max_entries(max_entries: Option<usize>) -> usize {
+    max_entries.map_or(MAX_CLIPBOARD_ENTRIES, |max_entries| {
+        max_entries.min(MAX_CLIPBOARD_ENTRIES)
+    })
+}
+
+// History is an audit trail, so losing its oldest records is reported rather than silent.
+pub fn write_history(entries: &[RecordEntry]) -> Result<Option<AppInfo>, RecordError> {
+    let dropped = write_records(entries, RecordType::History, MAX_HISTORY_ENTRIES)?;
+    Ok((dropped > 0).then_some(AppInfo::HistoryCapped {
+        dropped,
+        max: MAX_HISTORY_ENTRIES,
+    }))
 }
 
-pub fn write_history(entries: &[RecordEntry]) -> Result<(), RecordError> {
-    write_records(entries, RecordType::History)
+pub fn get_records_modified(record_type: &RecordType) -> Result<Option<SystemTime>, RecordError> {
+    let path = get_storage_path(record_type.clone())?;
+    Ok(metadata(path).and_then(|metadata| metadata.modified()).ok())
 }
 
 pub fn handle_remove(id: Uuid) -> Result<Vec<AppWarning>, AppError> {
+    let _lock = lock_records()?;
     let mut warnings = Vec::new();
     let clipboard_entries = match read_clipboard() {
         Ok(Some(entries)) => entries,
@@ -65,7 +115,197 @@ pub fn handle_remove(id: Uuid) -> Result<Vec<AppWarning>, AppError> {
     Ok(warnings)
 }
 
+pub fn handle_reorder(
+    id: Uuid,
+    position: ReorderPosition,
+) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
+    let _lock = lock_records()?;
+    let mut clipboard_entries = read_clipboard()?.unwrap_or(Vec::new());
+    let Some(index) = clipboard_entries.iter().position(|entry| entry.id == id) else {
+        return Ok((
+            Vec::new(),
+            vec![AppWarning::Record(RecordWarning::EntryNotFound)],
+        ));
+    };
+    let target = match position {
+        ReorderPosition::Front => 0,
+        ReorderPosition::Back => clipboard_entries.len() - 1,
+    };
+    move_entry(&mut clipboard_entries, index, target);
+    write_clipboard(&clipboard_entries)?;
+    Ok((
+        vec![AppInfo::Reorder {
+            path: clipboard_entries[target].path.clone(),
+            position,
+        }],
+        Vec::new(),
+    ))
+}
+
+// Shifts the entries in between so the rest of the order is preserved.
+pub fn move_entry<T>(entries: &mut [T], from: usize, to: usize) {
+    if from < to {
+        entries[from..=to].rotate_left(1);
+    } else {
+        entries[to..=from].rotate_right(1);
+    }
+}
+
+pub fn prune_records(include_history: bool) -> Result<Vec<AppInfo>, AppError> {
+    let _lock = lock_records()?;
+    let mut infos = Vec::new();
+    let clipboard_entries = read_clipboard()?.unwrap_or(Vec::new());
+    let (clipboard_entries, pruned) = retain_valid_entries(clipboard_entries);
+    if pruned > 0 {
+        write_clipboard(&clipboard_entries)?;
+    }
+    infos.push(AppInfo::Prune {
+        count: pruned,
+        record_type: RecordType::Clipboard,
+    });
+
+    if include_history {
+        let history_entries = read_history()?.unwrap_or(Vec::new());
+        let (history_entries, pruned) = retain_valid_entries(history_entries);
+        if pruned > 0 {
+            write_history(&history_entries)?;
+        }
+        infos.push(AppInfo::Prune {
+            count: pruned,
+            record_type: RecordType::History,
+        });
+    }
+    Ok(infos)
+}
+
+fn retain_valid_entries(entries: Vec<RecordEntry>) -> (Vec<RecordEntry>, usize) {
+    let total = entries.len();
+    let valid_entries: Vec<RecordEntry> = entries
+        .into_iter()
+        .filter(|entry| get_metadata(&entry.path, false).is_ok())
+        .collect();
+    let pruned = total - valid_entries.len();
+    (valid_entries, pruned)
+}
+
+pub fn dedup_clipboard() -> Result<Vec<AppInfo>, AppError> {
+    let _lock = lock_records()?;
+    let clipboard_entries = read_clipboard()?.unwrap_or(Vec::new());
+    let (clipboard_entries, merged) = dedup_entries(clipboard_entries);
+    if merged > 0 {
+        write_clipboard(&clipboard_entries)?;
+    }
+    Ok(vec![AppInfo::Dedup { count: merged }])
+}
+
+// Paths are canonicalized when staged, so identical paths mean the same entry. The most
+// recently staged duplicate wins and takes the place of the first occurrence.
+pub fn dedup_entries(entries: Vec<RecordEntry>) -> (Vec<RecordEntry>, usize) {
+    let total = entries.len();
+    let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+    let mut unique_entries: Vec<RecordEntry> = Vec::with_capacity(total);
+    for entry in entries {
+        match positions.get(&entry.path) {
+            Some(&position) => {
+                if entry.timestamp >= unique_entries[position].timestamp {
+                    unique_entries[position] = entry;
+                }
+            }
+            None => {
+                positions.insert(entry.path.clone(), unique_entries.len());
+                unique_entries.push(entry);
+            }
+        }
+    }
+    let merged = total - unique_entries.len();
+    (unique_entries, merged)
+}
+
+pub fn export_history_csv(path: &Path) -> Result<Vec<AppInfo>, AppError> {
+    let history_entries = read_history()?.unwrap_or(Vec::new());
+    File::create(path)
+        .and_then(|mut file| write_history_csv(&history_entries, &mut file))
+        .map_err(|source| RecordError::ExportCsv {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    Ok(vec![AppInfo::ExportCsv {
+        path: path.to_path_buf(),
+        count: history_entries.len(),
+    }])
+}
+
+pub fn write_history_csv<W: Write>(entries: &[RecordEntry], writer: &mut W) -> std::io::Result<()> {
+    write_csv_record(
+        writer,
+        &[
+            "id",
+            "operation",
+            "entry_type",
+            "size",
+            "timestamp_iso",
+            "path",
+        ],
+    )?;
+    for entry in entries {
+        write_csv_record(
+            writer,
+            &[
+                &entry.id.to_string(),
+                &entry.operation.to_string(),
+                &entry.entry_type.to_string(),
+                &entry.size.map(|size| size.to_string()).unwrap_or_default(),
+                &format_timestamp_with(entry.timestamp, ISO_TIME_FORMAT),
+                &entry.path.to_string_lossy(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// Every field goes through the same quoting, so a value never has to be known to be safe.
+fn write_csv_record<W: Write>(writer: &mut W, fields: &[&str]) -> std::io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|field| quote_csv_field(field)).collect();
+    writeln!(writer, "{}", fields.join(","))
+}
+
+// Fields containing a delimiter, quote or line break are quoted, with inner quotes doubled.
+fn quote_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn clear_history_older_than(older_than: Duration) -> Result<Vec<AppInfo>, AppError> {
+    let _lock = lock_records()?;
+    let history_entries = read_history()?.unwrap_or(Vec::new());
+    let cutoff = SystemTime::now()
+        .checked_sub(older_than)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let (history_entries, removed) = retain_newer_than(history_entries, cutoff);
+    if removed > 0 {
+        write_history(&history_entries)?;
+    }
+    Ok(vec![AppInfo::ClearHistory { count: removed }])
+}
+
+pub fn retain_newer_than(
+    entries: Vec<RecordEntry>,
+    cutoff: SystemTime,
+) -> (Vec<RecordEntry>, usize) {
+    let total = entries.len();
+    let newer_entries: Vec<RecordEntry> = entries
+        .into_iter()
+        .filter(|entry| entry.timestamp >= cutoff)
+        .collect();
+    let removed = total - newer_entries.len();
+    (newer_entries, removed)
+}
+
 pub fn clear_records() -> Result<Vec<AppInfo>, AppError> {
+    let _lock = lock_records()?;
     let mut infos = Vec::new();
     for record_type in [RecordType::Clipboard, RecordType::History] {
         let record_path = get_storage_path(record_type)?;
@@ -82,9 +322,21 @@ pub fn clear_records() -> Result<Vec<AppInfo>, AppError> {
         };
     }
 
-    let dir_path = state_dir()
-        .ok_or(RecordError::GetStateDir)?
-        .join("file_clipper");
+    let content_path = get_state_dir()?.join(CONTENT_DIR);
+    match remove_dir_all(&content_path) {
+        Err(source) if source.kind() != ErrorKind::NotFound => {
+            return Err(AppError::Record(RecordError::ClearRecords {
+                path: content_path.clone(),
+                source,
+            }));
+        }
+        Err(_) => {}
+        Ok(_) => {
+            infos.push(AppInfo::Clear { path: content_path });
+        }
+    }
+
+    let dir_path = get_state_dir()?;
     match remove_dir(&dir_path) {
         Err(source) if source.kind() != ErrorKind::NotFound => {
             return Err(AppError::Record(RecordError::ClearRecords {
@@ -99,15 +351,171 @@ pub fn clear_records() -> Result<Vec<AppInfo>, AppError> {
     Ok(infos)
 }
 
-fn get_storage_path(record_type: RecordType) -> Result<PathBuf, RecordError> {
-    let dir_path = state_dir()
+pub fn get_content_dir() -> Result<PathBuf, RecordError> {
+    let dir_path = get_state_dir()?.join(CONTENT_DIR);
+    create_dir_all(&dir_path).map_err(|source| RecordError::CreateConfigDir {
+        path: dir_path.to_path_buf(),
+        source,
+    })?;
+    Ok(dir_path)
+}
+
+// Released when the last guard in this process is dropped. Nested calls share the lock already
+// held, since a second file lock from the same process would wait on the first forever.
+pub struct RecordLock;
+
+impl Drop for RecordLock {
+    fn drop(&mut self) {
+        let mut held = RECORD_LOCK.lock().unwrap();
+        held.0 -= 1;
+        if held.0 == 0 {
+            held.1 = None;
+        }
+    }
+}
+
+// Taken around every read-modify-write of the records, so another clp process, such as a
+// running `copy --watch`, cannot interleave its own write and drop entries.
+pub fn lock_records() -> Result<RecordLock, RecordError> {
+    let mut held = RECORD_LOCK.lock().unwrap();
+    if held.0 == 0 {
+        held.1 = Some(open_lock_file()?);
+    }
+    held.0 += 1;
+    Ok(RecordLock)
+}
+
+// The lock file sits next to the state directory rather than in it, so `clp clear` can remove
+// the directory while the lock is held.
+fn open_lock_file() -> Result<File, RecordError> {
+    let dir_path = state_dir().ok_or(RecordError::GetStateDir)?;
+    create_dir_all(&dir_path).map_err(|source| RecordError::CreateConfigDir {
+        path: dir_path.to_path_buf(),
+        source,
+    })?;
+    let path = dir_path.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|source| RecordError::CreateRecordFile {
+            path: path.clone(),
+            source,
+        })?;
+    file.lock()
+        .map_err(|source| RecordError::LockRecordFile { path, source })?;
+    Ok(file)
+}
+
+pub fn get_state_dir() -> Result<PathBuf, RecordError> {
+    Ok(state_dir()
         .ok_or(RecordError::GetStateDir)?
-        .join("file_clipper");
+        .join("file_clipper"))
+}
+
+pub fn get_storage_paths() -> Result<StoragePaths, RecordError> {
+    Ok(StoragePaths {
+        state_dir: get_state_dir()?,
+        clipboard: get_storage_path(RecordType::Clipboard)?,
+        history: get_storage_path(RecordType::History)?,
+    })
+}
+
+pub fn get_storage_path(record_type: RecordType) -> Result<PathBuf, RecordError> {
+    let dir_path = get_state_dir()?;
     create_dir_all(&dir_path).map_err(|source| RecordError::CreateConfigDir {
         path: dir_path.to_path_buf(),
         source,
     })?;
-    Ok(dir_path.join(format!("{}.toml", record_type)))
+    Ok(dir_path.join(format!(
+        "{}.{}",
+        record_type,
+        get_storage_backend().extension()
+    )))
+}
+
+// Runs at most once per process, before anything reads the records. The messages are returned
+// so the caller decides where they go.
+pub fn migrate_record_files() -> Result<Vec<AppInfo>, RecordError> {
+    if RECORD_MIGRATION.set(()).is_err() {
+        return Ok(Vec::new());
+    }
+    let dir_path = get_state_dir()?;
+    create_dir_all(&dir_path).map_err(|source| RecordError::CreateConfigDir {
+        path: dir_path.to_path_buf(),
+        source,
+    })?;
+    let mut infos = match home_dir() {
+        Some(home) => migrate_legacy_records(&home.join(LEGACY_STATE_DIR), &dir_path)?,
+        None => Vec::new(),
+    };
+    infos.extend(import_other_backend(
+        &dir_path,
+        get_storage_backend(),
+        get_other_storage_backend(),
+    )?);
+    Ok(infos)
+}
+
+// Switching FILE_CLIPPER_STORAGE would otherwise start from empty records. The other format's
+// file is removed once imported, so switching back later imports the current records again.
+fn import_other_backend(
+    dir_path: &Path,
+    selected: &dyn StorageBackend,
+    other: &dyn StorageBackend,
+) -> Result<Vec<AppInfo>, RecordError> {
+    let mutex = Mutex::new(());
+    let mut infos = Vec::new();
+    for record_type in [RecordType::Clipboard, RecordType::History] {
+        let to = dir_path.join(format!("{}.{}", record_type, selected.extension()));
+        let from = dir_path.join(format!("{}.{}", record_type, other.extension()));
+        if to.exists() || !from.is_file() {
+            continue;
+        }
+        let entries = other.read(&from, &mutex)?.unwrap_or_default();
+        selected.write(&to, &mutex, &entries)?;
+        remove_file(&from).map_err(|source| RecordError::RemoveImportedRecordFile {
+            path: from.clone(),
+            source,
+        })?;
+        infos.push(AppInfo::ImportRecordFile { from, to });
+    }
+    Ok(infos)
+}
+
+// Older releases always stored records under ~/.local/state, which differs from the state
+// directory when XDG_STATE_HOME is set. Records are only moved while the new location has none
+// in any storage format. Older releases only wrote TOML, so that is all there is to move.
+fn migrate_legacy_records(legacy_dir: &Path, dir_path: &Path) -> Result<Vec<AppInfo>, RecordError> {
+    let has_records = [RecordType::Clipboard, RecordType::History]
+        .iter()
+        .flat_map(|record_type| {
+            [TomlStorage.extension(), AppendStorage.extension()]
+                .map(|extension| dir_path.join(format!("{}.{}", record_type, extension)))
+        })
+        .any(|path| path.exists());
+    if legacy_dir == dir_path || has_records {
+        return Ok(Vec::new());
+    }
+    let file_names = [RecordType::Clipboard, RecordType::History]
+        .map(|record_type| format!("{}.toml", record_type));
+    let mut infos = Vec::new();
+    for name in file_names {
+        let from = legacy_dir.join(&name);
+        if !from.is_file() {
+            continue;
+        }
+        let to = dir_path.join(&name);
+        rename(&from, &to)
+            .or_else(|_| copy(&from, &to).and_then(|_| remove_file(&from)))
+            .map_err(|source| RecordError::MigrateRecordFile {
+                path: from.clone(),
+                source,
+            })?;
+        infos.push(AppInfo::MigrateRecordFile { from, to });
+    }
+    Ok(infos)
 }
 
 fn read_records(record_type: RecordType) -> Result<Option<Vec<RecordEntry>>, RecordError> {
@@ -115,23 +523,332 @@ fn read_records(record_type: RecordType) -> Result<Option<Vec<RecordEntry>>, Rec
         RecordType::Clipboard => (get_storage_path(RecordType::Clipboard)?, &CLIPBOARD_MUTEX),
         RecordType::History => (get_storage_path(RecordType::History)?, &HISTORY_MUTEX),
     };
-    read_toml_file(&path, mutex).map(|data| data.map(|d| d.entries))
+    get_storage_backend().read(&path, mutex)
 }
 
-fn write_records(entries: &[RecordEntry], record_type: RecordType) -> Result<(), RecordError> {
+// Returns how many of the oldest entries were dropped to stay within `max_entries`.
+fn write_records(
+    entries: &[RecordEntry],
+    record_type: RecordType,
+    max_entries: usize,
+) -> Result<usize, RecordError> {
     let (path, mutex) = match record_type {
         RecordType::Clipboard => (get_storage_path(RecordType::Clipboard)?, &CLIPBOARD_MUTEX),
         RecordType::History => (get_storage_path(RecordType::History)?, &HISTORY_MUTEX),
     };
-    let capped_entries = if entries.len() > MAX_CLIPBOARD_ENTRIES {
-        &entries[..MAX_CLIPBOARD_ENTRIES]
+    let capped_entries = if entries.len() > max_entries {
+        &entries[..max_entries]
     } else {
         entries
     };
-    let record_data = RecordData {
-        entries: capped_entries.to_vec(),
+    get_storage_backend().write(&path, mutex, capped_entries)?;
+    Ok(entries.len() - capped_entries.len())
+}
+
+// Records are stored as one TOML file by default. FILE_CLIPPER_STORAGE=append switches to an
+// append-only JSON lines log, which only writes what changed and suits large clipboards better.
+trait StorageBackend {
+    fn extension(&self) -> &'static str;
+
+    fn read(&self, path: &Path, mutex: &Mutex<()>)
+    -> Result<Option<Vec<RecordEntry>>, RecordError>;
+
+    fn write(
+        &self,
+        path: &Path,
+        mutex: &Mutex<()>,
+        entries: &[RecordEntry],
+    ) -> Result<(), RecordError>;
+}
+
+struct TomlStorage;
+
+struct AppendStorage;
+
+fn get_storage_backend() -> &'static dyn StorageBackend {
+    if append_storage_enabled() {
+        &AppendStorage
+    } else {
+        &TomlStorage
+    }
+}
+
+fn get_other_storage_backend() -> &'static dyn StorageBackend {
+    if append_storage_enabled() {
+        &TomlStorage
+    } else {
+        &AppendStorage
+    }
+}
+
+fn append_storage_enabled() -> bool {
+    env::var(STORAGE_ENV).is_ok_and(|value| value == "append")
+}
+
+impl StorageBackend for TomlStorage {
+    fn extension(&self) -> &'static str {
+        "toml"
+    }
+
+    fn read(
+        &self,
+        path: &Path,
+        mutex: &Mutex<()>,
+    ) -> Result<Option<Vec<RecordEntry>>, RecordError> {
+        read_toml_file(path, mutex).map(|data| data.map(|d| d.entries))
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        mutex: &Mutex<()>,
+        entries: &[RecordEntry],
+    ) -> Result<(), RecordError> {
+        if human_readable_enabled() {
+            let record_data = HumanReadableRecordData {
+                version: RECORD_SCHEMA_VERSION,
+                entries: entries
+                    .iter()
+                    .map(|entry| HumanReadableRecordEntry {
+                        entry,
+                        timestamp_iso: DateTime::<Local>::from(entry.timestamp).to_rfc3339(),
+                        size_human: entry.size.map(format_size),
+                    })
+                    .collect(),
+            };
+            write_toml_file(path, mutex, record_data)
+        } else {
+            let record_data = RecordData {
+                version: RECORD_SCHEMA_VERSION,
+                entries: entries.to_vec(),
+            };
+            write_toml_file(path, mutex, record_data)
+        }
+    }
+}
+
+impl StorageBackend for AppendStorage {
+    fn extension(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn read(
+        &self,
+        path: &Path,
+        mutex: &Mutex<()>,
+    ) -> Result<Option<Vec<RecordEntry>>, RecordError> {
+        let _lock = mutex.lock().unwrap();
+        read_log_file(path).map(|log| log.map(|(entries, _)| entries))
+    }
+
+    // Appends the lines that turn the stored entries into `entries`, and rewrites the log when
+    // that is not possible, e.g. after a reorder, or when it has grown too long.
+    fn write(
+        &self,
+        path: &Path,
+        mutex: &Mutex<()>,
+        entries: &[RecordEntry],
+    ) -> Result<(), RecordError> {
+        let _lock = mutex.lock().unwrap();
+        let changes = read_log_file(path)?.and_then(|(stored, line_count)| {
+            get_log_changes(&stored, entries).filter(|changes| {
+                line_count + changes.len() <= LOG_COMPACT_FACTOR * entries.len() + LOG_COMPACT_SLACK
+            })
+        });
+        match changes {
+            Some(changes) => write_log_lines(path, &changes, true),
+            None => {
+                let lines: Vec<RecordLogLine> = [RecordLogLine::Version(RECORD_SCHEMA_VERSION)]
+                    .into_iter()
+                    .chain(entries.iter().rev().cloned().map(RecordLogLine::Put))
+                    .collect();
+                write_log_lines(path, &lines, false)
+            }
+        }
+    }
+}
+
+// Returns the live entries and how many lines the log holds.
+fn read_log_file(path: &Path) -> Result<Option<(Vec<RecordEntry>, usize)>, RecordError> {
+    let mut file = match File::open(path) {
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(RecordError::OpenRecordFile {
+                path: path.into(),
+                source,
+            });
+        }
+        Ok(file) => file,
+    };
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|source| RecordError::ReadRecordFile {
+            path: path.into(),
+            source,
+        })?;
+
+    let mut entries: VecDeque<RecordEntry> = VecDeque::new();
+    let mut line_count = 0;
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        line_count += 1;
+        let parsed: RecordLogLine =
+            serde_json::from_str(line).map_err(|source| RecordError::DeserializeRecordLog {
+                path: path.into(),
+                line: index + 1,
+                source,
+            })?;
+        match parsed {
+            RecordLogLine::Version(version) if version > RECORD_SCHEMA_VERSION => {
+                return Err(RecordError::UnsupportedSchemaVersion {
+                    path: path.into(),
+                    version,
+                });
+            }
+            RecordLogLine::Version(_) => {}
+            RecordLogLine::Put(entry) => {
+                match entries.iter_mut().find(|stored| stored.id == entry.id) {
+                    Some(stored) => *stored = entry,
+                    None => entries.push_front(entry),
+                }
+            }
+            RecordLogLine::Remove(id) => entries.retain(|stored| stored.id != id),
+        }
+    }
+    Ok(Some((entries.into(), line_count)))
+}
+
+// New entries can only be added in front of the stored ones, and the stored ones have to keep
+// their order, which covers staging, pasting and capping. Anything else needs a rewrite.
+fn get_log_changes(stored: &[RecordEntry], entries: &[RecordEntry]) -> Option<Vec<RecordLogLine>> {
+    let stored_ids: HashSet<Uuid> = stored.iter().map(|entry| entry.id).collect();
+    let entry_ids: HashSet<Uuid> = entries.iter().map(|entry| entry.id).collect();
+    let added_count = entries
+        .iter()
+        .position(|entry| stored_ids.contains(&entry.id))
+        .unwrap_or(entries.len());
+    let (added, kept) = entries.split_at(added_count);
+    let remaining: Vec<&RecordEntry> = stored
+        .iter()
+        .filter(|entry| entry_ids.contains(&entry.id))
+        .collect();
+    if kept.len() != remaining.len()
+        || kept
+            .iter()
+            .zip(&remaining)
+            .any(|(entry, stored)| entry.id != stored.id)
+    {
+        return None;
+    }
+
+    let removed = stored
+        .iter()
+        .filter(|entry| !entry_ids.contains(&entry.id))
+        .map(|entry| RecordLogLine::Remove(entry.id));
+    // Compared as stored, since timestamps only keep whole seconds.
+    let modified = kept
+        .iter()
+        .zip(remaining)
+        .filter(|(entry, stored)| {
+            serde_json::to_value(entry).ok() != serde_json::to_value(stored).ok()
+        })
+        .map(|(entry, _)| RecordLogLine::Put(entry.clone()));
+    let added = added.iter().rev().cloned().map(RecordLogLine::Put);
+    Some(removed.chain(modified).chain(added).collect())
+}
+
+fn write_log_lines(path: &Path, lines: &[RecordLogLine], append: bool) -> Result<(), RecordError> {
+    let mut contents = String::new();
+    for line in lines {
+        contents.push_str(
+            &serde_json::to_string(line).map_err(|source| RecordError::SerializeJson { source })?,
+        );
+        contents.push('\n');
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|source| RecordError::CreateRecordFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    file.write_all(contents.as_bytes())
+        .map_err(|source| RecordError::WriteRecordFile {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+// Times the write and read round trip of each storage backend, TOML and the append log, on
+// scratch files in the temp directory so the real clipboard is left alone.
+pub fn benchmark_records(entries: usize, rounds: u32) -> Result<Vec<RecordBenchmark>, RecordError> {
+    let entries: Vec<RecordEntry> = (0..entries)
+        .map(|index| RecordEntry {
+            id: Uuid::new_v4(),
+            timestamp: SystemTime::now(),
+            size: Some(index as u64),
+            size_is_lower_bound: false,
+            non_canonical: false,
+            exclude_hidden: false,
+            destination: None,
+            source: None,
+            pasted_at: None,
+            note: None,
+            created_destination: false,
+            operation: Operation::Copy,
+            entry_type: EntryType::File,
+            path: PathBuf::from(format!("/tmp/file_clipper/bench/file_{}.txt", index)),
+        })
+        .collect();
+    [&TomlStorage as &dyn StorageBackend, &AppendStorage]
+        .into_iter()
+        .map(|backend| benchmark_backend(backend, &entries, rounds))
+        .collect()
+}
+
+// Each round starts from a missing file, so the append log is written in full like the TOML
+// file instead of appending nothing after the first round.
+fn benchmark_backend(
+    backend: &dyn StorageBackend,
+    entries: &[RecordEntry],
+    rounds: u32,
+) -> Result<RecordBenchmark, RecordError> {
+    let path = env::temp_dir().join(format!(
+        "file_clipper-bench-{}.{}",
+        Uuid::new_v4(),
+        backend.extension()
+    ));
+    let mutex = Mutex::new(());
+    let mut benchmark = RecordBenchmark {
+        backend: backend.extension(),
+        entries: entries.len(),
+        rounds,
+        write: Duration::ZERO,
+        read: Duration::ZERO,
     };
-    write_toml_file(&path, mutex, record_data)
+    let result = (|| {
+        for _ in 0..rounds {
+            let _ = remove_file(&path);
+            let started = Instant::now();
+            backend.write(&path, &mutex, entries)?;
+            benchmark.write += started.elapsed();
+            let started = Instant::now();
+            backend.read(&path, &mutex)?;
+            benchmark.read += started.elapsed();
+        }
+        Ok(())
+    })();
+    let _ = remove_file(&path);
+    result.map(|_| benchmark)
+}
+
+fn human_readable_enabled() -> bool {
+    env::var(HUMAN_READABLE_ENV).is_ok_and(|value| value == "1" || value == "true")
 }
 
 fn read_toml_file<P: AsRef<Path>>(
@@ -139,7 +856,7 @@ fn read_toml_file<P: AsRef<Path>>(
     mutex: &Mutex<()>,
 ) -> Result<Option<RecordData>, RecordError> {
     let path = path.as_ref();
-    let _lock = mutex.lock().unwrap();
+    let lock = mutex.lock().unwrap();
 
     let mut file = match File::open(path) {
         Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
@@ -159,19 +876,44 @@ fn read_toml_file<P: AsRef<Path>>(
             source,
         })?;
 
-    match toml_from_str(&contents) {
-        Err(source) => Err(RecordError::DeserializeRecordFile {
+    let parsed: RecordData =
+        toml_from_str(&contents).map_err(|source| RecordError::DeserializeRecordFile {
             path: path.into(),
             source,
-        }),
-        Ok(parsed) => Ok(Some(parsed)),
+        })?;
+    drop(lock);
+
+    if parsed.version == RECORD_SCHEMA_VERSION {
+        return Ok(Some(parsed));
     }
+    let migrated = migrate_record_data(parsed, path)?;
+    write_toml_file(path, mutex, &migrated)?;
+    Ok(Some(migrated))
 }
 
-fn write_toml_file<P: AsRef<Path>>(
+// Upgrades record data one schema version at a time. New fields are filled by their serde
+// defaults while parsing, so a step only needs code when existing data has to change shape.
+fn migrate_record_data(mut data: RecordData, path: &Path) -> Result<RecordData, RecordError> {
+    if data.version > RECORD_SCHEMA_VERSION {
+        return Err(RecordError::UnsupportedSchemaVersion {
+            path: path.to_path_buf(),
+            version: data.version,
+        });
+    }
+    while data.version < RECORD_SCHEMA_VERSION {
+        data.version = match data.version {
+            // v0 files predate the version key; their entries already parse with defaults.
+            0 => 1,
+            version => version + 1,
+        };
+    }
+    Ok(data)
+}
+
+fn write_toml_file<P: AsRef<Path>, T: Serialize>(
     path: P,
     mutex: &Mutex<()>,
-    data: RecordData,
+    data: T,
 ) -> Result<(), RecordError> {
     let path = path.as_ref();
     let _lock = mutex.lock().unwrap();
@@ -198,12 +940,30 @@ fn write_toml_file<P: AsRef<Path>>(
 mod tests {
     use super::*;
     use crate::{
-        models::Operation,
-        test_helpers::{create_mock_record_entry, setup_test_env},
+        files::handle_transfer,
+        models::{EntryType, Operation, TransferOptions},
+        test_helpers::{
+            create_mock_record_entry, create_test_file, get_test_entry, setup_test_env,
+        },
     };
     use serial_test::serial;
     use std::io::Write;
-    use tempfile::NamedTempFile;
+    use tempfile::{NamedTempFile, tempdir};
+
+    #[test]
+    #[serial]
+    fn test_lock_records_excludes_other_holders() {
+        let env = setup_test_env();
+        let lock = lock_records().unwrap();
+        // Taking it again in the same process shares the held lock instead of waiting on it.
+        let nested = lock_records().unwrap();
+        let other = File::open(env.state_dir.with_file_name(LOCK_FILE)).unwrap();
+        assert!(other.try_lock().is_err());
+        drop(nested);
+        assert!(other.try_lock().is_err());
+        drop(lock);
+        assert!(other.try_lock().is_ok());
+    }
 
     #[test]
     fn test_write_then_read_toml_file() {
@@ -228,6 +988,7 @@ mod tests {
             ),
         ];
         let record_data = RecordData {
+            version: RECORD_SCHEMA_VERSION,
             entries: entries.clone(),
         };
 
@@ -289,6 +1050,51 @@ mod tests {
         assert_eq!(capped_clipboard.len(), MAX_CLIPBOARD_ENTRIES);
     }
 
+    #[test]
+    #[serial]
+    fn test_write_history_reports_capping() {
+        let _env = setup_test_env();
+        let entries: Vec<RecordEntry> = (0..(MAX_HISTORY_ENTRIES + 7))
+            .map(|_| create_mock_record_entry(None, None, None, None, None))
+            .collect();
+
+        let info = write_history(&entries).unwrap();
+        assert!(matches!(
+            info,
+            Some(AppInfo::HistoryCapped { dropped: 7, max }) if max == MAX_HISTORY_ENTRIES
+        ));
+        assert_eq!(read_history().unwrap().unwrap().len(), MAX_HISTORY_ENTRIES);
+
+        assert!(write_history(&entries[..3]).unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_write_clipboard_with_max() {
+        let _env = setup_test_env();
+        let entries: Vec<RecordEntry> = (0..10)
+            .map(|_| create_mock_record_entry(None, None, None, None, None))
+            .collect();
+
+        write_clipboard_with_max(&entries, Some(3)).unwrap();
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 3);
+        assert_eq!(clipboard[0].id, entries[0].id);
+
+        write_clipboard_with_max(&entries, None).unwrap();
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_get_max_entries() {
+        assert_eq!(get_max_entries(None), MAX_CLIPBOARD_ENTRIES);
+        assert_eq!(get_max_entries(Some(5)), 5);
+        assert_eq!(
+            get_max_entries(Some(MAX_CLIPBOARD_ENTRIES + 1)),
+            MAX_CLIPBOARD_ENTRIES
+        );
+    }
+
     #[test]
     #[serial]
     fn test_handle_remove_existing() {
@@ -369,6 +1175,40 @@ mod tests {
         assert!(!dir_path.exists());
     }
 
+    #[test]
+    #[serial]
+    fn test_clear_records_removes_staged_content() {
+        let _env = setup_test_env();
+        let content_dir = get_content_dir().unwrap();
+        create_dir_all(content_dir.join("staged")).unwrap();
+        create_test_file(&content_dir.join("staged").join("out.txt"), "content");
+
+        let result = clear_records().unwrap();
+
+        assert_eq!(result.len(), 4);
+        assert!(matches!(&result[2], AppInfo::Clear { path: p } if p == &content_dir));
+        assert!(!content_dir.exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_clear_records_after_staging() {
+        let env = setup_test_env();
+        let file_path = env.source_dir.join("a.txt");
+        create_test_file(&file_path, "a");
+        handle_transfer(
+            vec![&file_path],
+            Operation::Copy,
+            &TransferOptions::default(),
+        )
+        .unwrap();
+
+        let result = clear_records().unwrap();
+
+        assert!(matches!(result.last(), Some(AppInfo::Clear { path }) if *path == env.state_dir));
+        assert!(!env.state_dir.exists());
+    }
+
     #[test]
     #[serial]
     fn test_clear_records_when_empty() {
@@ -535,6 +1375,401 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_retain_newer_than() {
+        let now = SystemTime::now();
+        let old_entry =
+            create_mock_record_entry(None, None, None, Some(now - Duration::from_secs(120)), None);
+        let new_entry = create_mock_record_entry(None, None, None, Some(now), None);
+
+        let (entries, removed) = retain_newer_than(
+            vec![new_entry.clone(), old_entry],
+            now - Duration::from_secs(60),
+        );
+        assert_eq!(removed, 1);
+        assert_eq!(entries, vec![new_entry]);
+    }
+
+    #[test]
+    fn test_move_entry() {
+        let mut entries = vec![0, 1, 2, 3];
+        move_entry(&mut entries, 2, 0);
+        assert_eq!(entries, vec![2, 0, 1, 3]);
+        move_entry(&mut entries, 0, 3);
+        assert_eq!(entries, vec![0, 1, 3, 2]);
+        move_entry(&mut entries, 1, 1);
+        assert_eq!(entries, vec![0, 1, 3, 2]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_handle_reorder() {
+        let _env = setup_test_env();
+        let first = create_mock_record_entry(None, None, None, None, None);
+        let second = create_mock_record_entry(None, None, None, None, None);
+        let third = create_mock_record_entry(None, None, None, None, None);
+        write_clipboard(&[first.clone(), second.clone(), third.clone()]).unwrap();
+
+        let clipboard_ids = || -> Vec<Uuid> {
+            read_clipboard()
+                .unwrap()
+                .unwrap()
+                .iter()
+                .map(|entry| entry.id)
+                .collect()
+        };
+
+        let (infos, warnings) = handle_reorder(third.id, ReorderPosition::Front).unwrap();
+        assert!(warnings.is_empty());
+        assert!(matches!(
+            &infos[..],
+            [AppInfo::Reorder { path, position: ReorderPosition::Front }] if *path == third.path
+        ));
+        assert_eq!(clipboard_ids(), vec![third.id, first.id, second.id]);
+
+        handle_reorder(third.id, ReorderPosition::Back).unwrap();
+        assert_eq!(clipboard_ids(), vec![first.id, second.id, third.id]);
+
+        let (infos, warnings) = handle_reorder(Uuid::new_v4(), ReorderPosition::Front).unwrap();
+        assert!(infos.is_empty());
+        assert!(matches!(
+            warnings[..],
+            [AppWarning::Record(RecordWarning::EntryNotFound)]
+        ));
+    }
+
+    #[test]
+    fn test_dedup_entries_keeps_most_recent() {
+        let now = SystemTime::now();
+        let path = PathBuf::from("/tmp/duplicate.txt");
+        let older = create_mock_record_entry(
+            Some(path.clone()),
+            None,
+            None,
+            Some(now - Duration::from_secs(60)),
+            None,
+        );
+        let other = create_mock_record_entry(None, None, None, Some(now), None);
+        let newer = create_mock_record_entry(Some(path.clone()), None, None, Some(now), None);
+
+        let (entries, merged) =
+            dedup_entries(vec![older, other.clone(), newer.clone(), newer.clone()]);
+        assert_eq!(merged, 2);
+        assert_eq!(entries, vec![newer, other]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_dedup_clipboard() {
+        let _env = setup_test_env();
+        let entry = create_mock_record_entry(None, None, None, None, None);
+        let duplicate = RecordEntry {
+            id: Uuid::new_v4(),
+            ..entry.clone()
+        };
+        write_clipboard(&[entry, duplicate.clone()]).unwrap();
+
+        let infos = dedup_clipboard().unwrap();
+        assert!(matches!(infos[..], [AppInfo::Dedup { count: 1 }]));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].id, duplicate.id);
+    }
+
+    fn split_csv_record(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(String::new()),
+                c => fields.last_mut().unwrap().push(c),
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn test_write_history_csv_round_trip() {
+        let entries = vec![
+            create_mock_record_entry(
+                Some(PathBuf::from("/tmp/plain.txt")),
+                Some(Operation::Cut),
+                None,
+                None,
+                Some(42),
+            ),
+            RecordEntry {
+                size: None,
+                ..create_mock_record_entry(
+                    Some(PathBuf::from("/tmp/a, \"quoted\" name")),
+                    Some(Operation::Copy),
+                    Some(EntryType::Directory),
+                    None,
+                    None,
+                )
+            },
+        ];
+        let mut buffer = Vec::new();
+        write_history_csv(&entries, &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "id,operation,entry_type,size,timestamp_iso,path");
+        assert!(lines[2].ends_with(",\"/tmp/a, \"\"quoted\"\" name\""));
+
+        for (line, entry) in lines[1..].iter().zip(&entries) {
+            let fields = split_csv_record(line);
+            assert_eq!(fields.len(), 6);
+            assert_eq!(fields[0], entry.id.to_string());
+            assert_eq!(fields[1], entry.operation.to_string());
+            assert_eq!(fields[2], entry.entry_type.to_string());
+            assert_eq!(
+                fields[3],
+                entry.size.map(|size| size.to_string()).unwrap_or_default()
+            );
+            let timestamp: SystemTime = DateTime::parse_from_rfc3339(&fields[4]).unwrap().into();
+            let expected = entry
+                .timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let actual = timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            assert_eq!(actual, expected);
+            assert_eq!(PathBuf::from(&fields[5]), entry.path);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_export_history_csv() {
+        let _env = setup_test_env();
+        let temp_dir = tempdir().unwrap();
+        let csv_path = temp_dir.path().join("ops.csv");
+        write_history(&[
+            create_mock_record_entry(None, None, None, None, None),
+            create_mock_record_entry(None, None, None, None, None),
+        ])
+        .unwrap();
+
+        let infos = export_history_csv(&csv_path).unwrap();
+        assert!(matches!(infos[..], [AppInfo::ExportCsv { count: 2, .. }]));
+        let output = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(output.lines().count(), 3);
+    }
+
+    #[test]
+    #[serial]
+    fn test_clear_history_older_than() {
+        let _env = setup_test_env();
+        let now = SystemTime::now();
+        let old_entry = create_mock_record_entry(
+            None,
+            None,
+            None,
+            Some(now - Duration::from_secs(60 * 60 * 48)),
+            None,
+        );
+        let new_entry = create_mock_record_entry(None, None, None, Some(now), None);
+        write_history(&[new_entry.clone(), old_entry]).unwrap();
+
+        let infos = clear_history_older_than(Duration::from_secs(60 * 60 * 24)).unwrap();
+        assert!(matches!(infos[..], [AppInfo::ClearHistory { count: 1 }]));
+
+        let history = read_history().unwrap().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, new_entry.id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_read_toml_file_migrates_v0() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("v0.toml");
+        let mutex = Mutex::new(());
+        let id = Uuid::new_v4();
+        std::fs::write(
+            &path,
+            format!(
+                r#"
+                [[entries]]
+                timestamp = 0
+                size = 100
+                operation = "Copy"
+                entry_type = "File"
+                path = "/tmp/file.txt"
+                id = "{}"
+                "#,
+                id
+            ),
+        )
+        .unwrap();
+
+        let data = read_toml_file(&path, &mutex).unwrap().unwrap();
+        assert_eq!(data.version, RECORD_SCHEMA_VERSION);
+        assert_eq!(data.entries[0].id, id);
+        assert!(!data.entries[0].size_is_lower_bound);
+        assert_eq!(data.entries[0].destination, None);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&format!("version = {}", RECORD_SCHEMA_VERSION)));
+        let reread = read_toml_file(&path, &mutex).unwrap().unwrap();
+        assert_eq!(reread.entries, data.entries);
+    }
+
+    #[test]
+    fn test_read_toml_file_rejects_newer_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("future.toml");
+        let mutex = Mutex::new(());
+        std::fs::write(
+            &path,
+            format!("version = {}\nentries = []\n", RECORD_SCHEMA_VERSION + 1),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            read_toml_file(&path, &mutex),
+            Err(RecordError::UnsupportedSchemaVersion { .. })
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_prune_records_removes_dangling_entries() {
+        let env = setup_test_env();
+        let valid_path = env.source_dir.join("valid.txt");
+        create_test_file(&valid_path, "valid");
+        let valid_entry = get_test_entry(&valid_path, Operation::Copy);
+        let dangling_entry = create_mock_record_entry(
+            Some(env.source_dir.join("dangling.txt")),
+            Some(Operation::Copy),
+            None,
+            None,
+            None,
+        );
+        write_clipboard(&[dangling_entry.clone(), valid_entry.clone()]).unwrap();
+        write_history(std::slice::from_ref(&dangling_entry)).unwrap();
+
+        let infos = prune_records(false).unwrap();
+        assert_eq!(infos.len(), 1);
+        assert!(matches!(
+            infos[0],
+            AppInfo::Prune {
+                count: 1,
+                record_type: RecordType::Clipboard
+            }
+        ));
+
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].id, valid_entry.id);
+
+        let history = read_history().unwrap().unwrap();
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_prune_records_with_history() {
+        let env = setup_test_env();
+        let valid_path = env.source_dir.join("valid.txt");
+        create_test_file(&valid_path, "valid");
+        let valid_entry = get_test_entry(&valid_path, Operation::Copy);
+        let dangling_entry = create_mock_record_entry(
+            Some(env.source_dir.join("dangling.txt")),
+            Some(Operation::Cut),
+            None,
+            None,
+            None,
+        );
+        write_history(&[valid_entry.clone(), dangling_entry]).unwrap();
+
+        let infos = prune_records(true).unwrap();
+        assert_eq!(infos.len(), 2);
+        assert!(matches!(
+            infos[0],
+            AppInfo::Prune {
+                count: 0,
+                record_type: RecordType::Clipboard
+            }
+        ));
+        assert!(matches!(
+            infos[1],
+            AppInfo::Prune {
+                count: 1,
+                record_type: RecordType::History
+            }
+        ));
+
+        let history = read_history().unwrap().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, valid_entry.id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_records_modified() {
+        let _env = setup_test_env();
+        assert!(
+            get_records_modified(&RecordType::Clipboard)
+                .unwrap()
+                .is_none()
+        );
+
+        write_clipboard(&[create_mock_record_entry(None, None, None, None, None)]).unwrap();
+        assert!(
+            get_records_modified(&RecordType::Clipboard)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_write_records_human_readable() {
+        let _env = setup_test_env();
+        let entry = create_mock_record_entry(None, None, None, None, Some(2048));
+
+        unsafe {
+            env::set_var(HUMAN_READABLE_ENV, "1");
+        }
+        write_clipboard(std::slice::from_ref(&entry)).unwrap();
+        unsafe {
+            env::remove_var(HUMAN_READABLE_ENV);
+        }
+
+        let path = get_storage_path(RecordType::Clipboard).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(contents.contains("timestamp_iso"));
+        assert!(contents.contains("size_human = \"2.0 KiB\""));
+
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 1);
+        assert_eq!(clipboard[0].id, entry.id);
+        assert_eq!(clipboard[0].size, Some(2048));
+    }
+
+    #[test]
+    #[serial]
+    fn test_write_records_without_human_readable() {
+        let _env = setup_test_env();
+        write_clipboard(&[create_mock_record_entry(None, None, None, None, None)]).unwrap();
+
+        let path = get_storage_path(RecordType::Clipboard).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(!contents.contains("timestamp_iso"));
+        assert!(!contents.contains("size_human"));
+    }
+
     #[test]
     fn test_get_storage_path_clipboard() {
         let result = get_storage_path(RecordType::Clipboard);
@@ -543,6 +1778,46 @@ mod tests {
         assert!(path.to_string_lossy().contains("clipboard.toml"));
     }
 
+    #[test]
+    fn test_migrate_legacy_records() {
+        let legacy_dir = tempdir().unwrap();
+        let dir = tempdir().unwrap();
+        create_test_file(&legacy_dir.path().join("clipboard.toml"), "entries = []");
+        create_test_file(&legacy_dir.path().join("history.toml"), "entries = []");
+
+        let infos = migrate_legacy_records(legacy_dir.path(), dir.path()).unwrap();
+        assert_eq!(infos.len(), 2);
+        assert!(dir.path().join("clipboard.toml").is_file());
+        assert!(dir.path().join("history.toml").is_file());
+        assert!(!legacy_dir.path().join("clipboard.toml").exists());
+        assert!(!legacy_dir.path().join("history.toml").exists());
+    }
+
+    #[test]
+    fn test_migrate_legacy_records_keeps_existing_records() {
+        let legacy_dir = tempdir().unwrap();
+        let dir = tempdir().unwrap();
+        create_test_file(&legacy_dir.path().join("clipboard.toml"), "legacy");
+        create_test_file(&dir.path().join("history.toml"), "current");
+
+        let infos = migrate_legacy_records(legacy_dir.path(), dir.path()).unwrap();
+        assert!(infos.is_empty());
+        assert!(legacy_dir.path().join("clipboard.toml").exists());
+        assert!(!dir.path().join("clipboard.toml").exists());
+    }
+
+    #[test]
+    fn test_migrate_legacy_records_keeps_append_records() {
+        let legacy_dir = tempdir().unwrap();
+        let dir = tempdir().unwrap();
+        create_test_file(&legacy_dir.path().join("clipboard.toml"), "legacy");
+        create_test_file(&dir.path().join("clipboard.ndjson"), "current");
+
+        let infos = migrate_legacy_records(legacy_dir.path(), dir.path()).unwrap();
+        assert!(infos.is_empty());
+        assert!(legacy_dir.path().join("clipboard.toml").exists());
+    }
+
     #[test]
     fn test_get_storage_path_history() {
         let result = get_storage_path(RecordType::History);
@@ -550,4 +1825,183 @@ mod tests {
         let path = result.unwrap();
         assert!(path.to_string_lossy().contains("history.toml"));
     }
+
+    #[test]
+    fn test_benchmark_records() {
+        let benchmarks = benchmark_records(MAX_CLIPBOARD_ENTRIES, 3).unwrap();
+
+        assert_eq!(
+            benchmarks
+                .iter()
+                .map(|benchmark| benchmark.backend)
+                .collect::<Vec<_>>(),
+            ["toml", "ndjson"]
+        );
+        for benchmark in benchmarks {
+            assert_eq!(benchmark.entries, MAX_CLIPBOARD_ENTRIES);
+            assert_eq!(benchmark.rounds, 3);
+            assert!(benchmark.write > Duration::ZERO);
+            assert!(benchmark.read > Duration::ZERO);
+            // A full clipboard should round-trip well within a second even in a debug build.
+            assert!((benchmark.write + benchmark.read) / benchmark.rounds < Duration::from_secs(1));
+        }
+    }
+
+    // Record files keep whole seconds, so these entries read back unchanged.
+    fn log_entry() -> RecordEntry {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        create_mock_record_entry(None, None, None, Some(timestamp), None)
+    }
+
+    fn count_lines(path: &Path) -> usize {
+        std::fs::read_to_string(path).unwrap().lines().count()
+    }
+
+    #[test]
+    fn test_import_other_backend() {
+        let dir = tempdir().unwrap();
+        let mutex = Mutex::new(());
+        let entries = vec![log_entry(), log_entry()];
+        TomlStorage
+            .write(&dir.path().join("clipboard.toml"), &mutex, &entries)
+            .unwrap();
+
+        let infos = import_other_backend(dir.path(), &AppendStorage, &TomlStorage).unwrap();
+        assert!(matches!(
+            &infos[..],
+            [AppInfo::ImportRecordFile { from, to }]
+                if from.ends_with("clipboard.toml") && to.ends_with("clipboard.ndjson")
+        ));
+        assert!(!dir.path().join("clipboard.toml").exists());
+        let imported = AppendStorage
+            .read(&dir.path().join("clipboard.ndjson"), &mutex)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            imported.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+            entries.iter().map(|entry| entry.id).collect::<Vec<_>>()
+        );
+
+        // Records already in the selected format are never replaced.
+        TomlStorage
+            .write(&dir.path().join("clipboard.toml"), &mutex, &[])
+            .unwrap();
+        let infos = import_other_backend(dir.path(), &AppendStorage, &TomlStorage).unwrap();
+        assert!(infos.is_empty());
+        assert!(dir.path().join("clipboard.toml").exists());
+    }
+
+    #[test]
+    fn test_toml_storage_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clipboard.toml");
+        let mutex = Mutex::new(());
+        let entries: Vec<RecordEntry> = (0..3).map(|_| log_entry()).collect();
+
+        assert!(TomlStorage.read(&path, &mutex).unwrap().is_none());
+        TomlStorage.write(&path, &mutex, &entries).unwrap();
+        let read_back = TomlStorage.read(&path, &mutex).unwrap().unwrap();
+
+        assert_eq!(read_back.len(), 3);
+        assert!(
+            read_back
+                .iter()
+                .zip(&entries)
+                .all(|(read, written)| read.id == written.id && read.path == written.path)
+        );
+    }
+
+    #[test]
+    fn test_append_storage_appends_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clipboard.ndjson");
+        let mutex = Mutex::new(());
+        let mut entries: Vec<RecordEntry> = (0..3).map(|_| log_entry()).collect();
+        let read = |path: &Path| AppendStorage.read(path, &mutex).unwrap().unwrap();
+
+        AppendStorage.write(&path, &mutex, &entries).unwrap();
+        assert_eq!(count_lines(&path), 4);
+        assert_eq!(read(&path), entries);
+
+        // Staging adds one line, a removal adds a tombstone and an edit replaces in place.
+        entries.insert(0, log_entry());
+        AppendStorage.write(&path, &mutex, &entries).unwrap();
+        assert_eq!(count_lines(&path), 5);
+        entries.remove(2);
+        AppendStorage.write(&path, &mutex, &entries).unwrap();
+        assert_eq!(count_lines(&path), 6);
+        entries[1].destination = Some(PathBuf::from("/tmp/dest"));
+        AppendStorage.write(&path, &mutex, &entries).unwrap();
+        assert_eq!(count_lines(&path), 7);
+        assert_eq!(read(&path), entries);
+
+        // A reorder cannot be expressed as appended lines, so the log is rewritten.
+        entries.swap(0, 2);
+        AppendStorage.write(&path, &mutex, &entries).unwrap();
+        assert_eq!(count_lines(&path), entries.len() + 1);
+        assert_eq!(read(&path), entries);
+    }
+
+    #[test]
+    fn test_append_storage_compacts() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clipboard.ndjson");
+        let mutex = Mutex::new(());
+        let kept = log_entry();
+
+        for _ in 0..100 {
+            let staged = log_entry();
+            AppendStorage
+                .write(&path, &mutex, &[staged, kept.clone()])
+                .unwrap();
+            AppendStorage
+                .write(&path, &mutex, std::slice::from_ref(&kept))
+                .unwrap();
+        }
+
+        assert!(count_lines(&path) <= LOG_COMPACT_FACTOR + LOG_COMPACT_SLACK + 1);
+        assert_eq!(
+            AppendStorage.read(&path, &mutex).unwrap().unwrap(),
+            vec![kept]
+        );
+    }
+
+    #[test]
+    fn test_append_storage_rejects_newer_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("clipboard.ndjson");
+        std::fs::write(
+            &path,
+            format!("{{\"version\":{}}}\n", RECORD_SCHEMA_VERSION + 1),
+        )
+        .unwrap();
+
+        let result = AppendStorage.read(&path, &Mutex::new(()));
+
+        assert!(matches!(
+            result,
+            Err(RecordError::UnsupportedSchemaVersion { .. })
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn test_storage_env_selects_append_backend() {
+        let _env = setup_test_env();
+        let entry = log_entry();
+        unsafe {
+            env::set_var(STORAGE_ENV, "append");
+        }
+        write_clipboard(std::slice::from_ref(&entry)).unwrap();
+        let path = get_storage_path(RecordType::Clipboard).unwrap();
+        let clipboard = read_clipboard().unwrap();
+        unsafe {
+            env::remove_var(STORAGE_ENV);
+        }
+
+        assert!(path.ends_with("clipboard.ndjson"));
+        assert!(path.is_file());
+        assert_eq!(clipboard, Some(vec![entry]));
+        assert!(read_clipboard().unwrap().is_none());
+    }
 }
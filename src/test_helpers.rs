@@ -1,5 +1,6 @@
 use ratatui::widgets::{ScrollbarState, TableState};
 use std::{
+    collections::HashMap,
     env::set_var,
     fs::{File, create_dir_all},
     io::Write,
@@ -54,11 +55,19 @@ pub fn create_test_file(path: &Path, content: &str) {
 }
 
 pub fn get_test_entry(path: &Path, operation: Operation) -> RecordEntry {
-    let meta = get_metadata(path).expect("Failed to get metadata for test entry");
+    let meta = get_metadata(path, false).expect("Failed to get metadata for test entry");
     RecordEntry {
         id: Uuid::new_v4(),
         timestamp: meta.modified,
         size: meta.size,
+        size_is_lower_bound: false,
+        non_canonical: false,
+        exclude_hidden: false,
+        source: None,
+        pasted_at: None,
+        note: None,
+        created_destination: false,
+        destination: None,
         operation,
         entry_type: meta.entry_type,
         path: meta.absolute_path,
@@ -83,6 +92,14 @@ pub fn create_mock_record_entry(
         id,
         timestamp,
         size,
+        size_is_lower_bound: false,
+        non_canonical: false,
+        exclude_hidden: false,
+        source: None,
+        pasted_at: None,
+        note: None,
+        created_destination: false,
+        destination: None,
         operation,
         entry_type,
         path,
@@ -94,7 +111,7 @@ pub fn create_file_and_get_metadata(dir: &TempDir, file_name: &str, content: &st
     let mut file = File::create(&file_path).expect("Failed to create test file");
     write!(file, "{}", content).expect("Failed to write to test file");
     file.sync_all().expect("Failed to sync file");
-    get_metadata(&file_path).expect("Failed to get metadata for test file")
+    get_metadata(&file_path, false).expect("Failed to get metadata for test file")
 }
 
 pub fn create_test_tui(entries_count: usize) -> Tui {
@@ -116,11 +133,27 @@ pub fn create_test_tui(entries_count: usize) -> Tui {
         table_state: TableState::default(),
         scroll_state: ScrollbarState::new(entries.len().saturating_sub(1)),
         invalid: vec![false; entries.len()],
+        last_checked: vec![None; entries.len()],
         marked: vec![false; entries.len()],
         should_exit: false,
+        follow: false,
+        operation: None,
+        records_modified: None,
         warnings: Vec::new(),
         infos: Vec::new(),
         paste_content: None,
+        destination_prompt: None,
+        destination: None,
+        abbreviate_home: false,
+        paste_progress: None,
+        rename_prompt: None,
+        renames: HashMap::new(),
+        group_by: None,
+        non_interactive: false,
+        watch_validity: false,
+        auto_removed: None,
+        confirm_remove: false,
+        pending_remove: None,
     };
     if !entries.is_empty() {
         tui.table_state.select(Some(0));
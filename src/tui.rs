@@ -1,7 +1,6 @@
-use chrono::{DateTime, Local};
 use crossterm::event::{self, Event, KeyCode, KeyEvent};
 use ratatui::{
-    Frame, TerminalOptions, Viewport,
+    DefaultTerminal, Frame, TerminalOptions, Viewport,
     layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{
         Modifier, Style,
@@ -9,26 +8,100 @@ use ratatui::{
     },
     text::Line,
     widgets::{
-        Block, Borders, Cell, HighlightSpacing, Row, Scrollbar, ScrollbarOrientation,
+        Block, Borders, Cell, HighlightSpacing, Paragraph, Row, Scrollbar, ScrollbarOrientation,
         ScrollbarState, Table, TableState,
     },
 };
-use std::{env::current_dir, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env::{self, current_dir},
+    ffi::OsStr,
+    fs::{create_dir_all, symlink_metadata},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use uuid::Uuid;
 
 use crate::{
     errors::{AppError, AppInfo, AppWarning, FileError, TuiError},
-    files::{get_metadata, handle_paste},
-    models::{PasteContent, RecordEntry, RecordType},
-    records::{handle_remove, read_entries},
+    files::{get_metadata, get_paste_target, handle_paste},
+    models::{
+        GroupBy, ListOptions, Operation, PasteContent, PasteOptions, RecordEntry, RecordType,
+        format_entry_size, format_timestamp,
+    },
+    records::{
+        get_records_modified, handle_remove, lock_records, move_entry, read_clipboard,
+        read_entries, write_clipboard,
+    },
 };
 
 const HEIGHT: u16 = 20;
 const OPERATION_WIDTH: u16 = 10;
 const SELECTED_WIDTH: u16 = 8;
 const TIMESTAMP_WIDTH: u16 = 30;
+const SIZE_WIDTH: u16 = 14;
+const DUPLICATE_WIDTH: u16 = 5;
+const NOTE_WIDTH: u16 = 24;
 const POLL_INTERVAL: u64 = 100;
-const CLIPBOARD_HELPER_TEXT: &str = "Navigation: j/k; Select: space; Paste: p; Remove: x; Quit: q";
-const HISTORY_HELPER_TEXT: &str = "Navigation: j/k; Select: space; Paste: p; Quit: q";
+const VALIDITY_TTL: u64 = 5000;
+const NOTE_DURATION: u64 = 3000;
+const CLIPBOARD_HELPER_TEXT: &str = "Navigation: j/k; Invalid: [/]; Move: J/K; Select: space; Paste: p; Paste to: P; Rename: r; Remove: x; Refresh: R; Short paths: ~; Quit: q";
+const HISTORY_HELPER_TEXT: &str = "Navigation: j/k; Invalid: [/]; Select: space; Paste: p; Paste to: P; Rename: r; Refresh: R; Short paths: ~; Quit: q";
+const DESTINATION_HELPER_TEXT: &str = "Confirm: Enter; Cancel: Esc";
+const PASTE_HELPER_TEXT: &str = "Cancel: Esc";
+const REMOVE_HELPER_TEXT: &str = "Confirm: y; Cancel: n";
+const CONFIRM_REMOVE_ENV: &str = "FILE_CLIPPER_CONFIRM_REMOVE";
+
+// Entries are pasted one at a time so the loop can redraw and check for a cancel key in between.
+// An entry whose target already exists is deferred until the terminal is restored, because the
+// collision prompt reads from stdin.
+pub struct PasteProgress {
+    pub pending: VecDeque<RecordEntry>,
+    pub deferred: Vec<RecordEntry>,
+    pub source: RecordType,
+    pub destination: PathBuf,
+    pub completed: usize,
+    pub total: usize,
+    pub cancelled: bool,
+}
+
+impl PasteProgress {
+    pub fn new(paste_content: PasteContent, destination: PathBuf) -> Self {
+        Self {
+            total: paste_content.entries.len(),
+            pending: VecDeque::from(paste_content.entries),
+            deferred: Vec::new(),
+            source: paste_content.source,
+            destination,
+            completed: 0,
+            cancelled: false,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.cancelled || self.pending.is_empty()
+    }
+
+    // Entries that were not pasted yet are left untouched in the clipboard.
+    fn cancel(&mut self) {
+        self.cancelled = true;
+        self.pending.clear();
+        self.deferred.clear();
+    }
+}
+
+pub struct DestinationPrompt {
+    pub input: String,
+    pub message: Option<String>,
+    pub confirm_create: bool,
+}
+
+// Edits the file name an entry is pasted as, identified by id so reordering keeps the override.
+pub struct RenamePrompt {
+    pub id: Uuid,
+    pub input: String,
+    pub message: Option<String>,
+}
 
 pub struct Tui {
     pub entries: Vec<RecordEntry>,
@@ -36,11 +109,130 @@ pub struct Tui {
     pub table_state: TableState,
     pub scroll_state: ScrollbarState,
     pub invalid: Vec<bool>,
+    pub last_checked: Vec<Option<Instant>>,
     pub marked: Vec<bool>,
     pub should_exit: bool,
+    pub follow: bool,
+    pub operation: Option<Operation>,
+    pub records_modified: Option<SystemTime>,
     pub warnings: Vec<AppWarning>,
     pub infos: Vec<AppInfo>,
     pub paste_content: Option<PasteContent>,
+    pub destination_prompt: Option<DestinationPrompt>,
+    pub destination: Option<PathBuf>,
+    pub abbreviate_home: bool,
+    pub paste_progress: Option<PasteProgress>,
+    pub rename_prompt: Option<RenamePrompt>,
+    pub renames: HashMap<Uuid, String>,
+    pub group_by: Option<GroupBy>,
+    pub non_interactive: bool,
+    pub watch_validity: bool,
+    pub auto_removed: Option<(usize, Instant)>,
+    pub confirm_remove: bool,
+    pub pending_remove: Option<Uuid>,
+}
+
+// Header rows only exist on screen; selection and marking keep indexing `entries`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplayRow {
+    Header(Operation),
+    Entry(usize),
+}
+
+fn expand_tilde(input: &str) -> PathBuf {
+    match (input.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(input),
+    }
+}
+
+fn abbreviate_home(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.to_string_lossy()),
+        None => path.to_string_lossy().into_owned(),
+    }
+}
+
+fn validate_rename(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        Err("Name cannot be empty")
+    } else if name.contains('/') || name == "." || name == ".." {
+        Err("Name cannot be a path")
+    } else {
+        Ok(())
+    }
+}
+
+// Flags every occurrence of a path after its first, so one copy of each stays unmarked.
+fn get_duplicate_flags(entries: &[RecordEntry]) -> Vec<bool> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .map(|entry| !seen.insert(&entry.path))
+        .collect()
+}
+
+pub fn filter_entries(
+    entries: Vec<RecordEntry>,
+    operation: &Option<Operation>,
+) -> Vec<RecordEntry> {
+    match operation {
+        Some(operation) => entries
+            .into_iter()
+            .filter(|entry| entry.operation == *operation)
+            .collect(),
+        None => entries,
+    }
+}
+
+// The sort is stable, so entries keep their clipboard order within a group.
+pub fn group_entries(mut entries: Vec<RecordEntry>, group_by: Option<GroupBy>) -> Vec<RecordEntry> {
+    if group_by == Some(GroupBy::Operation) {
+        entries.sort_by_key(|entry| entry.operation.clone());
+    }
+    entries
+}
+
+// Expects entries ordered by `group_entries`, so each group gets a single header.
+pub fn get_display_rows(entries: &[RecordEntry], group_by: Option<GroupBy>) -> Vec<DisplayRow> {
+    let mut rows = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let starts_group = index == 0 || entries[index - 1].operation != entry.operation;
+        if group_by == Some(GroupBy::Operation) && starts_group {
+            rows.push(DisplayRow::Header(entry.operation.clone()));
+        }
+        rows.push(DisplayRow::Entry(index));
+    }
+    rows
+}
+
+// The scan starts just past the selection and wraps around, so the selected entry itself is only
+// found again when it is the sole invalid one.
+fn find_invalid(invalid: &[bool], selected: Option<usize>, forward: bool) -> Option<usize> {
+    let len = invalid.len();
+    let start = selected.unwrap_or(if forward { len.saturating_sub(1) } else { 0 });
+    (1..=len)
+        .map(|step| {
+            if forward {
+                (start + step) % len
+            } else {
+                (start + len - step % len) % len
+            }
+        })
+        .find(|index| invalid[*index])
+}
+
+// Removing an entry asks first unless this is set to 0 or false.
+fn confirm_remove_enabled() -> bool {
+    !env::var(CONFIRM_REMOVE_ENV).is_ok_and(|value| value == "0" || value == "false")
+}
+
+fn get_display_index(rows: &[DisplayRow], entry_index: usize) -> Option<usize> {
+    rows.iter()
+        .position(|row| *row == DisplayRow::Entry(entry_index))
 }
 
 type ColumnDef<'a> = (
@@ -50,23 +242,51 @@ type ColumnDef<'a> = (
 );
 
 impl Tui {
-    pub fn new(mode: RecordType) -> Result<Self, AppError> {
-        let entries = read_entries(&mode)?;
-        if entries.is_empty() {
+    pub fn new(mode: RecordType, options: &ListOptions) -> Result<Self, AppError> {
+        let entries = group_entries(
+            filter_entries(read_entries(&mode)?, &options.operation),
+            options.group_by,
+        );
+        if entries.is_empty() && !options.follow {
             println!("[Info]: {} is empty", mode);
         }
-        Ok(Self {
+        let records_modified = if options.follow {
+            get_records_modified(&mode)?
+        } else {
+            None
+        };
+        let mut tui = Self {
             table_state: TableState::default().with_selected(0),
             scroll_state: ScrollbarState::new(entries.len().saturating_sub(1)),
             invalid: vec![false; entries.len()],
+            last_checked: vec![None; entries.len()],
             marked: vec![false; entries.len()],
-            should_exit: entries.is_empty(),
+            should_exit: entries.is_empty() && !options.follow,
+            follow: options.follow,
+            operation: options.operation.clone(),
+            records_modified,
             entries,
             mode,
             warnings: Vec::new(),
             infos: Vec::new(),
             paste_content: None,
-        })
+            destination_prompt: None,
+            destination: None,
+            abbreviate_home: false,
+            paste_progress: None,
+            rename_prompt: None,
+            renames: HashMap::new(),
+            group_by: options.group_by,
+            non_interactive: options.non_interactive,
+            watch_validity: options.watch_validity,
+            auto_removed: None,
+            confirm_remove: !options.non_interactive && confirm_remove_enabled(),
+            pending_remove: None,
+        };
+        if options.select_all {
+            tui.select_all_valid();
+        }
+        Ok(tui)
     }
 
     pub fn run(mut self) -> Result<(Vec<AppInfo>, Vec<AppWarning>), AppError> {
@@ -77,9 +297,28 @@ impl Tui {
         let loop_result = (|| {
             loop {
                 if self.should_exit {
+                    if self.paste_content.is_some() {
+                        self.run_paste(&mut terminal)?;
+                    }
                     break;
                 }
 
+                if self.follow {
+                    let records_modified = get_records_modified(&self.mode)?;
+                    if records_modified != self.records_modified {
+                        self.records_modified = records_modified;
+                        let entries = group_entries(
+                            filter_entries(read_entries(&self.mode)?, &self.operation),
+                            self.group_by,
+                        );
+                        self.reconcile_entries(entries);
+                    }
+                }
+
+                if self.watch_validity {
+                    self.remove_invalid_entries()?;
+                }
+
                 terminal
                     .draw(|frame| {
                         self.render_ui(frame, frame.area());
@@ -104,9 +343,17 @@ impl Tui {
         let _ = terminal.clear();
         ratatui::restore();
 
-        if let Some(paste_content) = self.paste_content {
-            let destination_path = current_dir().map_err(|source| FileError::Cwd { source })?;
-            match handle_paste(destination_path, Some(paste_content)) {
+        if let Some(progress) = self.paste_progress.take()
+            && !progress.deferred.is_empty()
+        {
+            match handle_paste(
+                progress.destination,
+                Some(PasteContent {
+                    entries: progress.deferred,
+                    source: progress.source,
+                }),
+                &self.get_paste_options(),
+            ) {
                 Err(error) => return Err(error),
                 Ok((infos, warnings)) => {
                     self.infos.extend(infos);
@@ -122,12 +369,131 @@ impl Tui {
     }
 
     fn render_ui(&mut self, frame: &mut Frame, area: Rect) {
-        self.render_table(frame, area);
-        self.render_scrollbar(frame, area);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        self.render_table(frame, chunks[0]);
+        self.render_scrollbar(frame, chunks[0]);
+        self.render_status_line(frame, chunks[1]);
+    }
+
+    fn get_status_line(&self) -> String {
+        let count = |flags: &[bool]| flags.iter().filter(|flag| **flag).count();
+        format!(
+            "Records: {}; Entries: {}; Marked: {}; Invalid: {}",
+            self.mode,
+            self.entries.len(),
+            count(&self.marked),
+            count(&self.invalid)
+        )
+    }
+
+    fn render_status_line(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Paragraph::new(self.get_status_line()).style(Style::default().fg(NEUTRAL.c400)),
+            area,
+        );
+    }
+
+    fn run_paste(&mut self, terminal: &mut DefaultTerminal) -> Result<(), AppError> {
+        let Some(paste_content) = self.paste_content.take() else {
+            return Ok(());
+        };
+        let destination = match self.destination.take() {
+            Some(destination) => destination,
+            None => current_dir().map_err(|source| FileError::Cwd { source })?,
+        };
+        self.paste_progress = Some(PasteProgress::new(paste_content, destination));
+
+        while self
+            .paste_progress
+            .as_ref()
+            .is_some_and(|progress| !progress.is_done())
+        {
+            terminal
+                .draw(|frame| {
+                    self.render_ui(frame, frame.area());
+                })
+                .map_err(|source| TuiError::TerminalDraw { source })?;
+            while event::poll(Duration::ZERO).map_err(|source| TuiError::EventPolling { source })? {
+                if let Event::Key(key) =
+                    event::read().map_err(|source| TuiError::EventRead { source })?
+                {
+                    self.handle_paste_keypress(key);
+                }
+            }
+            self.paste_step()?;
+        }
+        Ok(())
+    }
+
+    fn handle_paste_keypress(&mut self, key: KeyEvent) {
+        let cancel = matches!(
+            key,
+            KeyEvent {
+                code: KeyCode::Esc | KeyCode::Char('q'),
+                ..
+            } | KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: event::KeyModifiers::CONTROL,
+                ..
+            }
+        );
+        if cancel && let Some(progress) = self.paste_progress.as_mut() {
+            progress.cancel();
+        }
+    }
+
+    fn get_paste_options(&self) -> PasteOptions {
+        PasteOptions {
+            renames: self.renames.clone(),
+            non_interactive: self.non_interactive,
+            ..Default::default()
+        }
+    }
+
+    fn paste_step(&mut self) -> Result<(), AppError> {
+        let options = self.get_paste_options();
+        let Some(progress) = self.paste_progress.as_mut() else {
+            return Ok(());
+        };
+        let Some(entry) = progress.pending.pop_front() else {
+            return Ok(());
+        };
+        progress.completed += 1;
+        let target = get_paste_target(&progress.destination, &entry, &options)?;
+        if symlink_metadata(&target).is_ok() {
+            progress.deferred.push(entry);
+            return Ok(());
+        }
+        let (infos, warnings) = handle_paste(
+            &progress.destination,
+            Some(PasteContent {
+                entries: vec![entry],
+                source: progress.source.clone(),
+            }),
+            &options,
+        )?;
+        self.infos.extend(infos);
+        self.warnings.extend(warnings);
+        Ok(())
     }
 
     fn render_table(&mut self, frame: &mut Frame, area: Rect) {
-        let column_definitions: [ColumnDef; 4] = [
+        self.refresh_stale_validity();
+
+        let abbreviate = self.abbreviate_home;
+        let renames = &self.renames;
+        let render_path = move |path: &Path| {
+            if abbreviate {
+                abbreviate_home(path)
+            } else {
+                path.to_string_lossy().into_owned()
+            }
+        };
+
+        let mut column_definitions: Vec<ColumnDef> = vec![
             (
                 "Selected",
                 Constraint::Length(SELECTED_WIDTH),
@@ -147,17 +513,59 @@ impl Tui {
             (
                 "Accessed",
                 Constraint::Length(TIMESTAMP_WIDTH),
-                Box::new(|_, entry| {
-                    let local_datetime: DateTime<Local> = entry.timestamp.into();
-                    local_datetime.format("%a, %b %d %Y, %H:%M:%S").to_string()
-                }),
+                Box::new(|_, entry| format_timestamp(entry.timestamp)),
+            ),
+            (
+                "Size",
+                Constraint::Length(SIZE_WIDTH),
+                Box::new(|_, entry| format_entry_size(entry)),
             ),
             (
                 "Path",
-                Constraint::Min(0),
-                Box::new(|_, entry| entry.path.to_string_lossy().into_owned()),
+                Constraint::Fill(1),
+                Box::new(move |_, entry| match renames.get(&entry.id) {
+                    Some(name) => format!("{} -> {}", render_path(&entry.path), name),
+                    None => render_path(&entry.path),
+                }),
             ),
         ];
+        let duplicates = get_duplicate_flags(&self.entries);
+        if duplicates.iter().any(|duplicate| *duplicate) {
+            column_definitions.insert(
+                1,
+                (
+                    "Dup",
+                    Constraint::Length(DUPLICATE_WIDTH),
+                    Box::new(move |index, _| {
+                        if duplicates[index] {
+                            "dup".to_string()
+                        } else {
+                            String::new()
+                        }
+                    }),
+                ),
+            );
+        }
+        if self.entries.iter().any(|entry| entry.note.is_some()) {
+            column_definitions.push((
+                "Note",
+                Constraint::Length(NOTE_WIDTH),
+                Box::new(|_, entry| entry.note.clone().unwrap_or_default()),
+            ));
+        }
+        if self.mode == RecordType::History {
+            column_definitions.push((
+                "Destination",
+                Constraint::Fill(1),
+                Box::new(move |_, entry| {
+                    entry
+                        .destination
+                        .as_deref()
+                        .map(render_path)
+                        .unwrap_or_default()
+                }),
+            ));
+        }
 
         let header = column_definitions
             .iter()
@@ -176,11 +584,27 @@ impl Tui {
             .map(|(_, constraint, _)| *constraint)
             .collect();
 
-        let rows = self.entries.iter().enumerate().map(|(index, entry)| {
-            let valid = get_metadata(&entry.path).is_ok();
-            self.invalid[index] = !valid;
+        let display_rows = get_display_rows(&self.entries, self.group_by);
+        let mut display_state = self.table_state;
+        display_state.select(
+            self.table_state
+                .selected()
+                .and_then(|selected| get_display_index(&display_rows, selected)),
+        );
 
-            let style = if !valid {
+        let rows = display_rows.iter().map(|row| {
+            let index = match row {
+                DisplayRow::Header(operation) => {
+                    return Row::new([Cell::from(operation.to_string())]).style(
+                        Style::default()
+                            .fg(NEUTRAL.c400)
+                            .add_modifier(Modifier::BOLD),
+                    );
+                }
+                DisplayRow::Entry(index) => *index,
+            };
+            let entry = &self.entries[index];
+            let style = if self.invalid[index] {
                 Style::default().fg(NEUTRAL.c500).crossed_out()
             } else if self.marked[index] {
                 Style::default().fg(TEAL.c300)
@@ -200,12 +624,64 @@ impl Tui {
                     .borders(Borders::ALL)
                     .title_top(format!("File Clipper - {}", self.mode))
                     .title_bottom(
-                        Line::from(if self.mode == RecordType::Clipboard {
-                            CLIPBOARD_HELPER_TEXT
-                        } else {
-                            HISTORY_HELPER_TEXT
-                        })
-                        .centered(),
+                        match (
+                            &self.paste_progress,
+                            &self.destination_prompt,
+                            &self.rename_prompt,
+                        ) {
+                            (Some(progress), _, _) => Line::from(format!(
+                                "Pasting {}/{}: {} ({})",
+                                progress.completed + 1,
+                                progress.total,
+                                progress
+                                    .pending
+                                    .front()
+                                    .map(|entry| render_path(&entry.path))
+                                    .unwrap_or_default(),
+                                PASTE_HELPER_TEXT
+                            )),
+                            (None, Some(prompt), _) => Line::from(format!(
+                                "Paste to: {}_ ({})",
+                                prompt.input,
+                                prompt.message.as_deref().unwrap_or(DESTINATION_HELPER_TEXT)
+                            )),
+                            (None, None, Some(prompt)) => Line::from(format!(
+                                "Paste as: {}_ ({})",
+                                prompt.input,
+                                prompt.message.as_deref().unwrap_or(DESTINATION_HELPER_TEXT)
+                            )),
+                            (None, None, None) if self.pending_remove.is_some() => {
+                                Line::from(format!(
+                                    "Remove {} from the clipboard? ({})",
+                                    self.pending_remove
+                                        .and_then(|id| {
+                                            self.entries.iter().find(|entry| entry.id == id)
+                                        })
+                                        .map(|entry| render_path(&entry.path))
+                                        .unwrap_or_default(),
+                                    REMOVE_HELPER_TEXT
+                                ))
+                                .centered()
+                            }
+                            (None, None, None) => match self.auto_removed {
+                                Some((count, removed_at))
+                                    if removed_at.elapsed()
+                                        < Duration::from_millis(NOTE_DURATION) =>
+                                {
+                                    Line::from(format!(
+                                        "Removed {} entries whose files disappeared",
+                                        count
+                                    ))
+                                    .centered()
+                                }
+                                _ => Line::from(if self.mode == RecordType::Clipboard {
+                                    CLIPBOARD_HELPER_TEXT
+                                } else {
+                                    HISTORY_HELPER_TEXT
+                                })
+                                .centered(),
+                            },
+                        },
                     ),
             )
             .header(header)
@@ -216,7 +692,8 @@ impl Tui {
             .direction(Direction::Horizontal)
             .constraints([Constraint::Min(0), Constraint::Length(1)])
             .split(area);
-        frame.render_stateful_widget(table, chunks[0], &mut self.table_state);
+        frame.render_stateful_widget(table, chunks[0], &mut display_state);
+        *self.table_state.offset_mut() = display_state.offset();
     }
 
     fn render_scrollbar(&mut self, frame: &mut Frame, area: Rect) {
@@ -233,6 +710,16 @@ impl Tui {
     }
 
     fn handle_keypress(&mut self, key: KeyEvent) -> Result<(), AppError> {
+        if self.pending_remove.is_some() {
+            return self.handle_remove_keypress(key);
+        }
+        if self.destination_prompt.is_some() {
+            return self.handle_destination_keypress(key);
+        }
+        if self.rename_prompt.is_some() {
+            self.handle_rename_keypress(key);
+            return Ok(());
+        }
         match key {
             KeyEvent {
                 code:
@@ -253,6 +740,14 @@ impl Tui {
                 self.mark_all();
                 Ok(())
             }
+            KeyEvent {
+                code: KeyCode::Char('J'),
+                ..
+            } => self.reorder(true),
+            KeyEvent {
+                code: KeyCode::Char('K'),
+                ..
+            } => self.reorder(false),
             KeyEvent {
                 code: KeyCode::Char('j') | KeyCode::Down,
                 ..
@@ -313,6 +808,41 @@ impl Tui {
                 self.bottom();
                 Ok(())
             }
+            KeyEvent {
+                code: KeyCode::Char(']'),
+                ..
+            } => {
+                self.jump_to_invalid(true);
+                Ok(())
+            }
+            KeyEvent {
+                code: KeyCode::Char('['),
+                ..
+            } => {
+                self.jump_to_invalid(false);
+                Ok(())
+            }
+            KeyEvent {
+                code: KeyCode::Char('R'),
+                ..
+            } => {
+                self.refresh_validity();
+                Ok(())
+            }
+            KeyEvent {
+                code: KeyCode::Char('r'),
+                ..
+            } => {
+                self.open_rename_prompt();
+                Ok(())
+            }
+            KeyEvent {
+                code: KeyCode::Char('~'),
+                ..
+            } => {
+                self.abbreviate_home = !self.abbreviate_home;
+                Ok(())
+            }
             KeyEvent {
                 code: KeyCode::Char('x') | KeyCode::Char('d'),
                 ..
@@ -321,6 +851,13 @@ impl Tui {
                 code: KeyCode::Char('p') | KeyCode::Enter,
                 ..
             } => self.paste(),
+            KeyEvent {
+                code: KeyCode::Char('P'),
+                ..
+            } => {
+                self.open_destination_prompt();
+                Ok(())
+            }
             KeyEvent {
                 code: KeyCode::Char('q'),
                 ..
@@ -337,6 +874,130 @@ impl Tui {
         }
     }
 
+    fn handle_destination_keypress(&mut self, key: KeyEvent) -> Result<(), AppError> {
+        let Some(prompt) = self.destination_prompt.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Char(c) => {
+                prompt.input.push(c);
+                prompt.message = None;
+                prompt.confirm_create = false;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+                prompt.message = None;
+                prompt.confirm_create = false;
+            }
+            KeyCode::Esc => self.destination_prompt = None,
+            KeyCode::Enter => return self.confirm_destination(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_destination_prompt(&mut self) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let input = current_dir()
+            .map(|cwd| cwd.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.destination_prompt = Some(DestinationPrompt {
+            input,
+            message: None,
+            confirm_create: false,
+        });
+    }
+
+    fn confirm_destination(&mut self) -> Result<(), AppError> {
+        let Some(prompt) = self.destination_prompt.as_mut() else {
+            return Ok(());
+        };
+        let destination = expand_tilde(&prompt.input);
+        if destination.exists() && !destination.is_dir() {
+            prompt.message = Some("Not a directory".to_string());
+            return Ok(());
+        }
+        if !destination.exists() {
+            if !prompt.confirm_create {
+                prompt.confirm_create = true;
+                prompt.message = Some("Does not exist; press Enter to create it".to_string());
+                return Ok(());
+            }
+            create_dir_all(&destination).map_err(|_| FileError::CreateDir {
+                path: destination.clone(),
+            })?;
+        }
+        self.destination_prompt = None;
+        self.destination = Some(destination);
+        self.paste()
+    }
+
+    fn handle_rename_keypress(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.rename_prompt.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) => {
+                prompt.input.push(c);
+                prompt.message = None;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+                prompt.message = None;
+            }
+            KeyCode::Esc => self.rename_prompt = None,
+            KeyCode::Enter => self.confirm_rename(),
+            _ => {}
+        }
+    }
+
+    fn open_rename_prompt(&mut self) {
+        let Some(entry) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.entries.get(selected))
+        else {
+            return;
+        };
+        let input = match self.renames.get(&entry.id) {
+            Some(name) => name.clone(),
+            None => entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        self.rename_prompt = Some(RenamePrompt {
+            id: entry.id,
+            input,
+            message: None,
+        });
+    }
+
+    // Confirming the original file name clears the override instead of storing it.
+    fn confirm_rename(&mut self) {
+        let Some(prompt) = self.rename_prompt.as_mut() else {
+            return;
+        };
+        if let Err(message) = validate_rename(&prompt.input) {
+            prompt.message = Some(message.to_string());
+            return;
+        }
+        let original = self
+            .entries
+            .iter()
+            .find(|entry| entry.id == prompt.id)
+            .and_then(|entry| entry.path.file_name());
+        if original == Some(OsStr::new(&prompt.input)) {
+            self.renames.remove(&prompt.id);
+        } else {
+            self.renames.insert(prompt.id, prompt.input.clone());
+        }
+        self.rename_prompt = None;
+    }
+
     fn next(&mut self, num_lines: u16) {
         let num_lines = num_lines as usize;
         let i = match self.table_state.selected() {
@@ -344,13 +1005,14 @@ impl Tui {
                 if i < self.entries.len().saturating_sub(num_lines) {
                     i + num_lines
                 } else {
-                    self.entries.len() - 1
+                    self.entries.len().saturating_sub(1)
                 }
             }
             None => 0,
         };
         self.table_state.select(Some(i));
         self.scroll_state = self.scroll_state.position(i);
+        self.check_validity_at(i);
     }
 
     fn previous(&mut self, num_lines: u16) {
@@ -361,21 +1023,59 @@ impl Tui {
         };
         self.table_state.select(Some(i));
         self.scroll_state = self.scroll_state.position(i);
+        self.check_validity_at(i);
     }
 
     fn top(&mut self) {
         self.table_state.select(Some(0));
         self.scroll_state = self.scroll_state.position(0);
+        self.check_validity_at(0);
     }
 
     fn bottom(&mut self) {
-        self.table_state.select(Some(self.entries.len() - 1));
-        self.scroll_state = self.scroll_state.position(self.entries.len() - 1);
+        let last = self.entries.len().saturating_sub(1);
+        self.table_state.select(Some(last));
+        self.scroll_state = self.scroll_state.position(last);
+        self.check_validity_at(last);
+    }
+
+    fn jump_to_invalid(&mut self, forward: bool) {
+        if let Some(index) = find_invalid(&self.invalid, self.table_state.selected(), forward) {
+            self.table_state.select(Some(index));
+            self.scroll_state = self.scroll_state.position(index);
+            self.check_validity_at(index);
+        }
+    }
+
+    fn check_validity_at(&mut self, index: usize) {
+        if let Some(entry) = self.entries.get(index) {
+            self.invalid[index] = get_metadata(&entry.path, false).is_err();
+            self.last_checked[index] = Some(Instant::now());
+        }
+    }
+
+    fn refresh_validity(&mut self) {
+        for index in 0..self.entries.len() {
+            self.check_validity_at(index);
+        }
+    }
+
+    fn refresh_stale_validity(&mut self) {
+        let ttl = Duration::from_millis(VALIDITY_TTL);
+        for index in 0..self.entries.len() {
+            let stale = match self.last_checked[index] {
+                Some(checked) => checked.elapsed() >= ttl,
+                None => true,
+            };
+            if stale {
+                self.check_validity_at(index);
+            }
+        }
     }
 
     fn mark(&mut self) {
         if let Some(selected) = self.table_state.selected()
-            && !self.invalid[selected]
+            && self.invalid.get(selected) == Some(&false)
         {
             self.marked[selected] = !self.marked[selected];
         }
@@ -389,21 +1089,168 @@ impl Tui {
         }
     }
 
+    fn select_all_valid(&mut self) {
+        self.refresh_validity();
+        self.marked = self.invalid.iter().map(|invalid| !invalid).collect();
+    }
+
     fn remove(&mut self) -> Result<(), AppError> {
-        if self.mode == RecordType::Clipboard {
-            if let Some(selected) = self.table_state.selected() {
-                match handle_remove(self.entries[selected].id) {
-                    Err(error) => return Err(error),
-                    Ok(warnings) => {
-                        self.warnings.extend(warnings);
-                    }
-                }
-            }
-            self.entries = read_entries(&self.mode)?;
+        if self.mode != RecordType::Clipboard {
+            return Ok(());
+        }
+        let Some(id) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.entries.get(selected))
+            .map(|entry| entry.id)
+        else {
+            return Ok(());
+        };
+        if self.confirm_remove {
+            self.pending_remove = Some(id);
+            return Ok(());
         }
+        self.remove_entry(id)
+    }
+
+    // Only `y` confirms; any other key keeps the entry, so a stray keypress is never destructive.
+    fn handle_remove_keypress(&mut self, key: KeyEvent) -> Result<(), AppError> {
+        let Some(id) = self.pending_remove.take() else {
+            return Ok(());
+        };
+        if key.code == KeyCode::Char('y') {
+            self.remove_entry(id)?;
+        }
+        Ok(())
+    }
+
+    fn remove_entry(&mut self, id: Uuid) -> Result<(), AppError> {
+        self.warnings.extend(handle_remove(id)?);
+        self.reconcile_entries(group_entries(read_entries(&self.mode)?, self.group_by));
         Ok(())
     }
 
+    // Relies on the validity flags refreshed while rendering, so a vanished file is dropped within
+    // one validity TTL. Only the clipboard is pruned; the history keeps its record of old pastes.
+    fn remove_invalid_entries(&mut self) -> Result<(), AppError> {
+        if self.mode != RecordType::Clipboard {
+            return Ok(());
+        }
+        let invalid_ids: HashSet<Uuid> = self
+            .entries
+            .iter()
+            .zip(&self.invalid)
+            .filter(|(_, invalid)| **invalid)
+            .map(|(entry, _)| entry.id)
+            .collect();
+        if invalid_ids.is_empty() {
+            return Ok(());
+        }
+        let _lock = lock_records()?;
+        let clipboard_entries: Vec<RecordEntry> = read_clipboard()?
+            .unwrap_or(Vec::new())
+            .into_iter()
+            .filter(|entry| !invalid_ids.contains(&entry.id))
+            .collect();
+        write_clipboard(&clipboard_entries)?;
+        self.records_modified = get_records_modified(&self.mode)?;
+        self.reconcile_entries(group_entries(
+            filter_entries(clipboard_entries, &self.operation),
+            self.group_by,
+        ));
+        self.auto_removed = Some((invalid_ids.len(), Instant::now()));
+        Ok(())
+    }
+
+    fn reorder(&mut self, down: bool) -> Result<(), AppError> {
+        if self.mode != RecordType::Clipboard {
+            return Ok(());
+        }
+        let Some((id, neighbor_id)) = self.move_selected(down) else {
+            return Ok(());
+        };
+        // The table may be filtered by operation, so the move is replayed on the full clipboard.
+        let _lock = lock_records()?;
+        let mut clipboard_entries = read_clipboard()?.unwrap_or(Vec::new());
+        let position = |id| clipboard_entries.iter().position(|entry| entry.id == id);
+        if let (Some(from), Some(to)) = (position(id), position(neighbor_id)) {
+            move_entry(&mut clipboard_entries, from, to);
+            write_clipboard(&clipboard_entries)?;
+            self.records_modified = get_records_modified(&self.mode)?;
+        }
+        Ok(())
+    }
+
+    fn move_selected(&mut self, down: bool) -> Option<(Uuid, Uuid)> {
+        let selected = self.table_state.selected()?;
+        let target = if down {
+            Some(selected + 1).filter(|target| *target < self.entries.len())
+        } else {
+            selected.checked_sub(1)
+        }
+        .filter(|target| {
+            self.group_by.is_none()
+                || self.entries[*target].operation == self.entries[selected].operation
+        })?;
+        let ids = (self.entries[selected].id, self.entries[target].id);
+        move_entry(&mut self.entries, selected, target);
+        move_entry(&mut self.marked, selected, target);
+        move_entry(&mut self.invalid, selected, target);
+        move_entry(&mut self.last_checked, selected, target);
+        self.table_state.select(Some(target));
+        self.scroll_state = self.scroll_state.position(target);
+        Some(ids)
+    }
+
+    fn reconcile_entries(&mut self, entries: Vec<RecordEntry>) {
+        let previous_state: HashMap<_, _> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                (
+                    entry.id,
+                    (
+                        self.marked[index],
+                        self.invalid[index],
+                        self.last_checked[index],
+                    ),
+                )
+            })
+            .collect();
+        let selected_id = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.entries.get(selected))
+            .map(|entry| entry.id);
+
+        self.marked = Vec::with_capacity(entries.len());
+        self.invalid = Vec::with_capacity(entries.len());
+        self.last_checked = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let (marked, invalid, last_checked) = previous_state
+                .get(&entry.id)
+                .copied()
+                .unwrap_or((false, false, None));
+            self.marked.push(marked);
+            self.invalid.push(invalid);
+            self.last_checked.push(last_checked);
+        }
+
+        let selected = match selected_id.and_then(|id| entries.iter().position(|e| e.id == id)) {
+            Some(index) => index,
+            None => self
+                .table_state
+                .selected()
+                .unwrap_or(0)
+                .min(entries.len().saturating_sub(1)),
+        };
+        self.entries = entries;
+        self.table_state.select(Some(selected));
+        self.scroll_state =
+            ScrollbarState::new(self.entries.len().saturating_sub(1)).position(selected);
+    }
+
     fn paste(&mut self) -> Result<(), AppError> {
         let mut marked_entries: Vec<RecordEntry> = self
             .entries
@@ -418,8 +1265,12 @@ impl Tui {
             .collect();
         if marked_entries.is_empty()
             && let Some(selected) = self.table_state.selected()
+            && let Some(entry) = self.entries.get(selected)
         {
-            marked_entries.push(self.entries[selected].clone());
+            marked_entries.push(entry.clone());
+        }
+        if marked_entries.is_empty() {
+            return Ok(());
         }
         let paste_content = PasteContent {
             entries: marked_entries,
@@ -437,7 +1288,16 @@ impl Tui {
 
 #[cfg(test)]
 mod tests {
-    use crate::test_helpers::create_test_tui;
+    use super::*;
+    use crate::{
+        records::{read_history, write_clipboard},
+        test_helpers::{
+            create_mock_record_entry, create_test_file, create_test_tui, get_test_entry,
+            setup_test_env,
+        },
+    };
+    use serial_test::serial;
+    use tempfile::tempdir;
 
     #[test]
     fn test_tui_navigation_next() {
@@ -628,4 +1488,557 @@ mod tests {
         tui.next(100);
         assert_eq!(tui.table_state.selected(), Some(99));
     }
+
+    #[test]
+    fn test_tui_refresh_validity() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("exists.txt");
+        create_test_file(&file_path, "content");
+
+        let mut tui = create_test_tui(2);
+        tui.entries[0].path = file_path;
+
+        tui.refresh_validity();
+        assert!(!tui.invalid[0]);
+        assert!(tui.invalid[1]);
+        assert!(tui.last_checked.iter().all(|checked| checked.is_some()));
+    }
+
+    #[test]
+    fn test_tui_select_all_valid_skips_invalid() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("exists.txt");
+        create_test_file(&file_path, "content");
+
+        let mut tui = create_test_tui(2);
+        tui.entries[0].path = file_path;
+
+        tui.select_all_valid();
+        assert_eq!(tui.marked, vec![true, false]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_tui_remove_invalid_entries() {
+        let env = setup_test_env();
+        let kept = env.source_dir.join("kept.txt");
+        create_test_file(&kept, "content");
+        let mut tui = create_test_tui(3);
+        tui.entries[0].path = kept.clone();
+        tui.entries[2].path = kept;
+        write_clipboard(&tui.entries).unwrap();
+        let vanished_id = tui.entries[1].id;
+        tui.watch_validity = true;
+        tui.marked = vec![false, false, true];
+
+        tui.refresh_validity();
+        tui.remove_invalid_entries().unwrap();
+
+        assert_eq!(tui.entries.len(), 2);
+        assert!(tui.entries.iter().all(|entry| entry.id != vanished_id));
+        assert_eq!(tui.marked, vec![false, true]);
+        assert!(matches!(tui.auto_removed, Some((1, _))));
+        let clipboard = read_clipboard().unwrap().unwrap();
+        assert_eq!(clipboard.len(), 2);
+
+        tui.auto_removed = None;
+        tui.remove_invalid_entries().unwrap();
+        assert!(tui.auto_removed.is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_tui_remove_needs_confirmation() {
+        let _env = setup_test_env();
+        let mut tui = create_test_tui(3);
+        write_clipboard(&tui.entries).unwrap();
+        tui.confirm_remove = true;
+        let key = |code| KeyEvent::from(code);
+
+        tui.handle_keypress(key(KeyCode::Char('x'))).unwrap();
+        assert_eq!(tui.pending_remove, Some(tui.entries[0].id));
+        tui.handle_keypress(key(KeyCode::Char('n'))).unwrap();
+        assert!(tui.pending_remove.is_none());
+        assert_eq!(tui.entries.len(), 3);
+
+        // A confirmation prompt swallows the next key, so `j` does not move the selection.
+        tui.handle_keypress(key(KeyCode::Char('d'))).unwrap();
+        tui.handle_keypress(key(KeyCode::Char('j'))).unwrap();
+        assert!(tui.pending_remove.is_none());
+        assert_eq!(tui.entries.len(), 3);
+        assert_eq!(tui.table_state.selected(), Some(0));
+
+        let removed_id = tui.entries[0].id;
+        tui.handle_keypress(key(KeyCode::Char('x'))).unwrap();
+        tui.handle_keypress(key(KeyCode::Char('y'))).unwrap();
+        assert!(tui.pending_remove.is_none());
+        assert_eq!(tui.entries.len(), 2);
+        assert!(tui.entries.iter().all(|entry| entry.id != removed_id));
+        assert_eq!(read_clipboard().unwrap().unwrap().len(), 2);
+
+        tui.confirm_remove = false;
+        tui.handle_keypress(key(KeyCode::Char('x'))).unwrap();
+        assert!(tui.pending_remove.is_none());
+        assert_eq!(tui.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_tui_refresh_stale_validity_uses_cache() {
+        let mut tui = create_test_tui(2);
+        tui.last_checked[0] = Some(Instant::now());
+
+        tui.refresh_stale_validity();
+        assert!(!tui.invalid[0]);
+        assert!(tui.invalid[1]);
+    }
+
+    #[test]
+    fn test_tui_selection_change_rechecks_row() {
+        let mut tui = create_test_tui(3);
+        tui.last_checked = vec![Some(Instant::now()); 3];
+
+        tui.next(1);
+        assert!(!tui.invalid[0]);
+        assert!(tui.invalid[1]);
+        assert!(!tui.invalid[2]);
+    }
+
+    #[test]
+    fn test_tui_reconcile_entries_keeps_state_by_id() {
+        let mut tui = create_test_tui(3);
+        tui.marked[1] = true;
+        tui.invalid[2] = true;
+        tui.table_state.select(Some(1));
+
+        let mut entries = tui.entries.clone();
+        let removed = entries.remove(0);
+        entries.insert(0, create_test_tui(1).entries[0].clone());
+        entries.swap(1, 2);
+        tui.reconcile_entries(entries.clone());
+
+        assert_eq!(tui.entries.len(), 3);
+        assert!(tui.entries.iter().all(|entry| entry.id != removed.id));
+        assert_eq!(tui.marked, vec![false, false, true]);
+        assert_eq!(tui.invalid, vec![false, true, false]);
+        assert_eq!(tui.last_checked.len(), 3);
+        assert_eq!(tui.table_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn test_tui_reconcile_entries_clamps_selection() {
+        let mut tui = create_test_tui(5);
+        tui.table_state.select(Some(4));
+
+        let entries = tui.entries[..2].to_vec();
+        tui.reconcile_entries(entries);
+
+        assert_eq!(tui.marked.len(), 2);
+        assert_eq!(tui.table_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn test_tui_reconcile_entries_to_empty() {
+        let mut tui = create_test_tui(2);
+        tui.reconcile_entries(Vec::new());
+
+        assert!(tui.entries.is_empty());
+        tui.mark();
+        tui.bottom();
+        tui.paste().unwrap();
+        assert!(tui.paste_content.is_none());
+    }
+
+    #[test]
+    fn test_filter_entries_by_operation() {
+        let entries = vec![
+            create_mock_record_entry(None, Some(Operation::Copy), None, None, None),
+            create_mock_record_entry(None, Some(Operation::Cut), None, None, None),
+            create_mock_record_entry(None, Some(Operation::Link), None, None, None),
+            create_mock_record_entry(None, Some(Operation::Cut), None, None, None),
+        ];
+
+        let filtered = filter_entries(entries.clone(), &Some(Operation::Cut));
+        assert_eq!(filtered.len(), 2);
+        assert!(
+            filtered
+                .iter()
+                .all(|entry| entry.operation == Operation::Cut)
+        );
+        assert_eq!(filtered[0].id, entries[1].id);
+        assert_eq!(filtered[1].id, entries[3].id);
+
+        assert_eq!(filter_entries(entries.clone(), &None).len(), 4);
+    }
+
+    #[test]
+    fn test_tui_status_line() {
+        let mut tui = create_test_tui(4);
+        tui.marked[0] = true;
+        tui.marked[2] = true;
+        tui.invalid[3] = true;
+        assert_eq!(
+            tui.get_status_line(),
+            "Records: clipboard; Entries: 4; Marked: 2; Invalid: 1"
+        );
+
+        tui.mode = RecordType::History;
+        tui.marked = vec![false; 4];
+        assert_eq!(
+            tui.get_status_line(),
+            "Records: history; Entries: 4; Marked: 0; Invalid: 1"
+        );
+    }
+
+    #[test]
+    fn test_find_invalid() {
+        let invalid = [false, true, false, false, true, false];
+        assert_eq!(find_invalid(&invalid, Some(0), true), Some(1));
+        assert_eq!(find_invalid(&invalid, Some(1), true), Some(4));
+        assert_eq!(find_invalid(&invalid, Some(4), true), Some(1));
+        assert_eq!(find_invalid(&invalid, Some(5), false), Some(4));
+        assert_eq!(find_invalid(&invalid, Some(4), false), Some(1));
+        assert_eq!(find_invalid(&invalid, Some(1), false), Some(4));
+        assert_eq!(find_invalid(&invalid, None, true), Some(1));
+        assert_eq!(find_invalid(&invalid, None, false), Some(4));
+        assert_eq!(find_invalid(&[false, true], Some(1), true), Some(1));
+        assert_eq!(find_invalid(&[false, false], Some(0), true), None);
+        assert_eq!(find_invalid(&[], None, false), None);
+    }
+
+    #[test]
+    fn test_get_display_rows() {
+        let entries = group_entries(
+            vec![
+                create_mock_record_entry(None, Some(Operation::Link), None, None, None),
+                create_mock_record_entry(None, Some(Operation::Copy), None, None, None),
+                create_mock_record_entry(None, Some(Operation::Cut), None, None, None),
+                create_mock_record_entry(None, Some(Operation::Copy), None, None, None),
+            ],
+            Some(GroupBy::Operation),
+        );
+        let rows = get_display_rows(&entries, Some(GroupBy::Operation));
+        assert_eq!(
+            rows,
+            vec![
+                DisplayRow::Header(Operation::Copy),
+                DisplayRow::Entry(0),
+                DisplayRow::Entry(1),
+                DisplayRow::Header(Operation::Cut),
+                DisplayRow::Entry(2),
+                DisplayRow::Header(Operation::Link),
+                DisplayRow::Entry(3),
+            ]
+        );
+        assert_eq!(get_display_index(&rows, 0), Some(1));
+        assert_eq!(get_display_index(&rows, 2), Some(4));
+        assert_eq!(get_display_index(&rows, 3), Some(6));
+        assert_eq!(get_display_index(&rows, 4), None);
+
+        let ungrouped = get_display_rows(&entries, None);
+        assert_eq!(get_display_index(&ungrouped, 3), Some(3));
+        assert!(get_display_rows(&[], Some(GroupBy::Operation)).is_empty());
+    }
+
+    #[test]
+    fn test_group_entries_keeps_order_within_group() {
+        let entries = vec![
+            create_mock_record_entry(None, Some(Operation::Cut), None, None, None),
+            create_mock_record_entry(None, Some(Operation::Copy), None, None, None),
+            create_mock_record_entry(None, Some(Operation::Cut), None, None, None),
+        ];
+        let grouped = group_entries(entries.clone(), Some(GroupBy::Operation));
+        let ids: Vec<Uuid> = grouped.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![entries[1].id, entries[0].id, entries[2].id]);
+        assert_eq!(group_entries(entries.clone(), None), entries);
+    }
+
+    #[test]
+    fn test_tui_move_selected_stays_within_group() {
+        let mut tui = create_test_tui(3);
+        tui.entries[2].operation = Operation::Cut;
+        tui.group_by = Some(GroupBy::Operation);
+        tui.next(1);
+
+        assert!(tui.move_selected(true).is_none());
+        assert_eq!(tui.table_state.selected(), Some(1));
+        assert!(tui.move_selected(false).is_some());
+        assert_eq!(tui.table_state.selected(), Some(0));
+    }
+
+    fn press(tui: &mut Tui, code: KeyCode) {
+        tui.handle_keypress(KeyEvent::from(code)).unwrap();
+    }
+
+    fn type_destination(tui: &mut Tui, destination: &str) {
+        press(tui, KeyCode::Char('P'));
+        tui.destination_prompt.as_mut().unwrap().input.clear();
+        for c in destination.chars() {
+            press(tui, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_tui_destination_prompt_existing_dir() {
+        let dir = tempdir().unwrap();
+        let mut tui = create_test_tui(2);
+
+        type_destination(&mut tui, &format!("{}x", dir.path().display()));
+        press(&mut tui, KeyCode::Backspace);
+        press(&mut tui, KeyCode::Enter);
+
+        assert!(tui.destination_prompt.is_none());
+        assert_eq!(tui.destination, Some(dir.path().to_path_buf()));
+        assert!(tui.paste_content.is_some());
+        assert!(tui.should_exit);
+    }
+
+    #[test]
+    fn test_tui_destination_prompt_creates_missing_dir() {
+        let dir = tempdir().unwrap();
+        let destination = dir.path().join("new");
+        let mut tui = create_test_tui(1);
+
+        type_destination(&mut tui, &destination.to_string_lossy());
+        press(&mut tui, KeyCode::Enter);
+        assert!(tui.destination_prompt.as_ref().unwrap().confirm_create);
+        assert!(!destination.exists());
+
+        press(&mut tui, KeyCode::Enter);
+        assert!(destination.is_dir());
+        assert_eq!(tui.destination, Some(destination));
+        assert!(tui.paste_content.is_some());
+    }
+
+    #[test]
+    fn test_tui_destination_prompt_cancel() {
+        let mut tui = create_test_tui(1);
+
+        type_destination(&mut tui, "/tmp");
+        press(&mut tui, KeyCode::Char('q'));
+        assert!(!tui.should_exit);
+        press(&mut tui, KeyCode::Esc);
+
+        assert!(tui.destination_prompt.is_none());
+        assert!(tui.destination.is_none());
+        assert!(tui.paste_content.is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_tui_paste_cancel_keeps_completed_entries() {
+        let env = setup_test_env();
+        let entries: Vec<RecordEntry> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| {
+                let path = env.source_dir.join(name);
+                create_test_file(&path, name);
+                get_test_entry(&path, Operation::Copy)
+            })
+            .collect();
+        write_clipboard(&entries).unwrap();
+
+        let mut tui = create_test_tui(0);
+        tui.paste_progress = Some(PasteProgress::new(
+            PasteContent {
+                entries: entries.clone(),
+                source: RecordType::Clipboard,
+            },
+            env.dest_dir.clone(),
+        ));
+        tui.paste_step().unwrap();
+        tui.handle_paste_keypress(KeyEvent::from(KeyCode::Esc));
+        tui.paste_step().unwrap();
+
+        let progress = tui.paste_progress.as_ref().unwrap();
+        assert!(progress.is_done());
+        assert_eq!(progress.completed, 1);
+        assert_eq!(tui.infos.len(), 1);
+        assert!(env.dest_dir.join("a.txt").exists());
+        assert!(!env.dest_dir.join("b.txt").exists());
+
+        let clipboard = read_clipboard().unwrap().unwrap();
+        let remaining: Vec<Uuid> = clipboard.iter().map(|entry| entry.id).collect();
+        assert_eq!(remaining, vec![entries[1].id, entries[2].id]);
+        assert_eq!(read_history().unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_tui_paste_step_defers_collisions() {
+        let env = setup_test_env();
+        let path = env.source_dir.join("a.txt");
+        create_test_file(&path, "new");
+        create_test_file(&env.dest_dir.join("a.txt"), "existing");
+        let entry = get_test_entry(&path, Operation::Copy);
+
+        let mut tui = create_test_tui(0);
+        tui.paste_progress = Some(PasteProgress::new(
+            PasteContent {
+                entries: vec![entry.clone()],
+                source: RecordType::Clipboard,
+            },
+            env.dest_dir.clone(),
+        ));
+        tui.paste_step().unwrap();
+
+        let progress = tui.paste_progress.as_ref().unwrap();
+        assert!(progress.is_done());
+        assert_eq!(progress.deferred, vec![entry]);
+        assert!(tui.infos.is_empty());
+    }
+
+    #[test]
+    fn test_validate_rename() {
+        assert!(validate_rename("report.txt").is_ok());
+        assert!(validate_rename("").is_err());
+        assert!(validate_rename("a/b").is_err());
+        assert!(validate_rename("/").is_err());
+        assert!(validate_rename("..").is_err());
+    }
+
+    #[test]
+    fn test_tui_rename_prompt() {
+        let mut tui = create_test_tui(2);
+        tui.next(1);
+        let id = tui.entries[1].id;
+
+        press(&mut tui, KeyCode::Char('r'));
+        assert_eq!(tui.rename_prompt.as_ref().unwrap().input, "1");
+        press(&mut tui, KeyCode::Backspace);
+        press(&mut tui, KeyCode::Enter);
+        assert!(tui.rename_prompt.as_ref().unwrap().message.is_some());
+        for c in "x/y".chars() {
+            press(&mut tui, KeyCode::Char(c));
+        }
+        press(&mut tui, KeyCode::Enter);
+        assert!(tui.rename_prompt.is_some());
+        assert!(tui.renames.is_empty());
+
+        press(&mut tui, KeyCode::Backspace);
+        press(&mut tui, KeyCode::Backspace);
+        press(&mut tui, KeyCode::Enter);
+        assert!(tui.rename_prompt.is_none());
+        assert_eq!(tui.renames.get(&id).map(String::as_str), Some("x"));
+
+        press(&mut tui, KeyCode::Char('r'));
+        assert_eq!(tui.rename_prompt.as_ref().unwrap().input, "x");
+        press(&mut tui, KeyCode::Backspace);
+        press(&mut tui, KeyCode::Char('1'));
+        press(&mut tui, KeyCode::Enter);
+        assert!(tui.renames.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_tui_paste_step_applies_rename() {
+        let env = setup_test_env();
+        let path = env.source_dir.join("a.txt");
+        create_test_file(&path, "a");
+        create_test_file(&env.dest_dir.join("a.txt"), "existing");
+        let entry = get_test_entry(&path, Operation::Copy);
+        write_clipboard(std::slice::from_ref(&entry)).unwrap();
+
+        let mut tui = create_test_tui(0);
+        tui.renames.insert(entry.id, "renamed.txt".to_string());
+        tui.paste_progress = Some(PasteProgress::new(
+            PasteContent {
+                entries: vec![entry],
+                source: RecordType::Clipboard,
+            },
+            env.dest_dir.clone(),
+        ));
+        tui.paste_step().unwrap();
+
+        assert!(tui.paste_progress.as_ref().unwrap().deferred.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("renamed.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            std::fs::read_to_string(env.dest_dir.join("a.txt")).unwrap(),
+            "existing"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_expand_tilde() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_tilde("~"), home);
+        assert_eq!(expand_tilde("~/docs"), home.join("docs"));
+        assert_eq!(expand_tilde("/tmp/~x"), PathBuf::from("/tmp/~x"));
+        assert_eq!(expand_tilde("~other"), PathBuf::from("~other"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_abbreviate_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(abbreviate_home(&home), "~");
+        assert_eq!(abbreviate_home(&home.join("docs/a.txt")), "~/docs/a.txt");
+        assert_eq!(
+            abbreviate_home(Path::new("/elsewhere/a.txt")),
+            "/elsewhere/a.txt"
+        );
+    }
+
+    #[test]
+    fn test_tui_move_selected_keeps_parallel_vectors_aligned() {
+        let mut tui = create_test_tui(3);
+        tui.marked[0] = true;
+        tui.invalid[0] = true;
+        tui.last_checked[0] = Some(Instant::now());
+        let ids: Vec<Uuid> = tui.entries.iter().map(|entry| entry.id).collect();
+
+        assert_eq!(tui.move_selected(true), Some((ids[0], ids[1])));
+        assert_eq!(tui.table_state.selected(), Some(1));
+        assert_eq!(tui.entries[1].id, ids[0]);
+        assert_eq!(tui.marked, vec![false, true, false]);
+        assert_eq!(tui.invalid, vec![false, true, false]);
+        assert!(tui.last_checked[1].is_some());
+        assert!(tui.last_checked[0].is_none());
+
+        assert_eq!(tui.move_selected(true), Some((ids[0], ids[2])));
+        assert_eq!(tui.move_selected(true), None);
+        assert_eq!(tui.table_state.selected(), Some(2));
+        assert_eq!(tui.marked, vec![false, false, true]);
+
+        assert_eq!(tui.move_selected(false), Some((ids[0], ids[2])));
+        assert_eq!(tui.table_state.selected(), Some(1));
+        assert_eq!(tui.entries[1].id, ids[0]);
+        assert_eq!(tui.entries[2].id, ids[2]);
+    }
+
+    #[test]
+    fn test_tui_move_selected_at_top() {
+        let mut tui = create_test_tui(2);
+        assert_eq!(tui.move_selected(false), None);
+        assert_eq!(tui.table_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_get_duplicate_flags() {
+        let mut tui = create_test_tui(4);
+        tui.entries[2].path = tui.entries[0].path.clone();
+        tui.entries[3].path = tui.entries[0].path.clone();
+
+        assert_eq!(
+            get_duplicate_flags(&tui.entries),
+            vec![false, false, true, true]
+        );
+        assert!(
+            get_duplicate_flags(&create_test_tui(3).entries)
+                .iter()
+                .all(|duplicate| !duplicate)
+        );
+    }
+
+    #[test]
+    fn test_tui_toggle_abbreviate_home() {
+        let mut tui = create_test_tui(1);
+        press(&mut tui, KeyCode::Char('~'));
+        assert!(tui.abbreviate_home);
+        press(&mut tui, KeyCode::Char('~'));
+        assert!(!tui.abbreviate_home);
+    }
 }